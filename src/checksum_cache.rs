@@ -0,0 +1,196 @@
+use crate::{utils, ChecksumAlgorithm, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// A cache handed to the walker threads through Config::checksums
+pub type SharedChecksumCache = Arc<Mutex<ChecksumCache>>;
+
+// The path is only used by prune, it is stored lossily so that a file name
+// that is not UTF-8 cannot keep the cache from being saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    path: String,
+    digest: String,
+}
+
+// Digests keyed by (device, inode, size, mtime) and algorithm. A file that is
+// rewritten gets a new mtime or size and so misses the cache. The file is only
+// read when the cache is first used.
+#[derive(Debug)]
+pub struct ChecksumCache {
+    path: PathBuf,
+    entries: Option<HashMap<String, CacheEntry>>,
+    dirty: bool,
+}
+
+impl ChecksumCache {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("denarborea").join("checksums.json"))
+    }
+
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            entries: None,
+            dirty: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // A missing or unreadable cache file starts an empty cache, it is
+    // rebuilt as checksums are calculated
+    fn entries(&mut self) -> &mut HashMap<String, CacheEntry> {
+        self.entries.get_or_insert_with(|| {
+            std::fs::read_to_string(&self.path)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn len(&mut self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&mut self) -> bool {
+        self.entries().is_empty()
+    }
+
+    pub fn get(&mut self, metadata: &Metadata, algorithm: ChecksumAlgorithm) -> Option<String> {
+        let key = cache_key(metadata, algorithm)?;
+        self.entries().get(&key).map(|entry| entry.digest.clone())
+    }
+
+    pub fn insert(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        algorithm: ChecksumAlgorithm,
+        digest: String,
+    ) {
+        if let Some(key) = cache_key(metadata, algorithm) {
+            // absolute, so that prune works from any directory
+            let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+            let entry = CacheEntry {
+                path: path.to_string_lossy().into_owned(),
+                digest,
+            };
+            self.entries().insert(key, entry);
+            self.dirty = true;
+        }
+    }
+
+    // Drops entries whose file is gone or no longer has the recorded device,
+    // inode, size and mtime. Returns the number of removed entries.
+    pub fn prune(&mut self) -> usize {
+        let entries = self.entries();
+        let before = entries.len();
+
+        entries.retain(|key, entry| {
+            let Ok(metadata) = std::fs::metadata(&entry.path) else {
+                return false;
+            };
+            // the algorithm is the last component of the key
+            let algorithm = key.rsplit(':').next().unwrap_or_default();
+            key_prefix(&metadata).is_some_and(|prefix| *key == format!("{}:{}", prefix, algorithm))
+        });
+
+        let removed = before - entries.len();
+        if removed > 0 {
+            self.dirty = true;
+        }
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = Some(HashMap::new());
+        self.dirty = true;
+    }
+
+    // Writes the cache if it changed. The file is replaced atomically so that
+    // an interrupted run cannot leave a truncated cache behind.
+    pub fn save(&mut self) -> Result<()> {
+        let Some(entries) = self.entries.as_ref().filter(|_| self.dirty) else {
+            return Ok(());
+        };
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        // per process, so that concurrent runs do not write the same file
+        let temp_path = self
+            .path
+            .with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&temp_path, serde_json::to_string(entries)?)
+            .map_err(|e| Error::io(&temp_path, e))?;
+        if let Err(e) = std::fs::rename(&temp_path, &self.path) {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(Error::io(&self.path, e));
+        }
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn shared(self) -> SharedChecksumCache {
+        Arc::new(Mutex::new(self))
+    }
+}
+
+// Calculates the checksum of `path`, going through `cache` when there is one.
+// Hashing happens outside the lock so walker threads do not wait on each other.
+pub(crate) fn calculate_checksum(
+    path: &Path,
+    algorithm: ChecksumAlgorithm,
+    cache: Option<&Mutex<ChecksumCache>>,
+) -> Result<String> {
+    let Some(cache) = cache else {
+        return utils::calculate_checksum(path, algorithm);
+    };
+    let lock = || cache.lock().unwrap_or_else(|e| e.into_inner());
+
+    let metadata = std::fs::metadata(path).map_err(|e| Error::io(path, e))?;
+    if let Some(digest) = lock().get(&metadata, algorithm) {
+        return Ok(digest);
+    }
+
+    let digest = utils::calculate_checksum(path, algorithm)?;
+
+    // skip caching when the file changed while it was being read
+    let after = std::fs::metadata(path).map_err(|e| Error::io(path, e))?;
+    if key_prefix(&metadata) == key_prefix(&after) {
+        lock().insert(path, &metadata, algorithm, digest.clone());
+    }
+    Ok(digest)
+}
+
+fn cache_key(metadata: &Metadata, algorithm: ChecksumAlgorithm) -> Option<String> {
+    Some(format!("{}:{}", key_prefix(metadata)?, algorithm.name()))
+}
+
+#[cfg(unix)]
+fn key_prefix(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(format!(
+        "{}:{}:{}:{}.{:09}",
+        metadata.dev(),
+        metadata.ino(),
+        metadata.size(),
+        metadata.mtime(),
+        metadata.mtime_nsec()
+    ))
+}
+
+// Without inode numbers a key could match an unrelated file, so nothing is
+// cached
+#[cfg(not(unix))]
+fn key_prefix(_metadata: &Metadata) -> Option<String> {
+    None
+}
@@ -1,7 +1,18 @@
+use crate::{Error, SharedChecksumCache};
+use chrono::{DateTime, Utc};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+mod layer;
+
+pub use layer::{ConfigLayer, ENV_PREFIX};
+
+pub const PROJECT_CONFIG_FILE: &str = ".denarborea.toml";
 
 #[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SortBy {
     #[value(name = "name")]
     Name,
@@ -15,7 +26,75 @@ pub enum SortBy {
     Type,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    #[default]
+    #[value(name = "md5")]
+    Md5,
+    #[value(name = "sha1")]
+    Sha1,
+    #[value(name = "sha256")]
+    Sha256,
+    #[value(name = "blake3")]
+    Blake3,
+    #[value(name = "xxh3")]
+    Xxh3,
+}
+
+impl ChecksumAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Md5 => "md5",
+            ChecksumAlgorithm::Sha1 => "sha1",
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Blake3 => "blake3",
+            ChecksumAlgorithm::Xxh3 => "xxh3",
+        }
+    }
+}
+
+// How --watch reports changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    // redraw the tree with changed entries highlighted
+    #[default]
+    #[value(name = "tree")]
+    Tree,
+    // print one line per change
+    #[value(name = "delta")]
+    Delta,
+}
+
+// Timestamp used by --newer and --older
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeField {
+    #[default]
+    #[value(name = "mtime")]
+    Mtime,
+    #[value(name = "ctime")]
+    Ctime,
+    #[value(name = "atime")]
+    Atime,
+    #[value(name = "birth")]
+    Birth,
+}
+
+impl TimeField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeField::Mtime => "mtime",
+            TimeField::Ctime => "ctime",
+            TimeField::Atime => "atime",
+            TimeField::Birth => "birth",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     #[value(name = "tree")]
     Tree,
@@ -27,6 +106,8 @@ pub enum OutputFormat {
     Csv,
     #[value(name = "markdown")]
     Markdown,
+    #[value(name = "ndjson")]
+    Ndjson,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,17 +126,56 @@ pub struct Config {
     pub files_only: bool,
     pub min_size: Option<u64>,
     pub max_size: Option<u64>,
+    // relative times are resolved when the config is loaded
+    pub newer: Option<DateTime<Utc>>,
+    pub older: Option<DateTime<Utc>>,
+    pub time_field: TimeField,
     pub exclude_patterns: Vec<String>,
-    pub include_patterns: Option<String>,
+    pub include_patterns: Vec<String>,
+    // matched against the path relative to the root
+    pub regex: Option<String>,
+    pub regex_ignore_case: bool,
+    // the --where expression, parsed when the walk starts
+    pub filter_expression: Option<String>,
+    // --grep, searched in files that pass the other filters
+    pub grep: Option<String>,
+    pub grep_ignore_case: bool,
+    pub grep_fixed_strings: bool,
     pub git_ignore: bool,
     pub git_status: bool,
+    pub git_changed_only: bool,
     pub limit: Option<usize>,
     pub output_format: OutputFormat,
     pub follow_links: bool,
     pub full_path: bool,
     pub show_checksum: bool,
+    pub checksum_algorithm: ChecksumAlgorithm,
+    // number of digest characters shown in the tree view
+    pub checksum_length: usize,
+    // keep digests in the user cache dir between runs
+    pub checksum_cache: bool,
+    // the open cache, set by the caller when checksum_cache is on. Without
+    // one every checksum is calculated from the file.
+    #[serde(skip)]
+    pub checksums: Option<SharedChecksumCache>,
+    // report files with identical content instead of the full tree
+    pub duplicates: bool,
+    pub watch: Option<WatchMode>,
+    // --rev, list the tree of a git commit instead of the worktree
+    pub revision: Option<String>,
+    // --git-diff, the changes of a revision range instead of the tree. Only
+    // set from the command line.
+    #[serde(skip)]
+    pub git_diff: Option<String>,
+    // --cache, directory listings reused while their mtime is unchanged
+    pub scan_cache: bool,
     pub show_stats: bool,
     pub interactive: bool,
+    pub disk_usage: bool,
+    pub show_disk_size: bool,
+    pub threads: Option<usize>,
+    pub stream: bool,
+    pub strict: bool,
 }
 
 impl Default for Config {
@@ -75,42 +195,141 @@ impl Default for Config {
             files_only: false,
             min_size: None,
             max_size: None,
+            newer: None,
+            older: None,
+            time_field: TimeField::Mtime,
             exclude_patterns: Vec::new(),
-            include_patterns: None,
+            include_patterns: Vec::new(),
+            regex: None,
+            regex_ignore_case: false,
+            filter_expression: None,
+            grep: None,
+            grep_ignore_case: false,
+            grep_fixed_strings: false,
             git_ignore: false,
             git_status: false,
+            git_changed_only: false,
             limit: None,
             output_format: OutputFormat::Tree,
             follow_links: false,
             full_path: false,
             show_checksum: false,
+            checksum_algorithm: ChecksumAlgorithm::Md5,
+            checksum_length: 8,
+            checksum_cache: true,
+            checksums: None,
+            duplicates: false,
+            watch: None,
+            revision: None,
+            git_diff: None,
+            scan_cache: false,
             show_stats: false,
             interactive: false,
+            disk_usage: false,
+            show_disk_size: false,
+            threads: None,
+            stream: false,
+            strict: false,
         }
     }
 }
 
 impl Config {
+    // Layers, from lowest to highest priority: built-in defaults, the user
+    // config file, the nearest .denarborea.toml above `target`, the selected
+    // profile, DENARBOREA_* environment variables and finally the command line.
+    // Profiles may be defined in either file; the project file wins on clashes.
+    pub fn load(target: &Path, profile: Option<&str>, cli: ConfigLayer) -> crate::Result<Self> {
+        let mut files = Vec::new();
+
+        if let Some(path) = Self::user_config_path().filter(|path| path.exists()) {
+            files.push(ConfigLayer::from_file(&path)?);
+        }
+        if let Some(path) = Self::find_project_config(target) {
+            files.push(ConfigLayer::from_file(&path)?);
+        }
+
+        let mut profiles = BTreeMap::new();
+        for file in &mut files {
+            profiles.append(&mut file.profile);
+        }
+
+        let env_profile = std::env::var(format!("{}PROFILE", ENV_PREFIX)).ok();
+        let mut layers = files;
+        if let Some(name) = profile.or(env_profile.as_deref()) {
+            layers.push(ConfigLayer::resolve_profile(name, &profiles)?);
+        }
+        layers.push(ConfigLayer::from_env()?);
+        layers.push(cli);
+
+        let config = layers
+            .into_iter()
+            .try_fold(Config::default(), |config, layer| layer.apply(config))?;
+        config.check_conflicts()?;
+        Ok(config)
+    }
+
+    // The command line rejects these combinations itself, this catches the
+    // ones where a side comes from a config file or the environment
+    fn check_conflicts(&self) -> crate::Result<()> {
+        if self.revision.is_none() {
+            return Ok(());
+        }
+
+        let conflicts = [
+            ("stream", self.stream),
+            ("watch", self.watch.is_some()),
+            ("interactive", self.interactive),
+            ("duplicates", self.duplicates),
+            ("git_status", self.git_status),
+            ("git_changed_only", self.git_changed_only),
+            ("show_disk_size", self.show_disk_size),
+            ("scan_cache", self.scan_cache),
+        ];
+        match conflicts.iter().find(|(_, set)| *set) {
+            Some((name, _)) => Err(crate::Error::Config(format!(
+                "`revision` cannot be combined with `{}`",
+                name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn user_config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("denarborea").join("config.toml"))
+    }
+
+    pub fn find_project_config(target: &Path) -> Option<PathBuf> {
+        let start = target.canonicalize().ok()?;
+        let start = if start.is_dir() {
+            start.as_path()
+        } else {
+            start.parent()?
+        };
+
+        start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
     pub fn load_from_file() -> crate::Result<Option<Self>> {
-        if let Some(config_dir) = dirs::config_dir() {
-            let config_path = config_dir.join("denarborea").join("config.toml");
-            if config_path.exists() {
-                let content = std::fs::read_to_string(config_path)?;
-                let config: Config = toml::from_str(&content)?;
-                return Ok(Some(config));
-            }
+        match Self::user_config_path().filter(|path| path.exists()) {
+            Some(path) => Ok(Some(
+                ConfigLayer::from_file(&path)?.apply(Config::default())?,
+            )),
+            None => Ok(None),
         }
-        Ok(None)
     }
 
     pub fn save_to_file(&self) -> crate::Result<()> {
-        if let Some(config_dir) = dirs::config_dir() {
-            let tree_config_dir = config_dir.join("denarborea");
-            std::fs::create_dir_all(&tree_config_dir)?;
+        if let Some(config_path) = Self::user_config_path() {
+            if let Some(parent) = config_path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+            }
 
-            let config_path = tree_config_dir.join("config.toml");
             let content = toml::to_string_pretty(self)?;
-            std::fs::write(config_path, content)?;
+            std::fs::write(&config_path, content).map_err(|e| Error::io(&config_path, e))?;
         }
         Ok(())
     }
@@ -125,6 +344,14 @@ impl Config {
         }
     }
 
+    pub fn effective_size(&self, info: &crate::FileInfo) -> u64 {
+        if self.show_disk_size {
+            info.disk_size.unwrap_or(info.size)
+        } else {
+            info.size
+        }
+    }
+
     pub fn matches_size_filter(&self, size: u64) -> bool {
         if let Some(min_size) = self.min_size {
             if size < min_size {
@@ -140,4 +367,38 @@ impl Config {
 
         true
     }
+
+    // NDJSON entries always carry the child counts, the tree formats (JSON
+    // included) only with --count
+    pub fn counts_children(&self) -> bool {
+        self.show_count || matches!(self.output_format, OutputFormat::Ndjson)
+    }
+
+    pub fn has_time_filter(&self) -> bool {
+        self.newer.is_some() || self.older.is_some()
+    }
+
+    // Files without the selected timestamp never match
+    pub fn matches_time_filter(&self, info: &crate::FileInfo) -> bool {
+        if !self.has_time_filter() {
+            return true;
+        }
+        let Some(time) = info.time(self.time_field).map(DateTime::<Utc>::from) else {
+            return false;
+        };
+
+        self.newer.is_none_or(|newer| time > newer) && self.older.is_none_or(|older| time < older)
+    }
+
+    // Filters that select files by their own attributes leave directories
+    // without matches behind, those are dropped from the output
+    pub fn prunes_empty_directories(&self) -> bool {
+        self.filter_extension.is_some()
+            || self.has_time_filter()
+            || !self.include_patterns.is_empty()
+            || self.regex.is_some()
+            || self.filter_expression.is_some()
+            || self.grep.is_some()
+            || self.git_changed_only
+    }
 }
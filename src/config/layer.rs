@@ -0,0 +1,351 @@
+use super::{ChecksumAlgorithm, Config, OutputFormat, SortBy, TimeField, WatchMode};
+use crate::utils::{parse_size, parse_time};
+use crate::{Error, Location, Result};
+use chrono::{DateTime, Utc};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const ENV_PREFIX: &str = "DENARBOREA_";
+
+// A partial configuration coming from one source: a config file, the
+// environment or the command line. Unset fields fall through to the layer
+// below, see Config::load for the order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub max_depth: Option<usize>,
+    #[serde(alias = "all")]
+    pub show_hidden: Option<bool>,
+    #[serde(alias = "size")]
+    pub show_size: Option<bool>,
+    #[serde(alias = "permissions")]
+    pub show_permissions: Option<bool>,
+    #[serde(alias = "time")]
+    pub show_time: Option<bool>,
+    #[serde(alias = "count")]
+    pub show_count: Option<bool>,
+    pub use_colors: Option<bool>,
+    #[serde(alias = "sort")]
+    pub sort_by: Option<SortBy>,
+    #[serde(alias = "reverse")]
+    pub reverse_sort: Option<bool>,
+    #[serde(alias = "extension")]
+    pub filter_extension: Option<String>,
+    pub directories_only: Option<bool>,
+    pub files_only: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub min_size: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub max_size: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_time")]
+    pub newer: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "deserialize_time")]
+    pub older: Option<DateTime<Utc>>,
+    pub time_field: Option<TimeField>,
+    #[serde(default, alias = "exclude", deserialize_with = "deserialize_patterns")]
+    pub exclude_patterns: Option<Vec<String>>,
+    #[serde(default, alias = "include", deserialize_with = "deserialize_patterns")]
+    pub include_patterns: Option<Vec<String>>,
+    pub regex: Option<String>,
+    pub regex_ignore_case: Option<bool>,
+    #[serde(alias = "where")]
+    pub filter_expression: Option<String>,
+    pub grep: Option<String>,
+    pub grep_ignore_case: Option<bool>,
+    pub grep_fixed_strings: Option<bool>,
+    pub git_ignore: Option<bool>,
+    pub git_status: Option<bool>,
+    pub git_changed_only: Option<bool>,
+    pub limit: Option<usize>,
+    #[serde(alias = "format")]
+    pub output_format: Option<OutputFormat>,
+    pub follow_links: Option<bool>,
+    pub full_path: Option<bool>,
+    #[serde(alias = "checksum")]
+    pub show_checksum: Option<bool>,
+    pub checksum_algorithm: Option<ChecksumAlgorithm>,
+    pub checksum_length: Option<usize>,
+    pub checksum_cache: Option<bool>,
+    pub duplicates: Option<bool>,
+    pub watch: Option<WatchMode>,
+    #[serde(alias = "rev")]
+    pub revision: Option<String>,
+    #[serde(alias = "cache")]
+    pub scan_cache: Option<bool>,
+    #[serde(alias = "stats")]
+    pub show_stats: Option<bool>,
+    pub interactive: Option<bool>,
+    #[serde(alias = "du")]
+    pub disk_usage: Option<bool>,
+    #[serde(alias = "disk_size")]
+    pub show_disk_size: Option<bool>,
+    pub threads: Option<usize>,
+    pub stream: Option<bool>,
+    pub strict: Option<bool>,
+    // only meaningful inside a [profile.<name>] table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, ConfigLayer>,
+}
+
+impl ConfigLayer {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let layer: ConfigLayer = toml::from_str(&content).map_err(|e| {
+            unknown_key_error(&e, &content, path)
+                .unwrap_or_else(|| Error::toml(e, &content).with_path(path))
+        })?;
+
+        if layer.inherits.is_some() {
+            return Err(Error::Config(format!(
+                "{}: `inherits` is only allowed inside a [profile.<name>] table",
+                path.display()
+            )));
+        }
+        if let Some((name, _)) = layer
+            .profile
+            .iter()
+            .find(|(_, profile)| !profile.profile.is_empty())
+        {
+            return Err(Error::Config(format!(
+                "{}: profiles cannot be nested, found one inside [profile.{}]",
+                path.display(),
+                name
+            )));
+        }
+        Ok(layer)
+    }
+
+    // Resolves `name` among `profiles`, following `inherits` chains so that
+    // each profile overrides the one it inherits from
+    pub fn resolve_profile(name: &str, profiles: &BTreeMap<String, ConfigLayer>) -> Result<Self> {
+        let mut chain: Vec<&str> = Vec::new();
+        let mut current = name;
+
+        loop {
+            if chain.contains(&current) {
+                chain.push(current);
+                return Err(Error::Config(format!(
+                    "Profile inheritance cycle: {}",
+                    chain.join(" -> ")
+                )));
+            }
+            chain.push(current);
+
+            let profile = profiles.get(current).ok_or_else(|| match chain.len() {
+                1 => Error::Config(format!("Unknown profile `{}`", current)),
+                _ => Error::Config(format!(
+                    "Profile `{}` inherits from unknown profile `{}`",
+                    chain[chain.len() - 2],
+                    current
+                )),
+            })?;
+
+            match profile.inherits.as_deref() {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+
+        chain
+            .iter()
+            .rev()
+            .try_fold(ConfigLayer::default(), |resolved, name| {
+                let mut profile = profiles[*name].clone();
+                profile.inherits = None;
+                resolved.merge(profile)
+            })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(std::env::vars())
+    }
+
+    // DENARBOREA_SHOW_HIDDEN=true sets show_hidden and so on. Values are read
+    // as TOML when possible (booleans, numbers, arrays) and as plain strings
+    // otherwise.
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let mut table = toml::Table::new();
+
+        for (name, raw) in vars {
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            // selects a profile, see Config::load
+            if key == "PROFILE" {
+                continue;
+            }
+            let key = key.to_lowercase();
+            let value = toml::from_str::<toml::Table>(&format!("value = {}", raw))
+                .ok()
+                .and_then(|mut parsed| parsed.remove("value"))
+                .unwrap_or_else(|| toml::Value::String(raw.clone()));
+
+            // validate one variable at a time so the error can name it
+            let mut single = toml::Table::new();
+            single.insert(key.clone(), value.clone());
+            if let Err(e) = single.try_into::<ConfigLayer>() {
+                return Err(Error::Config(format!("{}: {}", name, e.message())));
+            }
+            table.insert(key, value);
+        }
+
+        table
+            .try_into()
+            .map_err(|e: toml::de::Error| Error::Config(e.message().to_string()))
+    }
+
+    // Values set in `other` win. Profiles are merged by name.
+    pub fn merge(mut self, mut other: ConfigLayer) -> Result<Self> {
+        let mut profiles = std::mem::take(&mut self.profile);
+        profiles.append(&mut other.profile);
+
+        let mut table = toml::Table::try_from(self)?;
+        table.extend(toml::Table::try_from(other)?);
+        let mut merged: ConfigLayer = table.try_into()?;
+        merged.profile = profiles;
+        Ok(merged)
+    }
+
+    pub fn apply(self, config: Config) -> Result<Config> {
+        let mut layer = toml::Table::try_from(self)?;
+        layer.remove("inherits");
+        layer.remove("profile");
+
+        let mut table = toml::Table::try_from(config)?;
+        table.extend(layer);
+        Ok(table.try_into()?)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        let content = toml::to_string_pretty(self)?;
+        std::fs::write(path, content).map_err(|e| Error::io(path, e))
+    }
+}
+
+// serde reports unknown keys as "unknown field `x`, expected one of `a`, ..."
+// followed by the full list. Turn that into a short message with the file,
+// line and the closest known key.
+fn unknown_key_error(err: &toml::de::Error, content: &str, path: &Path) -> Option<Error> {
+    let message = err.message();
+    if !message.starts_with("unknown field") {
+        return None;
+    }
+
+    let mut names = message.split('`').skip(1).step_by(2);
+    let key = names.next()?;
+    let suggestion = names
+        .map(|name| (edit_distance(key, name), name))
+        .filter(|(distance, _)| *distance <= 2)
+        .min();
+
+    let mut output = path.display().to_string();
+    if let Some(span) = err.span() {
+        let location = Location::from_offset(content, span.start);
+        output.push_str(&format!(":{}", location.line));
+        if let Some(column) = location.column {
+            output.push_str(&format!(":{}", column));
+        }
+    }
+    output.push_str(&format!(": unknown key `{}`", key));
+    if let Some((_, name)) = suggestion {
+        output.push_str(&format!(", did you mean `{}`?", name));
+    }
+    Some(Error::Config(output))
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Sizes may be given in bytes or in the same format as --min-size
+fn deserialize_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&Some(text)).map_err(D::Error::custom),
+    }
+}
+
+// Times may be a duration before now, a date or a date and time, in the same
+// format as --newer. TOML dates are accepted unquoted.
+fn deserialize_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<DateTime<Utc>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Time {
+        Date(toml::value::Datetime),
+        Text(String),
+    }
+
+    let text = match Option::<Time>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Time::Date(date)) => date.to_string(),
+        Some(Time::Text(text)) => text,
+    };
+    parse_time(&text).map(Some).map_err(D::Error::custom)
+}
+
+// Patterns may be a list or a comma-separated string
+fn deserialize_patterns<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Patterns {
+        List(Vec<String>),
+        Text(String),
+    }
+
+    Ok(match Option::<Patterns>::deserialize(deserializer)? {
+        None => None,
+        Some(Patterns::List(list)) => Some(list),
+        Some(Patterns::Text(text)) => Some(
+            split_patterns(&text)
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+        ),
+    })
+}
+
+// Splits on commas outside of braces, so that `*.{rs,toml}` stays whole
+fn split_patterns(text: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0usize;
+    text.split(move |c| {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' => return depth == 0,
+            _ => {}
+        }
+        false
+    })
+}
@@ -0,0 +1,446 @@
+use crate::checksum_cache::calculate_checksum;
+use crate::{utils, Config, FileInfo, Result, TreeVisualizer};
+use colored::*;
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Removed,
+    TypeChanged,
+    Unreadable,
+    SizeChanged,
+    ContentChanged,
+    TimeChanged,
+    Unchanged,
+}
+
+impl DiffStatus {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DiffStatus::Added => "A ",
+            DiffStatus::Removed => "D ",
+            DiffStatus::TypeChanged => "X ",
+            DiffStatus::Unreadable => "! ",
+            DiffStatus::SizeChanged => "S ",
+            DiffStatus::ContentChanged => "C ",
+            DiffStatus::TimeChanged => "T ",
+            DiffStatus::Unchanged => "  ",
+        }
+    }
+
+    pub fn color(&self) -> colored::Color {
+        match self {
+            DiffStatus::Added => colored::Color::Green,
+            DiffStatus::Removed => colored::Color::Red,
+            DiffStatus::TypeChanged => colored::Color::Magenta,
+            DiffStatus::Unreadable => colored::Color::Red,
+            DiffStatus::SizeChanged => colored::Color::Yellow,
+            DiffStatus::ContentChanged => colored::Color::Yellow,
+            DiffStatus::TimeChanged => colored::Color::Blue,
+            DiffStatus::Unchanged => colored::Color::White,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiffStatus::Added => "added",
+            DiffStatus::Removed => "removed",
+            DiffStatus::TypeChanged => "type",
+            DiffStatus::Unreadable => "unreadable",
+            DiffStatus::SizeChanged => "size",
+            DiffStatus::ContentChanged => "content",
+            DiffStatus::TimeChanged => "mtime",
+            DiffStatus::Unchanged => "unchanged",
+        }
+    }
+}
+
+// One path present on either side. `path` is relative to both roots.
+#[derive(Debug, Clone)]
+pub struct DiffEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub status: DiffStatus,
+    pub left: Option<FileInfo>,
+    pub right: Option<FileInfo>,
+}
+
+impl DiffEntry {
+    pub fn is_dir(&self) -> bool {
+        self.right
+            .as_ref()
+            .or(self.left.as_ref())
+            .is_some_and(|info| info.is_dir)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffNode {
+    pub entry: DiffEntry,
+    pub children: Vec<DiffNode>,
+}
+
+// Both trees merged into one, entries sorted by name
+#[derive(Debug, Clone)]
+pub struct TreeDiff {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    pub nodes: Vec<DiffNode>,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl TreeDiff {
+    // Walks both roots with the same filters and compares entries by their
+    // path relative to the root. Files of equal size are checksummed with the
+    // configured algorithm, so a content change is only reported when the
+    // sizes match. An entry that could not be read on either side is never
+    // taken as unchanged.
+    pub fn compare(config: &Config, left: &Path, right: &Path) -> Result<Self> {
+        let mut left_visualizer = TreeVisualizer::new(config.clone());
+        let left_entries = left_visualizer.collect_entries(left)?;
+        let mut right_visualizer = TreeVisualizer::new(config.clone());
+        let right_entries = right_visualizer.collect_entries(right)?;
+
+        let mut merged: BTreeMap<PathBuf, (Option<FileInfo>, Option<FileInfo>)> = BTreeMap::new();
+        for info in left_entries {
+            if let Ok(relative) = info.path.strip_prefix(left).map(Path::to_path_buf) {
+                merged.entry(relative).or_default().0 = Some(info);
+            }
+        }
+        for info in right_entries {
+            if let Ok(relative) = info.path.strip_prefix(right).map(Path::to_path_buf) {
+                merged.entry(relative).or_default().1 = Some(info);
+            }
+        }
+
+        let mut errors = left_visualizer.errors().to_vec();
+        errors.extend_from_slice(right_visualizer.errors());
+
+        let mut entries_by_parent: HashMap<PathBuf, Vec<DiffEntry>> = HashMap::new();
+        for (path, (left, right)) in merged {
+            let Some(parent) = path.parent() else {
+                continue;
+            };
+            let entry = Self::compare_entry(path.clone(), left, right, config, &mut errors);
+            entries_by_parent
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push(entry);
+        }
+
+        let mut nodes = Self::build_nodes(Path::new(""), &mut entries_by_parent);
+        // same as the tree view, file filters leave no empty directories
+        if config.prunes_empty_directories() {
+            nodes = Self::prune_empty_directories(nodes);
+        }
+
+        Ok(Self {
+            left: left.to_path_buf(),
+            right: right.to_path_buf(),
+            nodes,
+            errors,
+        })
+    }
+
+    fn compare_entry(
+        path: PathBuf,
+        mut left: Option<FileInfo>,
+        mut right: Option<FileInfo>,
+        config: &Config,
+        errors: &mut Vec<(PathBuf, String)>,
+    ) -> DiffEntry {
+        let status = match (&mut left, &mut right) {
+            (None, _) => DiffStatus::Added,
+            (_, None) => DiffStatus::Removed,
+            (Some(l), Some(r)) => Self::compare_info(l, r, config, errors),
+        };
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        DiffEntry {
+            path,
+            name,
+            status,
+            left,
+            right,
+        }
+    }
+
+    fn compare_info(
+        left: &mut FileInfo,
+        right: &mut FileInfo,
+        config: &Config,
+        errors: &mut Vec<(PathBuf, String)>,
+    ) -> DiffStatus {
+        if left.is_dir != right.is_dir || left.is_symlink != right.is_symlink {
+            return DiffStatus::TypeChanged;
+        }
+        // the walk already reported these errors
+        if left.error.is_some() || right.error.is_some() {
+            return DiffStatus::Unreadable;
+        }
+        // directories only carry the changes of their contents
+        if left.is_dir {
+            return DiffStatus::Unchanged;
+        }
+        if left.size != right.size {
+            return DiffStatus::SizeChanged;
+        }
+
+        let mut unreadable = false;
+        for info in [&mut *left, &mut *right] {
+            if let Err(e) = Self::checksum(info, config) {
+                errors.push((info.path.clone(), e.reason()));
+                info.error = Some(e.reason());
+                unreadable = true;
+            }
+        }
+        if unreadable {
+            return DiffStatus::Unreadable;
+        }
+        if left.checksum != right.checksum {
+            return DiffStatus::ContentChanged;
+        }
+        if left.modified_time != right.modified_time {
+            return DiffStatus::TimeChanged;
+        }
+        DiffStatus::Unchanged
+    }
+
+    fn checksum(info: &mut FileInfo, config: &Config) -> Result<()> {
+        if info.checksum.is_none() {
+            info.checksum = Some(calculate_checksum(
+                &info.path,
+                config.checksum_algorithm,
+                config.checksums.as_deref(),
+            )?);
+        }
+        Ok(())
+    }
+
+    fn build_nodes(
+        parent: &Path,
+        entries_by_parent: &mut HashMap<PathBuf, Vec<DiffEntry>>,
+    ) -> Vec<DiffNode> {
+        let Some(entries) = entries_by_parent.remove(parent) else {
+            return Vec::new();
+        };
+
+        entries
+            .into_iter()
+            .map(|entry| {
+                let children = Self::build_nodes(&entry.path, entries_by_parent);
+                DiffNode { entry, children }
+            })
+            .collect()
+    }
+
+    fn prune_empty_directories(nodes: Vec<DiffNode>) -> Vec<DiffNode> {
+        nodes
+            .into_iter()
+            .filter_map(|mut node| {
+                if !node.entry.is_dir() {
+                    return Some(node);
+                }
+                node.children = Self::prune_empty_directories(node.children);
+                (!node.children.is_empty()).then_some(node)
+            })
+            .collect()
+    }
+
+    // Every entry in pre-order, the same order as the rendered tree
+    pub fn entries(&self) -> Vec<&DiffEntry> {
+        fn visit<'a>(nodes: &'a [DiffNode], out: &mut Vec<&'a DiffEntry>) {
+            for node in nodes {
+                out.push(&node.entry);
+                visit(&node.children, out);
+            }
+        }
+
+        let mut entries = Vec::new();
+        visit(&self.nodes, &mut entries);
+        entries
+    }
+
+    pub fn changes(&self) -> Vec<&DiffEntry> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.status != DiffStatus::Unchanged)
+            .collect()
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.changes().is_empty()
+    }
+
+    // Unreadable entries, also those outside the compared entries
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    pub fn count(&self, status: DiffStatus) -> usize {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.status == status)
+            .count()
+    }
+
+    pub fn render(&self, use_colors: bool) -> String {
+        let mut output = format!("{} -> {}\n", self.left.display(), self.right.display());
+        if use_colors {
+            output = output.blue().bold().to_string();
+        }
+        Self::render_nodes(&mut output, &self.nodes, "", use_colors);
+
+        let summary = [
+            DiffStatus::Added,
+            DiffStatus::Removed,
+            DiffStatus::TypeChanged,
+            DiffStatus::SizeChanged,
+            DiffStatus::ContentChanged,
+            DiffStatus::TimeChanged,
+        ]
+        .iter()
+        .map(|status| format!("{} {}", self.count(*status), status.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+        output.push_str(&format!("\n{}\n", summary));
+
+        if self.has_errors() {
+            output.push_str(&format!("\nErrors: {}\n", self.errors.len()));
+            for (path, error) in &self.errors {
+                let line = format!("{}: {}", path.display(), error);
+                if use_colors {
+                    output.push_str(&line.red().to_string());
+                } else {
+                    output.push_str(&line);
+                }
+                output.push('\n');
+            }
+        }
+        output
+    }
+
+    fn render_nodes(output: &mut String, nodes: &[DiffNode], prefix: &str, use_colors: bool) {
+        for (i, node) in nodes.iter().enumerate() {
+            let is_last = i == nodes.len() - 1;
+            let connector = if is_last { "'-- " } else { "|-- " };
+            let new_prefix = if is_last { "   " } else { "|   " };
+
+            output.push_str(&format!(
+                "{}{}{}\n",
+                prefix,
+                connector,
+                Self::format_entry(&node.entry, use_colors)
+            ));
+
+            if !node.children.is_empty() {
+                let child_prefix = format!("{}{}", prefix, new_prefix);
+                Self::render_nodes(output, &node.children, &child_prefix, use_colors);
+            }
+        }
+    }
+
+    fn format_entry(entry: &DiffEntry, use_colors: bool) -> String {
+        let status = entry.status;
+        let name = if entry.is_dir() && use_colors {
+            entry.name.blue().to_string()
+        } else {
+            entry.name.clone()
+        };
+
+        let detail = match (status, &entry.left, &entry.right) {
+            (DiffStatus::SizeChanged, Some(l), Some(r)) => format!(
+                "[{} -> {}]",
+                format_size(l.size, DECIMAL),
+                format_size(r.size, DECIMAL)
+            ),
+            (DiffStatus::TimeChanged, Some(l), Some(r)) => match (l.modified_time, r.modified_time)
+            {
+                (Some(lt), Some(rt)) => {
+                    format!("[{} -> {}]", utils::format_time(lt), utils::format_time(rt))
+                }
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+
+        if use_colors {
+            format!(
+                "{}{}{}",
+                status.symbol().color(status.color()),
+                name,
+                detail.dimmed()
+            )
+        } else {
+            format!("{}{}{}", status.symbol(), name, detail)
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        fn side(info: &Option<FileInfo>) -> serde_json::Value {
+            match info {
+                Some(info) => json!({
+                    "size": info.size,
+                    "modified_time": info.modified_time.map(utils::format_time),
+                    "checksum": info.checksum,
+                }),
+                None => serde_json::Value::Null,
+            }
+        }
+
+        fn nodes_json(nodes: &[DiffNode]) -> serde_json::Value {
+            nodes
+                .iter()
+                .map(|node| {
+                    let mut value = json!({
+                        "name": node.entry.name,
+                        "path": node.entry.path,
+                        "is_dir": node.entry.is_dir(),
+                        "status": node.entry.status.as_str(),
+                        "left": side(&node.entry.left),
+                        "right": side(&node.entry.right),
+                    });
+                    if !node.children.is_empty() {
+                        value["children"] = nodes_json(&node.children);
+                    }
+                    value
+                })
+                .collect()
+        }
+
+        let changes: Vec<_> = self
+            .changes()
+            .into_iter()
+            .map(|entry| json!({ "path": entry.path, "status": entry.status.as_str() }))
+            .collect();
+
+        json!({
+            "left": self.left,
+            "right": self.right,
+            "tree": nodes_json(&self.nodes),
+            "changes": changes,
+            "summary": {
+                "added": self.count(DiffStatus::Added),
+                "removed": self.count(DiffStatus::Removed),
+                "type": self.count(DiffStatus::TypeChanged),
+                "size": self.count(DiffStatus::SizeChanged),
+                "content": self.count(DiffStatus::ContentChanged),
+                "mtime": self.count(DiffStatus::TimeChanged),
+                "unreadable": self.count(DiffStatus::Unreadable),
+            },
+            "errors": self
+                .errors
+                .iter()
+                .map(|(path, error)| json!({ "path": path, "error": error }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
@@ -1,8 +1,14 @@
-use crate::{git::GitInfo, utils, Config, OutputFormat, Result};
+use crate::{
+    checksum_cache,
+    config::TimeField,
+    git::{GitInfo, GitState, GitStatus},
+    utils, ChecksumAlgorithm, ChecksumCache, Config, ContentMatches, Error, OutputFormat, Result,
+};
 use colored::*;
 use humansize::{format_size, DECIMAL};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
@@ -10,19 +16,36 @@ pub struct FileInfo {
     pub path: PathBuf,
     pub name: String,
     pub size: u64,
+    pub disk_size: Option<u64>,
     pub is_dir: bool,
     pub is_executable: bool,
     pub is_symlink: bool,
     pub modified_time: Option<SystemTime>,
+    pub accessed_time: Option<SystemTime>,
+    pub changed_time: Option<SystemTime>,
+    pub created_time: Option<SystemTime>,
     pub permissions: Option<u32>,
+    // symlink target when it does not come from disk, as with --rev
+    pub link_target: Option<PathBuf>,
     pub checksum: Option<String>,
     pub file_count: Option<usize>,
     pub dir_count: Option<usize>,
+    pub error: Option<String>,
+    // filled in by --grep
+    pub content_matches: Option<ContentMatches>,
 }
 
 impl FileInfo {
     pub fn from_path(path: &Path) -> Result<Self> {
-        let symlink_metadata = fs::symlink_metadata(path)?;
+        let symlink_metadata = fs::symlink_metadata(path).map_err(|e| Error::io(path, e))?;
+        Ok(Self::from_metadata(path, symlink_metadata, true))
+    }
+
+    pub fn from_metadata(
+        path: &Path,
+        symlink_metadata: fs::Metadata,
+        count_children: bool,
+    ) -> Self {
         let is_symlink = symlink_metadata.file_type().is_symlink();
 
         // Use regular metadata for size and other properties, but symlink_metadata for type detection
@@ -40,28 +63,85 @@ impl FileInfo {
         };
 
         let modified_time = metadata.modified().ok();
+        let accessed_time = utils::file_time(&metadata, TimeField::Atime);
+        let changed_time = utils::file_time(&metadata, TimeField::Ctime);
+        let created_time = utils::file_time(&metadata, TimeField::Birth);
         let permissions = Self::get_permissions(&metadata);
 
-        let (file_count, dir_count) = if metadata.is_dir() {
+        let (file_count, dir_count) = if metadata.is_dir() && count_children {
             let (files, dirs) = utils::count_files_in_dir(path);
             (Some(files), Some(dirs))
         } else {
             (None, None)
         };
 
-        Ok(Self {
+        Self {
             path: path.to_path_buf(),
             name,
             size: metadata.len(),
+            disk_size: utils::disk_size(&metadata),
             is_dir: metadata.is_dir(),
             is_executable: Self::is_executable(&metadata),
             is_symlink,
             modified_time,
+            accessed_time,
+            changed_time,
+            created_time,
             permissions,
+            link_target: None,
             checksum: None, //will be calculated on demand
             file_count,
             dir_count,
-        })
+            error: None,
+            content_matches: None,
+        }
+    }
+
+    pub fn time(&self, field: TimeField) -> Option<SystemTime> {
+        match field {
+            TimeField::Mtime => self.modified_time,
+            TimeField::Ctime => self.changed_time,
+            TimeField::Atime => self.accessed_time,
+            TimeField::Birth => self.created_time,
+        }
+    }
+
+    // Placeholder for an entry that could not be read
+    pub fn from_error(path: &Path, is_dir: bool, error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::without_metadata(path, is_dir)
+        }
+    }
+
+    // An entry that is not on disk, such as a file deleted from a git
+    // worktree
+    pub fn without_metadata(path: &Path, is_dir: bool) -> Self {
+        let name = match path.file_name() {
+            Some(file_name) => file_name.to_string_lossy().to_string(),
+            None => path.to_string_lossy().to_string(),
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            name,
+            size: 0,
+            disk_size: None,
+            is_dir,
+            is_executable: false,
+            is_symlink: false,
+            modified_time: None,
+            accessed_time: None,
+            changed_time: None,
+            created_time: None,
+            permissions: None,
+            link_target: None,
+            checksum: None,
+            file_count: None,
+            dir_count: None,
+            error: None,
+            content_matches: None,
+        }
     }
 
     #[cfg(unix)]
@@ -87,8 +167,22 @@ impl FileInfo {
     }
 
     pub fn calculate_checksum(&mut self) -> Option<&String> {
+        self.calculate_checksum_with(ChecksumAlgorithm::default())
+    }
+
+    pub fn calculate_checksum_with(&mut self, algorithm: ChecksumAlgorithm) -> Option<&String> {
+        self.calculate_checksum_cached(algorithm, None)
+    }
+
+    // Looks the digest up in `cache` first and records it there when it had
+    // to be calculated
+    pub fn calculate_checksum_cached(
+        &mut self,
+        algorithm: ChecksumAlgorithm,
+        cache: Option<&Mutex<ChecksumCache>>,
+    ) -> Option<&String> {
         if self.checksum.is_none() && !self.is_dir {
-            if let Ok(hash) = utils::calculate_md5(&self.path) {
+            if let Ok(hash) = checksum_cache::calculate_checksum(&self.path, algorithm, cache) {
                 self.checksum = Some(hash);
             }
         }
@@ -116,7 +210,10 @@ impl TreeDisplay {
 
     pub fn format_path(&self, path: &Path) -> Result<String> {
         let display_path = if self.config.full_path {
-            path.canonicalize()?.display().to_string()
+            path.canonicalize()
+                .map_err(|e| Error::io(path, e))?
+                .display()
+                .to_string()
         } else {
             path.display().to_string()
         };
@@ -131,7 +228,7 @@ impl TreeDisplay {
     pub fn format_file_info(&self, info: &FileInfo) -> Result<String> {
         match self.config.output_format {
             OutputFormat::Tree => self.format_tree_line(info),
-            OutputFormat::Json => self.format_json_line(info),
+            OutputFormat::Json | OutputFormat::Ndjson => self.format_json_line(info),
             OutputFormat::Csv => self.format_csv_line(info),
             OutputFormat::Markdown => self.format_markdown_line(info),
             OutputFormat::Xml => self.format_xml_line(info),
@@ -141,23 +238,26 @@ impl TreeDisplay {
     fn format_tree_line(&self, info: &FileInfo) -> Result<String> {
         let mut output = String::new();
 
-        // git status indicator
+        // git status indicator, directories are marked with the changes below them
+        let mut git_summary = None;
         if self.config.git_status {
             if let Some(ref git_info) = self.git_info {
-                let abs_path = info
-                    .path
-                    .canonicalize()
-                    .unwrap_or_else(|_| info.path.clone());
-
-                let status = git_info.get_status(&abs_path);
+                let mut state = git_info.get_status(&info.path);
+                if info.is_dir {
+                    git_summary = git_info.summary(&info.path);
+                    if state == GitState::CLEAN {
+                        if let Some(summary) = &git_summary {
+                            state = summary.mark();
+                        }
+                    }
+                }
 
-                let status_str = if self.config.use_colors {
-                    status.symbol().color(status.color()).to_string()
+                if self.config.use_colors {
+                    output.push_str(&Self::color_git_state(state));
                 } else {
-                    status.symbol().to_string()
-                };
-
-                output.push_str(&status_str.to_string());
+                    output.push_str(&state.symbol());
+                }
+                output.push(' ');
             }
         }
 
@@ -169,6 +269,22 @@ impl TreeDisplay {
 
         output.push_str(&formatted_name);
 
+        if let Some(summary) = git_summary {
+            let parts: Vec<String> = summary
+                .counts()
+                .iter()
+                .map(|(status, count)| {
+                    let part = format!("{} {}", count, status.label());
+                    if self.config.use_colors {
+                        part.color(status.color()).to_string()
+                    } else {
+                        part
+                    }
+                })
+                .collect();
+            output.push_str(&format!("[{}]", parts.join(", ")));
+        }
+
         // add file/directory counts
         if self.config.show_count && info.is_dir {
             if let (Some(files), Some(dirs)) = (info.file_count, info.dir_count) {
@@ -181,8 +297,16 @@ impl TreeDisplay {
             }
         }
 
-        if self.config.show_size && !info.is_dir {
-            let size_str = format!("[{}]", format_size(info.size, DECIMAL));
+        // in disk usage mode directories carry the total size of their contents
+        if self.config.disk_usage || (self.config.show_size && !info.is_dir) {
+            let size_str = match info.disk_size {
+                Some(disk_size) if self.config.show_disk_size => format!(
+                    "[{}, {} on disk]",
+                    format_size(info.size, DECIMAL),
+                    format_size(disk_size, DECIMAL)
+                ),
+                _ => format!("[{}]", format_size(info.size, DECIMAL)),
+            };
             if self.config.use_colors {
                 output.push_str(&size_str.dimmed().to_string());
             } else {
@@ -216,7 +340,10 @@ impl TreeDisplay {
         // Add checksum if requested
         if self.config.show_checksum && !info.is_dir {
             if let Some(checksum) = &info.checksum {
-                let checksum_str = format!("<{}>", &checksum[..8]);
+                let prefix = checksum
+                    .get(..self.config.checksum_length)
+                    .unwrap_or(checksum);
+                let checksum_str = format!("<{}>", prefix);
                 if self.config.use_colors {
                     output.push_str(&checksum_str.magenta().to_string());
                 } else {
@@ -225,9 +352,27 @@ impl TreeDisplay {
             }
         }
 
+        if let Some(matches) = info.content_matches.as_ref().filter(|m| m.count > 0) {
+            let noun = if matches.count == 1 {
+                "match"
+            } else {
+                "matches"
+            };
+            let matches_str = format!("[{} {}]", matches.count, noun);
+            if self.config.use_colors {
+                output.push_str(&matches_str.yellow().to_string());
+            } else {
+                output.push_str(&matches_str);
+            }
+        }
+
         // Add symlink if requested
         if info.is_symlink {
-            if let Ok(target) = fs::read_link(&info.path) {
+            let target = match &info.link_target {
+                Some(target) => Ok(target.clone()),
+                None => fs::read_link(&info.path),
+            };
+            if let Ok(target) = target {
                 let symlink_str = format!("-> {}", target.display());
                 if self.config.use_colors {
                     output.push_str(&symlink_str.cyan().to_string());
@@ -236,16 +381,70 @@ impl TreeDisplay {
                 }
             }
         }
+
+        if let Some(error) = &info.error {
+            let error_str = format!("[error: {}]", error);
+            if self.config.use_colors {
+                output.push_str(&error_str.red().to_string());
+            } else {
+                output.push_str(&error_str);
+            }
+        }
         Ok(output)
     }
 
+    // Colored like git status: staged changes green, everything else red
+    fn color_git_state(state: GitState) -> String {
+        let column = |status: GitStatus, staged: bool| {
+            let code = status.code().to_string();
+            match status {
+                GitStatus::Clean => code,
+                GitStatus::Ignored => code.bright_black().to_string(),
+                GitStatus::Untracked | GitStatus::Conflicted => code.red().to_string(),
+                _ if staged => code.green().to_string(),
+                _ => code.red().to_string(),
+            }
+        };
+        format!(
+            "{}{}",
+            column(state.index, true),
+            column(state.worktree, false)
+        )
+    }
+
+    // Index and worktree state of an entry, and the changes below a directory
+    pub fn git_json(&self, info: &FileInfo) -> Option<serde_json::Value> {
+        if !self.config.git_status {
+            return None;
+        }
+        let git_info = self.git_info.as_ref()?;
+        let state = git_info.status(&info.path)?;
+
+        let mut json = serde_json::json!({
+            "index": state.index.label(),
+            "worktree": state.worktree.label(),
+        });
+        if info.is_dir {
+            if let Some(summary) = git_info.summary(&info.path) {
+                json["changes"] = summary
+                    .counts()
+                    .iter()
+                    .map(|(status, count)| (status.label().to_string(), serde_json::json!(count)))
+                    .collect::<serde_json::Map<_, _>>()
+                    .into();
+            }
+        }
+        Some(json)
+    }
+
     fn format_json_line(&self, info: &FileInfo) -> Result<String> {
         use serde_json::json;
 
-        let json_obj = json!({
+        let mut json_obj = json!({
             "name": info.name,
             "path": info.path,
             "size": info.size,
+            "disk_size": info.disk_size,
             "is_dir": info.is_dir,
             "is_executable": info.is_executable,
             "is_symlink": info.is_symlink,
@@ -255,10 +454,57 @@ impl TreeDisplay {
             "file_count": info.file_count,
             "dir_count": info.dir_count,
         });
+        if let Some(error) = &info.error {
+            json_obj["error"] = json!(error);
+        }
+        if let Some(matches) = &info.content_matches {
+            json_obj["matches"] = Self::matches_json(matches);
+        }
+        if let Some(git) = self.git_json(info) {
+            json_obj["git"] = git;
+        }
 
         Ok(json_obj.to_string())
     }
 
+    pub fn matches_json(matches: &ContentMatches) -> serde_json::Value {
+        serde_json::json!({
+            "count": matches.count,
+            "lines": matches
+                .lines
+                .iter()
+                .map(|line| serde_json::json!({ "line": line.number, "text": line.text }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    // The first lines that matched --grep, shown below the file in a tree
+    pub fn format_match_lines(&self, info: &FileInfo) -> Vec<String> {
+        let Some(matches) = &info.content_matches else {
+            return Vec::new();
+        };
+        matches
+            .lines
+            .iter()
+            .map(|line| {
+                let number = format!("{}:", line.number);
+                if self.config.use_colors {
+                    format!("{} {}", number.dimmed(), line.text)
+                } else {
+                    format!("{} {}", number, line.text)
+                }
+            })
+            .collect()
+    }
+
+    pub fn csv_header(&self) -> String {
+        let mut header = "Name,Path,Size,IsDirectory,Modified,Permissions".to_string();
+        if self.config.show_checksum {
+            header.push_str(",Checksum");
+        }
+        header
+    }
+
     fn format_csv_line(&self, info: &FileInfo) -> Result<String> {
         let path = info.path.display().to_string();
         let size = info.size.to_string();
@@ -272,15 +518,20 @@ impl TreeDisplay {
             .map(utils::format_permissions)
             .unwrap_or_default();
 
-        Ok(format!(
+        let mut line = format!(
             "{},{},{},{},{},{}",
             info.name, path, size, is_dir, modified, permissions
-        ))
+        );
+        if self.config.show_checksum {
+            line.push(',');
+            line.push_str(info.checksum.as_deref().unwrap_or_default());
+        }
+        Ok(line)
     }
 
     fn format_markdown_line(&self, info: &FileInfo) -> Result<String> {
         let icon = if info.is_dir { "--" } else { "xx" };
-        let size = if info.is_dir {
+        let size = if info.is_dir && !self.config.disk_usage {
             "-".to_string()
         } else {
             format_size(info.size, DECIMAL)
@@ -0,0 +1,158 @@
+use crate::{checksum_cache, ChecksumAlgorithm, ChecksumCache, FileInfo};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Bytes read from each end of a file for the partial hash
+const BLOCK_SIZE: u64 = 4096;
+
+#[derive(Debug, Clone)]
+pub struct DuplicateSet {
+    pub size: u64,
+    pub checksum: String,
+    // one path per inode, hardlinks to the same file are not duplicates
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateSet {
+    // Bytes that would be freed by keeping a single copy
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Duplicates {
+    // largest waste first
+    pub sets: Vec<DuplicateSet>,
+}
+
+impl Duplicates {
+    // Files are grouped by size, then by a hash of their first and last
+    // blocks, and only the remaining candidates are fully hashed. Empty files,
+    // symlinks and unreadable files are skipped. Full hashes go through
+    // `cache` when there is one.
+    pub fn find(
+        entries: &[FileInfo],
+        algorithm: ChecksumAlgorithm,
+        cache: Option<&Mutex<ChecksumCache>>,
+    ) -> Self {
+        let mut by_size: HashMap<u64, Vec<&Path>> = HashMap::new();
+        for info in entries {
+            if !info.is_dir && !info.is_symlink && info.error.is_none() && info.size > 0 {
+                by_size.entry(info.size).or_default().push(&info.path);
+            }
+        }
+
+        let mut sets = Vec::new();
+        for (size, paths) in by_size {
+            if paths.len() < 2 {
+                continue;
+            }
+
+            // of several hardlinks, the first path in sort order is kept
+            let mut paths = paths;
+            paths.sort();
+            let mut inodes = HashSet::new();
+            let paths: Vec<&Path> = paths
+                .into_iter()
+                .filter(|path| file_id(path).is_none_or(|id| inodes.insert(id)))
+                .collect();
+
+            // small files are read whole by the partial hash anyway
+            let candidates = if size > 2 * BLOCK_SIZE {
+                group_by(paths, |path| partial_hash(path, size).ok())
+                    .into_iter()
+                    .map(|(_, group)| group)
+                    .collect()
+            } else {
+                vec![paths]
+            };
+
+            for candidate in candidates {
+                let by_checksum = group_by(candidate, |path| {
+                    checksum_cache::calculate_checksum(path, algorithm, cache).ok()
+                });
+                for (checksum, paths) in by_checksum {
+                    let mut paths: Vec<PathBuf> =
+                        paths.into_iter().map(Path::to_path_buf).collect();
+                    paths.sort();
+                    sets.push(DuplicateSet {
+                        size,
+                        checksum,
+                        paths,
+                    });
+                }
+            }
+        }
+
+        sets.sort_by(|a, b| {
+            b.wasted_bytes()
+                .cmp(&a.wasted_bytes())
+                .then_with(|| a.paths.cmp(&b.paths))
+        });
+        Self { sets }
+    }
+
+    pub fn wasted_bytes(&self) -> u64 {
+        self.sets.iter().map(DuplicateSet::wasted_bytes).sum()
+    }
+
+    // Maps every duplicate path to the 1-based number of its set, as shown
+    // in the output
+    pub fn set_numbers(&self) -> HashMap<&Path, usize> {
+        self.sets
+            .iter()
+            .enumerate()
+            .flat_map(|(index, set)| {
+                set.paths
+                    .iter()
+                    .map(move |path| (path.as_path(), index + 1))
+            })
+            .collect()
+    }
+}
+
+// Groups paths by `key`, keeping groups of at least two. Paths without a key
+// (unreadable files) are dropped.
+fn group_by<'a, K: Hash + Eq>(
+    paths: Vec<&'a Path>,
+    key: impl Fn(&Path) -> Option<K>,
+) -> Vec<(K, Vec<&'a Path>)> {
+    let mut groups: HashMap<K, Vec<&'a Path>> = HashMap::new();
+    for path in paths {
+        if let Some(key) = key(path) {
+            groups.entry(key).or_default().push(path);
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .collect()
+}
+
+fn partial_hash(path: &Path, size: u64) -> std::io::Result<u64> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buffer = vec![0; 2 * BLOCK_SIZE as usize];
+
+    let (head, tail) = buffer.split_at_mut(BLOCK_SIZE as usize);
+    file.read_exact(head)?;
+    file.seek(SeekFrom::Start(size - BLOCK_SIZE))?;
+    file.read_exact(tail)?;
+
+    Ok(xxhash_rust::xxh3::xxh3_64(&buffer))
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    Parse {
+        format: &'static str,
+        path: Option<PathBuf>,
+        location: Option<Location>,
+        message: String,
+    },
+    Git(String),
+    Config(String),
+    Viewer(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+impl Location {
+    // Converts a byte offset into a 1-based line and column
+    pub fn from_offset(text: &str, offset: usize) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: Some(before[line_start..].chars().count() + 1),
+        }
+    }
+}
+
+impl Error {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        }
+    }
+
+    pub fn not_found(path: &Path) -> Self {
+        Self::io(
+            path,
+            io::Error::new(io::ErrorKind::NotFound, "path does not exist"),
+        )
+    }
+
+    pub fn parse(format: &'static str, message: impl Into<String>) -> Self {
+        Error::Parse {
+            format,
+            path: None,
+            location: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn toml(err: toml::de::Error, content: &str) -> Self {
+        Error::Parse {
+            format: "TOML",
+            path: None,
+            location: err
+                .span()
+                .map(|span| Location::from_offset(content, span.start)),
+            message: err.message().to_string(),
+        }
+    }
+
+    // Attaches a path to Io and Parse errors that do not carry one yet
+    pub fn with_path(mut self, new_path: &Path) -> Self {
+        if let Error::Io { path, .. } | Error::Parse { path, .. } = &mut self {
+            if path.is_none() {
+                *path = Some(new_path.to_path_buf());
+            }
+        }
+        self
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Io { path, .. } | Error::Parse { path, .. } => path.as_deref(),
+            _ => None,
+        }
+    }
+
+    // Short description of the failure, as shown next to tree entries
+    pub fn reason(&self) -> String {
+        match self {
+            Error::Io { source, .. } if source.kind() != io::ErrorKind::Other => {
+                source.kind().to_string()
+            }
+            Error::Io { source, .. } => source.to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::Io { source, .. } if source.kind() == io::ErrorKind::NotFound)
+    }
+
+    // Exit codes are part of the CLI contract, scripts rely on them.
+    // 2 is left to clap for usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io { source, .. } => match source.kind() {
+                io::ErrorKind::NotFound => 3,
+                io::ErrorKind::PermissionDenied => 4,
+                _ => 5,
+            },
+            Error::Parse { .. } => 6,
+            Error::Git(_) => 7,
+            Error::Config(_) => 8,
+            Error::Viewer(_) => 9,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => match path {
+                Some(path) => write!(f, "{}: {}", path.display(), source),
+                None => write!(f, "{}", source),
+            },
+            Error::Parse {
+                format,
+                path,
+                location,
+                message,
+            } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(f, "invalid {}", format)?;
+                if let Some(location) = location {
+                    write!(f, " at line {}", location.line)?;
+                    if let Some(column) = location.column {
+                        write!(f, ", column {}", column)?;
+                    }
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Git(message) => write!(f, "git error: {}", message),
+            Error::Config(message) => write!(f, "{}", message),
+            Error::Viewer(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io { path: None, source }
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(err: notify::Error) -> Self {
+        let message = err.to_string();
+        let path = err.paths.first().cloned();
+        let source = match err.kind {
+            notify::ErrorKind::Io(source) => source,
+            notify::ErrorKind::PathNotFound => io::Error::new(io::ErrorKind::NotFound, message),
+            _ => io::Error::other(message),
+        };
+        Error::Io { path, source }
+    }
+}
+
+// serde_json and serde_yaml append the position to their message, it is
+// reported through `location` instead
+fn strip_position(message: String, line: usize, column: usize) -> String {
+    let suffix = format!(" at line {} column {}", line, column);
+    match message.strip_suffix(&suffix) {
+        Some(stripped) => stripped.to_string(),
+        None => message,
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            return Error::Io {
+                path: None,
+                source: err.into(),
+            };
+        }
+
+        let (line, column) = (err.line(), err.column());
+        Error::Parse {
+            format: "JSON",
+            path: None,
+            location: (line > 0).then_some(Location {
+                line,
+                column: Some(column),
+            }),
+            message: strip_position(err.to_string(), line, column),
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Self {
+        let location = err.location().map(|l| Location {
+            line: l.line(),
+            column: Some(l.column()),
+        });
+        let message = match location {
+            Some(Location {
+                line,
+                column: Some(column),
+            }) => strip_position(err.to_string(), line, column),
+            _ => err.to_string(),
+        };
+
+        Error::Parse {
+            format: "YAML",
+            path: None,
+            location,
+            message,
+        }
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Error::parse("TOML", err.message())
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Error::parse("TOML", err.to_string())
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        let location = err.position().map(|position| Location {
+            line: position.line() as usize,
+            column: None,
+        });
+
+        let message = err.to_string();
+
+        match err.into_kind() {
+            csv::ErrorKind::Io(source) => Error::Io { path: None, source },
+            _ => Error::Parse {
+                format: "CSV",
+                path: None,
+                location,
+                message,
+            },
+        }
+    }
+}
+
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        Error::parse("Parquet", err.to_string())
+    }
+}
+
+impl From<arrow::error::ArrowError> for Error {
+    fn from(err: arrow::error::ArrowError) -> Self {
+        Error::parse("Parquet", err.to_string())
+    }
+}
+
+impl From<syntect::Error> for Error {
+    fn from(err: syntect::Error) -> Self {
+        Error::Viewer(err.to_string())
+    }
+}
+
+impl From<walkdir::Error> for Error {
+    fn from(err: walkdir::Error) -> Self {
+        let path = err.path().map(Path::to_path_buf);
+        let message = err.to_string();
+        let source = err
+            .into_io_error()
+            .unwrap_or_else(|| io::Error::other(message));
+        Error::Io { path, source }
+    }
+}
+
+impl From<ignore::Error> for Error {
+    fn from(err: ignore::Error) -> Self {
+        match err {
+            ignore::Error::WithPath { path, err } => Error::from(*err).with_path(&path),
+            ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+                Error::from(*err)
+            }
+            ignore::Error::Io(source) => Error::Io { path: None, source },
+            ignore::Error::Loop { ref child, .. } => Error::Io {
+                path: Some(child.clone()),
+                source: io::Error::other(err.to_string()),
+            },
+            other => Error::Io {
+                path: None,
+                source: io::Error::other(other.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "git")]
+impl From<git2::Error> for Error {
+    fn from(err: git2::Error) -> Self {
+        Error::Git(err.message().to_string())
+    }
+}
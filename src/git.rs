@@ -1,30 +1,91 @@
+#[cfg(feature = "git")]
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "git")]
+use std::sync::{Arc, Mutex, PoisonError};
 
 #[cfg(feature = "git")]
-use git2::{Repository, Status};
+use git2::{Repository, Status, SubmoduleIgnore, SubmoduleStatus};
 
-#[derive(Debug, Clone, Copy)]
+// The state of one side of a path: the index against HEAD, or the worktree
+// against the index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum GitStatus {
     Untracked,
     Modified,
     Added,
     Deleted,
     Renamed,
+    TypeChange,
+    Conflicted,
+    // a submodule with modified or untracked files of its own
+    SubmoduleDirty,
     Ignored,
+    #[default]
     Clean,
 }
 
 impl GitStatus {
-    pub fn symbol(&self) -> &'static str {
+    // Statuses that count as a change, in the order they are summarized
+    pub const CHANGES: [GitStatus; 8] = [
+        GitStatus::Conflicted,
+        GitStatus::Modified,
+        GitStatus::TypeChange,
+        GitStatus::Added,
+        GitStatus::Deleted,
+        GitStatus::Renamed,
+        GitStatus::SubmoduleDirty,
+        GitStatus::Untracked,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "untracked" => Some(GitStatus::Untracked),
+            "modified" => Some(GitStatus::Modified),
+            "added" => Some(GitStatus::Added),
+            "deleted" => Some(GitStatus::Deleted),
+            "renamed" => Some(GitStatus::Renamed),
+            "typechange" => Some(GitStatus::TypeChange),
+            "conflicted" => Some(GitStatus::Conflicted),
+            "dirty-submodule" => Some(GitStatus::SubmoduleDirty),
+            "ignored" => Some(GitStatus::Ignored),
+            "clean" => Some(GitStatus::Clean),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GitStatus::Untracked => "untracked",
+            GitStatus::Modified => "modified",
+            GitStatus::Added => "added",
+            GitStatus::Deleted => "deleted",
+            GitStatus::Renamed => "renamed",
+            GitStatus::TypeChange => "typechange",
+            GitStatus::Conflicted => "conflicted",
+            GitStatus::SubmoduleDirty => "dirty-submodule",
+            GitStatus::Ignored => "ignored",
+            GitStatus::Clean => "clean",
+        }
+    }
+
+    pub fn is_change(&self) -> bool {
+        Self::CHANGES.contains(self)
+    }
+
+    // One column of `git status --short`
+    pub fn code(&self) -> char {
         match self {
-            GitStatus::Untracked => "??",
-            GitStatus::Modified => "M ",
-            GitStatus::Added => "A ",
-            GitStatus::Deleted => "D ",
-            GitStatus::Renamed => "R ",
-            GitStatus::Ignored => "I ",
-            GitStatus::Clean => "  ",
+            GitStatus::Untracked => '?',
+            GitStatus::Modified => 'M',
+            GitStatus::Added => 'A',
+            GitStatus::Deleted => 'D',
+            GitStatus::Renamed => 'R',
+            GitStatus::TypeChange => 'T',
+            GitStatus::Conflicted => 'U',
+            GitStatus::SubmoduleDirty => 'm',
+            GitStatus::Ignored => '!',
+            GitStatus::Clean => ' ',
         }
     }
 
@@ -35,122 +96,432 @@ impl GitStatus {
             GitStatus::Added => colored::Color::Green,
             GitStatus::Deleted => colored::Color::Red,
             GitStatus::Renamed => colored::Color::Blue,
+            GitStatus::TypeChange => colored::Color::Magenta,
+            GitStatus::Conflicted => colored::Color::BrightRed,
+            GitStatus::SubmoduleDirty => colored::Color::Yellow,
             GitStatus::Ignored => colored::Color::BrightBlack,
             GitStatus::Clean => colored::Color::White,
         }
     }
+
+    // The earlier of two changes in CHANGES order
+    #[cfg(feature = "git")]
+    fn dominant(self, other: Option<GitStatus>) -> GitStatus {
+        match other {
+            Some(other) if Self::position(other) < Self::position(self) => other,
+            _ => self,
+        }
+    }
+
+    #[cfg(feature = "git")]
+    fn position(status: GitStatus) -> usize {
+        Self::CHANGES
+            .iter()
+            .position(|change| *change == status)
+            .unwrap_or(Self::CHANGES.len())
+    }
+}
+
+// The two columns of `git status --short`. Untracked, ignored and conflicted
+// paths have the same status on both sides.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GitState {
+    pub index: GitStatus,
+    pub worktree: GitStatus,
+}
+
+impl GitState {
+    pub const CLEAN: GitState = GitState {
+        index: GitStatus::Clean,
+        worktree: GitStatus::Clean,
+    };
+
+    pub fn both(status: GitStatus) -> Self {
+        Self {
+            index: status,
+            worktree: status,
+        }
+    }
+
+    // Clean only matches when both sides are
+    pub fn has(&self, status: GitStatus) -> bool {
+        match status {
+            GitStatus::Clean => *self == Self::CLEAN,
+            _ => self.index == status || self.worktree == status,
+        }
+    }
+
+    pub fn is_change(&self) -> bool {
+        self.index.is_change() || self.worktree.is_change()
+    }
+
+    // What the path is counted as in directory summaries, staged changes first
+    pub fn primary(&self) -> GitStatus {
+        if self.index.is_change() {
+            self.index
+        } else {
+            self.worktree
+        }
+    }
+
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.index.code(), self.worktree.code())
+    }
+}
+
+// The changes below a directory, counted the way git status lists them: an
+// untracked directory counts once, however many files it holds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitSummary {
+    // in GitStatus::CHANGES order, without zero counts
+    counts: Vec<(GitStatus, usize)>,
+    staged: Option<GitStatus>,
+    unstaged: Option<GitStatus>,
 }
 
-#[derive(Clone)]
+impl GitSummary {
+    pub fn counts(&self) -> &[(GitStatus, usize)] {
+        &self.counts
+    }
+
+    pub fn count(&self, status: GitStatus) -> usize {
+        self.counts
+            .iter()
+            .find(|(counted, _)| *counted == status)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|(_, count)| count).sum()
+    }
+
+    // The state a directory is marked with: the first staged and the first
+    // unstaged change below it
+    pub fn mark(&self) -> GitState {
+        let worktree = self.unstaged.unwrap_or_default();
+        let index = match self.staged {
+            Some(staged) => staged,
+            None if worktree == GitStatus::Untracked => GitStatus::Untracked,
+            None => GitStatus::Clean,
+        };
+        GitState { index, worktree }
+    }
+
+    #[cfg(feature = "git")]
+    fn add(&mut self, state: GitState) {
+        let primary = state.primary();
+        match self
+            .counts
+            .iter_mut()
+            .find(|(counted, _)| *counted == primary)
+        {
+            Some((_, count)) => *count += 1,
+            None => {
+                self.counts.push((primary, 1));
+                self.counts
+                    .sort_by_key(|(status, _)| GitStatus::position(*status));
+            }
+        }
+
+        if state.index.is_change() && state.index != GitStatus::Untracked {
+            self.staged = Some(state.index.dominant(self.staged));
+        }
+        if state.worktree.is_change() {
+            self.unstaged = Some(state.worktree.dominant(self.unstaged));
+        }
+    }
+}
+
+// The statuses of one repository and the summaries of its directories, keyed
+// by paths relative to its workdir
+#[cfg(feature = "git")]
+#[derive(Debug, Default)]
+struct RepoStatus {
+    statuses: HashMap<PathBuf, GitState>,
+    summaries: HashMap<PathBuf, GitSummary>,
+}
+
+// The repository a directory belongs to and the directory's path inside it
+#[cfg(feature = "git")]
+type Location = Option<(Arc<RepoStatus>, PathBuf)>;
+
+#[cfg(feature = "git")]
+#[derive(Debug, Default)]
+struct Repos {
+    // keyed by directories as they were asked about
+    dirs: HashMap<PathBuf, Location>,
+    // keyed by canonical workdir, a repository reached through different
+    // paths is only read once
+    loaded: HashMap<PathBuf, Arc<RepoStatus>>,
+}
+
+// Repositories are discovered from the paths asked about rather than from the
+// current directory, so a tree can be anywhere inside a repository or span
+// several independent ones, each path resolving against its own repository.
+#[derive(Debug, Clone, Default)]
 pub struct GitInfo {
     #[cfg(feature = "git")]
-    repo: Option<std::sync::Arc<Repository>>,
-    status_cache: HashMap<std::path::PathBuf, GitStatus>,
+    repos: Arc<Mutex<Repos>>,
 }
 
 impl GitInfo {
-    pub fn new(root_path: &Path) -> Self {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // None when the path is not inside a repository
+    pub fn status(&self, _path: &Path) -> Option<GitState> {
         #[cfg(feature = "git")]
         {
-            let repo = Repository::discover(root_path).ok();
-            let mut git_info = Self {
-                repo: repo.map(std::sync::Arc::new),
-                status_cache: HashMap::new(),
+            let (dir, name) = match (_path.parent(), _path.file_name()) {
+                (Some(parent), Some(name)) => (parent, Some(name)),
+                _ => (_path, None),
             };
-
-            git_info.load_status();
-            git_info
+            let mut repos = self.repos.lock().unwrap_or_else(PoisonError::into_inner);
+            let (repo, relative) = repos.locate(dir)?;
+            let relative = match name {
+                Some(name) => relative.join(name),
+                None => relative,
+            };
+            Some(repo.status_of(&relative))
         }
 
         #[cfg(not(feature = "git"))]
+        None
+    }
+
+    // The changes below a directory, None when there are none. A directory
+    // with a repository of its own is summarized from that repository.
+    pub fn summary(&self, _dir: &Path) -> Option<GitSummary> {
+        #[cfg(feature = "git")]
         {
-            Self {
-                status_cache: HashMap::new(),
-            }
+            let mut repos = self.repos.lock().unwrap_or_else(PoisonError::into_inner);
+            let (repo, relative) = repos.locate(_dir)?;
+            repo.summaries.get(&relative).cloned()
         }
+
+        #[cfg(not(feature = "git"))]
+        None
     }
 
-    #[cfg(feature = "git")]
-    fn load_status(&mut self) {
-        if let Some(ref repo) = self.repo {
-            let mut status_opts = git2::StatusOptions::new();
-            status_opts.include_ignored(true);
-            status_opts.include_untracked(true);
-
-            if let Ok(statuses) = repo.statuses(Some(&mut status_opts)) {
-                for entry in statuses.iter() {
-                    if let Some(path) = entry.path() {
-                        let path_buf = std::path::PathBuf::from(path);
-                        let status = self.convert_status(entry.status());
-                        self.status_cache.insert(path_buf, status);
+    // Changed paths below `root` that are not on disk, such as deleted files.
+    // Repositories nested below the root are only known once the walk has
+    // asked about them, so this is called after it.
+    pub fn missing(&self, _root: &Path) -> Vec<PathBuf> {
+        #[cfg(feature = "git")]
+        {
+            let mut repos = self.repos.lock().unwrap_or_else(PoisonError::into_inner);
+            repos.locate(_root);
+
+            let mut missing = Vec::new();
+            for (dir, location) in &repos.dirs {
+                let Some((repo, relative)) = location else {
+                    continue;
+                };
+                // each repository from the root or from its own top directory
+                if dir != _root && !(relative.as_os_str().is_empty() && dir.starts_with(_root)) {
+                    continue;
+                }
+                for (path, state) in &repo.statuses {
+                    let Ok(below) = path.strip_prefix(relative) else {
+                        continue;
+                    };
+                    let path = dir.join(below);
+                    if state.is_change() && std::fs::symlink_metadata(&path).is_err() {
+                        missing.push(path);
                     }
                 }
             }
+            missing.sort();
+            missing.dedup();
+            missing
         }
+
+        #[cfg(not(feature = "git"))]
+        Vec::new()
     }
 
-    #[cfg(not(feature = "git"))]
-    fn load_status(&mut self) {
-        // No ops for non git repositories
+    pub fn get_status(&self, path: &Path) -> GitState {
+        self.status(path).unwrap_or_default()
     }
 
-    #[cfg(feature = "git")]
-    fn convert_status(&self, flags: Status) -> GitStatus {
-        // Check index status first (Staged changes)
-        if flags.contains(Status::INDEX_NEW) {
-            GitStatus::Added
-        } else if flags.contains(Status::INDEX_MODIFIED) {
-            GitStatus::Modified
-        } else if flags.contains(Status::INDEX_DELETED) {
-            GitStatus::Deleted
-        } else if flags.contains(Status::INDEX_RENAMED) {
-            GitStatus::Renamed
-        }
-        // Check working tree status (unstaged change)
-        else if flags.contains(Status::WT_NEW) {
-            GitStatus::Untracked
-        } else if flags.contains(Status::WT_MODIFIED) {
-            GitStatus::Modified
-        } else if flags.contains(Status::WT_DELETED) {
-            GitStatus::Deleted
-        } else if flags.contains(Status::WT_RENAMED) {
-            GitStatus::Renamed
-        } else if flags.contains(Status::IGNORED) {
-            GitStatus::Ignored
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.get_status(path).has(GitStatus::Ignored)
+    }
+}
+
+#[cfg(feature = "git")]
+impl Repos {
+    fn locate(&mut self, dir: &Path) -> Location {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
         } else {
-            GitStatus::Clean
+            dir
+        };
+        if let Some(location) = self.dirs.get(dir) {
+            return location.clone();
         }
+
+        // a .git directory, or file for submodules and worktrees, starts a
+        // repository of its own, anything else belongs to its parent's
+        let location = if dir.join(".git").exists() {
+            Repository::open(dir)
+                .ok()
+                .and_then(|repo| self.load(&repo))
+                .map(|repo| (repo, PathBuf::new()))
+        } else {
+            match (dir.parent(), dir.file_name()) {
+                (Some(parent), Some(name)) => self
+                    .locate(parent)
+                    .map(|(repo, relative)| (repo, relative.join(name))),
+                // ".", ".." or the filesystem root, git searches upwards itself
+                _ => self.discover(dir),
+            }
+        };
+        self.dirs.insert(dir.to_path_buf(), location.clone());
+        location
+    }
+
+    fn discover(&mut self, dir: &Path) -> Location {
+        let repo = Repository::discover(dir).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let relative = dir
+            .canonicalize()
+            .ok()?
+            .strip_prefix(&workdir)
+            .ok()?
+            .to_path_buf();
+        Some((self.load(&repo)?, relative))
     }
 
-    pub fn get_status(&self, path: &Path) -> GitStatus {
-        if let Some(status) = self.status_cache.get(path) {
-            return *status;
+    // Bare repositories have no files to report on
+    fn load(&mut self, repo: &Repository) -> Option<Arc<RepoStatus>> {
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        if let Some(loaded) = self.loaded.get(&workdir) {
+            return Some(loaded.clone());
         }
 
-        #[cfg(feature = "git")]
-        if let Some(ref repo) = self.repo {
-            if let Some(workdir) = repo.workdir() {
-                if let Ok(relative_path) = path.strip_prefix(workdir) {
-                    // Remove "./" prefix if present
-                    let relative_path = relative_path.strip_prefix("./").unwrap_or(relative_path);
-
-                    if let Some(status) = self.status_cache.get(relative_path) {
-                        return *status;
-                    }
+        let mut status = RepoStatus::default();
+        let mut status_opts = git2::StatusOptions::new();
+        status_opts.include_ignored(true);
+        status_opts.include_untracked(true);
+        if let Ok(statuses) = repo.statuses(Some(&mut status_opts)) {
+            for entry in statuses.iter() {
+                if let Some(path) = entry.path() {
+                    status
+                        .statuses
+                        .insert(PathBuf::from(path), convert_status(entry.status()));
+                }
+            }
+        }
+        Self::mark_dirty_submodules(repo, &mut status.statuses);
 
-                    let relative_path_str = relative_path.to_string_lossy().replace("\\", "/");
-                    let normalized_path = std::path::PathBuf::from(relative_path_str);
-                    if let Some(status) = self.status_cache.get(&normalized_path) {
-                        return *status;
-                    }
+        for (path, state) in &status.statuses {
+            if state.is_change() {
+                for dir in path.ancestors().skip(1) {
+                    status
+                        .summaries
+                        .entry(dir.to_path_buf())
+                        .or_default()
+                        .add(*state);
                 }
             }
         }
 
-        GitStatus::Clean
+        let status = Arc::new(status);
+        self.loaded.insert(workdir, status.clone());
+        Some(status)
     }
+}
 
-    pub fn is_ignored(&self, path: &Path) -> bool {
-        matches!(self.get_status(path), GitStatus::Ignored)
+#[cfg(feature = "git")]
+impl Repos {
+    // A submodule whose checkout has changes of its own, as opposed to one
+    // pointing at another commit, which is an ordinary modification
+    fn mark_dirty_submodules(repo: &Repository, statuses: &mut HashMap<PathBuf, GitState>) {
+        let Ok(submodules) = repo.submodules() else {
+            return;
+        };
+        for submodule in submodules {
+            let Some(name) = submodule.name() else {
+                continue;
+            };
+            let Ok(flags) = repo.submodule_status(name, SubmoduleIgnore::None) else {
+                continue;
+            };
+            let dirty = flags.intersects(
+                SubmoduleStatus::WD_INDEX_MODIFIED
+                    | SubmoduleStatus::WD_WD_MODIFIED
+                    | SubmoduleStatus::WD_UNTRACKED,
+            );
+            if dirty && !flags.contains(SubmoduleStatus::WD_MODIFIED) {
+                statuses
+                    .entry(submodule.path().to_path_buf())
+                    .or_default()
+                    .worktree = GitStatus::SubmoduleDirty;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "git")]
+impl RepoStatus {
+    // git reports an untracked or ignored directory once, its contents share
+    // its status
+    fn status_of(&self, relative: &Path) -> GitState {
+        if let Some(state) = self.statuses.get(relative) {
+            return *state;
+        }
+        relative
+            .ancestors()
+            .skip(1)
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .filter_map(|ancestor| self.statuses.get(ancestor))
+            .find(|state| state.has(GitStatus::Untracked) || state.has(GitStatus::Ignored))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "git")]
+fn convert_status(flags: Status) -> GitState {
+    if flags.contains(Status::CONFLICTED) {
+        return GitState::both(GitStatus::Conflicted);
     }
+    if flags.contains(Status::IGNORED) {
+        return GitState::both(GitStatus::Ignored);
+    }
+    if flags.contains(Status::WT_NEW) {
+        return GitState::both(GitStatus::Untracked);
+    }
+
+    // staged changes
+    let index = if flags.contains(Status::INDEX_NEW) {
+        GitStatus::Added
+    } else if flags.contains(Status::INDEX_MODIFIED) {
+        GitStatus::Modified
+    } else if flags.contains(Status::INDEX_DELETED) {
+        GitStatus::Deleted
+    } else if flags.contains(Status::INDEX_RENAMED) {
+        GitStatus::Renamed
+    } else if flags.contains(Status::INDEX_TYPECHANGE) {
+        GitStatus::TypeChange
+    } else {
+        GitStatus::Clean
+    };
+
+    // unstaged changes
+    let worktree = if flags.contains(Status::WT_MODIFIED) {
+        GitStatus::Modified
+    } else if flags.contains(Status::WT_DELETED) {
+        GitStatus::Deleted
+    } else if flags.contains(Status::WT_RENAMED) {
+        GitStatus::Renamed
+    } else if flags.contains(Status::WT_TYPECHANGE) {
+        GitStatus::TypeChange
+    } else {
+        GitStatus::Clean
+    };
+
+    GitState { index, worktree }
 }
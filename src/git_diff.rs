@@ -0,0 +1,388 @@
+use crate::git::GitStatus;
+use crate::revision::{open_repository, relative_path};
+use crate::{Config, Error, PathFilter, Result};
+use colored::*;
+use git2::{Delta, DiffFindOptions, Oid, Patch, RevparseMode, Tree};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+// Statuses a file can have in a diff, in the order they are summarized
+const STATUSES: [GitStatus; 5] = [
+    GitStatus::Added,
+    GitStatus::Modified,
+    GitStatus::Deleted,
+    GitStatus::Renamed,
+    GitStatus::TypeChange,
+];
+
+// One changed file, or a directory holding some. `path` is relative to the
+// root.
+#[derive(Debug, Clone)]
+pub struct GitDiffEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    // None for directories
+    pub status: Option<GitStatus>,
+    // where a renamed file came from, relative to the root when it was inside
+    // it and to the repository otherwise
+    pub old_path: Option<PathBuf>,
+    // directories carry the totals of the files below them
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub binary: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitDiffNode {
+    pub entry: GitDiffEntry,
+    pub children: Vec<GitDiffNode>,
+}
+
+// --git-diff: the files changed between two revisions, or between a revision
+// and the worktree, as a tree of the directories holding them
+#[derive(Debug, Clone)]
+pub struct GitDiff {
+    pub root: PathBuf,
+    pub from: String,
+    pub to: String,
+    pub nodes: Vec<GitDiffNode>,
+}
+
+impl GitDiff {
+    // `range` is `A..B`, `A...B` to compare B with the merge base of both,
+    // or a single revision to compare with the worktree, like `git diff`.
+    // Only changes below `root` are kept, and the same path filters as for a
+    // tree apply.
+    pub fn compare(config: &Config, root: &Path, range: &str) -> Result<Self> {
+        let (repo, workdir) = open_repository(root)?;
+        let prefix = relative_path(&workdir, root)?;
+
+        let unknown =
+            |e: git2::Error| Error::Git(format!("unknown revision `{}`: {}", range, e.message()));
+        let spec = repo.revparse(range).map_err(unknown)?;
+        let (left, right) = match range.split_once("...") {
+            Some(sides) => sides,
+            None => range.split_once("..").unwrap_or((range, "")),
+        };
+        let label = |side: &str| if side.is_empty() { "HEAD" } else { side }.to_string();
+
+        let tree_of = |id: Oid| -> Result<Tree> {
+            repo.find_object(id, None)?.peel_to_tree().map_err(unknown)
+        };
+        let head = || -> Result<Oid> { Ok(repo.revparse_single("HEAD").map_err(unknown)?.id()) };
+        let from_id = match spec.from() {
+            Some(object) => object.id(),
+            None => head()?,
+        };
+
+        let (from, to, to_tree) = if spec.mode().contains(RevparseMode::SINGLE) {
+            (label(left), "worktree".to_string(), None)
+        } else {
+            let to_id = match spec.to() {
+                Some(object) => object.id(),
+                None => head()?,
+            };
+            if spec.mode().contains(RevparseMode::MERGE_BASE) {
+                let base = repo.merge_base(from_id, to_id)?;
+                let short = base.to_string()[..7].to_string();
+                (short, label(right), Some((base, to_id)))
+            } else {
+                (label(left), label(right), Some((from_id, to_id)))
+            }
+        };
+
+        let mut diff = match to_tree {
+            Some((from_id, to_id)) => {
+                repo.diff_tree_to_tree(Some(&tree_of(from_id)?), Some(&tree_of(to_id)?), None)?
+            }
+            None => repo.diff_tree_to_workdir_with_index(Some(&tree_of(from_id)?), None)?,
+        };
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        let patterns = PathFilter::new(config, root)?;
+        let mut entries: BTreeMap<PathBuf, GitDiffEntry> = BTreeMap::new();
+        for (index, delta) in diff.deltas().enumerate() {
+            let status = match delta.status() {
+                Delta::Added | Delta::Copied => GitStatus::Added,
+                Delta::Modified => GitStatus::Modified,
+                Delta::Deleted => GitStatus::Deleted,
+                Delta::Renamed => GitStatus::Renamed,
+                Delta::Typechange => GitStatus::TypeChange,
+                _ => continue,
+            };
+            let file = match status {
+                GitStatus::Deleted => delta.old_file(),
+                _ => delta.new_file(),
+            };
+            let Some(relative) = file.path().and_then(|path| path.strip_prefix(&prefix).ok())
+            else {
+                continue;
+            };
+            if !Self::matches_filters(config, &patterns, root, relative) {
+                continue;
+            }
+
+            let old_path = delta
+                .old_file()
+                .path()
+                .filter(|_| status == GitStatus::Renamed)
+                .map(|old| old.strip_prefix(&prefix).unwrap_or(old).to_path_buf());
+
+            let patch = Patch::from_diff(&diff, index)?;
+            let binary = patch
+                .as_ref()
+                .is_some_and(|patch| patch.delta().flags().is_binary());
+            let (_, lines_added, lines_removed) = match &patch {
+                Some(patch) => patch.line_stats()?,
+                None => (0, 0, 0),
+            };
+
+            for dir in relative.ancestors().skip(1) {
+                if dir.as_os_str().is_empty() {
+                    break;
+                }
+                let totals = entries
+                    .entry(dir.to_path_buf())
+                    .or_insert_with(|| Self::entry(dir, None));
+                totals.lines_added += lines_added;
+                totals.lines_removed += lines_removed;
+            }
+            let mut entry = Self::entry(relative, Some(status));
+            entry.old_path = old_path;
+            entry.lines_added = lines_added;
+            entry.lines_removed = lines_removed;
+            entry.binary = binary;
+            entries.insert(relative.to_path_buf(), entry);
+        }
+
+        // the map is ordered by path, so siblings stay sorted by name
+        let mut entries_by_parent: HashMap<PathBuf, Vec<GitDiffEntry>> = HashMap::new();
+        for (path, entry) in entries {
+            let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            entries_by_parent.entry(parent).or_default().push(entry);
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            from,
+            to,
+            nodes: Self::build_nodes(Path::new(""), &mut entries_by_parent),
+        })
+    }
+
+    fn entry(path: &Path, status: Option<GitStatus>) -> GitDiffEntry {
+        GitDiffEntry {
+            path: path.to_path_buf(),
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            is_dir: status.is_none(),
+            status,
+            old_path: None,
+            lines_added: 0,
+            lines_removed: 0,
+            binary: false,
+        }
+    }
+
+    // --exclude, --include, --regex and -e apply. Hidden files are kept
+    // without --all, like git diff a change to .github/ is still a change.
+    fn matches_filters(
+        config: &Config,
+        patterns: &PathFilter,
+        root: &Path,
+        relative: &Path,
+    ) -> bool {
+        if !patterns.matches(&root.join(relative), false) {
+            return false;
+        }
+
+        let extensions = config.get_extension();
+        extensions.is_empty()
+            || relative
+                .extension()
+                .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase()))
+    }
+
+    fn build_nodes(
+        parent: &Path,
+        entries_by_parent: &mut HashMap<PathBuf, Vec<GitDiffEntry>>,
+    ) -> Vec<GitDiffNode> {
+        let Some(entries) = entries_by_parent.remove(parent) else {
+            return Vec::new();
+        };
+
+        entries
+            .into_iter()
+            .map(|entry| {
+                let children = Self::build_nodes(&entry.path, entries_by_parent);
+                GitDiffNode { entry, children }
+            })
+            .collect()
+    }
+
+    // Every entry in pre-order, the same order as the rendered tree
+    pub fn entries(&self) -> Vec<&GitDiffEntry> {
+        fn visit<'a>(nodes: &'a [GitDiffNode], out: &mut Vec<&'a GitDiffEntry>) {
+            for node in nodes {
+                out.push(&node.entry);
+                visit(&node.children, out);
+            }
+        }
+
+        let mut entries = Vec::new();
+        visit(&self.nodes, &mut entries);
+        entries
+    }
+
+    // The changed files, without the directories holding them
+    pub fn changes(&self) -> Vec<&GitDiffEntry> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .collect()
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.nodes.is_empty()
+    }
+
+    pub fn count(&self, status: GitStatus) -> usize {
+        self.changes()
+            .into_iter()
+            .filter(|entry| entry.status == Some(status))
+            .count()
+    }
+
+    // Lines added and removed over all files
+    pub fn totals(&self) -> (usize, usize) {
+        self.changes()
+            .into_iter()
+            .fold((0, 0), |(added, removed), entry| {
+                (added + entry.lines_added, removed + entry.lines_removed)
+            })
+    }
+
+    pub fn render(&self, use_colors: bool) -> String {
+        let mut output = format!("{} -> {}\n", self.from, self.to);
+        if use_colors {
+            output = output.blue().bold().to_string();
+        }
+        Self::render_nodes(&mut output, &self.nodes, "", use_colors);
+
+        let (added, removed) = self.totals();
+        let mut summary: Vec<String> = STATUSES
+            .iter()
+            .map(|status| format!("{} {}", self.count(*status), status.label()))
+            .collect();
+        summary.push(format!("+{} -{} lines", added, removed));
+        output.push_str(&format!("\n{}\n", summary.join(", ")));
+        output
+    }
+
+    fn render_nodes(output: &mut String, nodes: &[GitDiffNode], prefix: &str, use_colors: bool) {
+        for (i, node) in nodes.iter().enumerate() {
+            let is_last = i == nodes.len() - 1;
+            let connector = if is_last { "'-- " } else { "|-- " };
+            let new_prefix = if is_last { "   " } else { "|   " };
+
+            output.push_str(&format!(
+                "{}{}{}\n",
+                prefix,
+                connector,
+                Self::format_entry(&node.entry, use_colors)
+            ));
+
+            if !node.children.is_empty() {
+                let child_prefix = format!("{}{}", prefix, new_prefix);
+                Self::render_nodes(output, &node.children, &child_prefix, use_colors);
+            }
+        }
+    }
+
+    fn format_entry(entry: &GitDiffEntry, use_colors: bool) -> String {
+        let symbol = match entry.status {
+            Some(status) if use_colors => format!("{} ", status.code())
+                .color(status.color())
+                .to_string(),
+            Some(status) => format!("{} ", status.code()),
+            None => "  ".to_string(),
+        };
+        let name = if entry.is_dir && use_colors {
+            entry.name.blue().to_string()
+        } else {
+            entry.name.clone()
+        };
+
+        let lines = if entry.binary {
+            "[binary]".to_string()
+        } else if use_colors {
+            format!(
+                "[{} {}]",
+                format!("+{}", entry.lines_added).green(),
+                format!("-{}", entry.lines_removed).red()
+            )
+        } else {
+            format!("[+{} -{}]", entry.lines_added, entry.lines_removed)
+        };
+
+        let mut output = format!("{}{}{}", symbol, name, lines);
+        if let Some(old_path) = &entry.old_path {
+            let renamed = format!("<- {}", old_path.display());
+            if use_colors {
+                output.push_str(&renamed.dimmed().to_string());
+            } else {
+                output.push_str(&renamed);
+            }
+        }
+        output
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        fn entry_json(entry: &GitDiffEntry) -> serde_json::Value {
+            json!({
+                "name": entry.name,
+                "path": entry.path,
+                "is_dir": entry.is_dir,
+                "status": entry.status.map(|status| status.label()),
+                "old_path": entry.old_path,
+                "lines_added": entry.lines_added,
+                "lines_removed": entry.lines_removed,
+                "binary": entry.binary,
+            })
+        }
+
+        fn nodes_json(nodes: &[GitDiffNode]) -> serde_json::Value {
+            nodes
+                .iter()
+                .map(|node| {
+                    let mut value = entry_json(&node.entry);
+                    if !node.children.is_empty() {
+                        value["children"] = nodes_json(&node.children);
+                    }
+                    value
+                })
+                .collect()
+        }
+
+        let mut summary: serde_json::Map<String, serde_json::Value> = STATUSES
+            .iter()
+            .map(|status| (status.label().to_string(), json!(self.count(*status))))
+            .collect();
+        let (added, removed) = self.totals();
+        summary.insert("lines_added".to_string(), json!(added));
+        summary.insert("lines_removed".to_string(), json!(removed));
+
+        json!({
+            "root": self.root,
+            "from": self.from,
+            "to": self.to,
+            "tree": nodes_json(&self.nodes),
+            "changes": self.changes().into_iter().map(entry_json).collect::<Vec<_>>(),
+            "summary": summary,
+        })
+    }
+}
@@ -0,0 +1,477 @@
+use crate::tree::TreeNode;
+use crate::{FileViewer, Result, TreeDisplay, ViewerFormat};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserAction {
+    Continue,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserFocus {
+    Tree,
+    Viewer,
+}
+
+#[derive(Debug, Clone)]
+pub struct BrowserRow {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub has_children: bool,
+    pub expanded: bool,
+    pub prefix: String,
+    pub label: String,
+}
+
+struct ViewerPane {
+    path: PathBuf,
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+pub struct TreeBrowser {
+    root: PathBuf,
+    nodes: Vec<TreeNode>,
+    display: TreeDisplay,
+    expanded: HashSet<PathBuf>,
+    rows: Vec<BrowserRow>,
+    selected: usize,
+    scroll: usize,
+    page_height: usize,
+    focus: BrowserFocus,
+    viewer: Option<ViewerPane>,
+}
+
+impl TreeBrowser {
+    pub(crate) fn new(
+        root: &Path,
+        nodes: Vec<TreeNode>,
+        display: TreeDisplay,
+        expand_depth: usize,
+    ) -> Self {
+        let mut browser = Self {
+            root: root.to_path_buf(),
+            nodes,
+            display,
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            page_height: 20,
+            focus: BrowserFocus::Tree,
+            viewer: None,
+        };
+        browser.expand_to_depth(expand_depth);
+        browser
+    }
+
+    pub fn rows(&self) -> &[BrowserRow] {
+        &self.rows
+    }
+
+    pub fn selected_path(&self) -> Option<&Path> {
+        self.rows.get(self.selected).map(|row| row.path.as_path())
+    }
+
+    pub fn viewer_path(&self) -> Option<&Path> {
+        self.viewer.as_ref().map(|viewer| viewer.path.as_path())
+    }
+
+    pub fn focus(&self) -> BrowserFocus {
+        self.focus
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
+
+        let result = self.event_loop(&mut stdout);
+
+        execute!(stdout, cursor::Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write) -> Result<()> {
+        loop {
+            let (width, height) = terminal::size()?;
+            self.render(out, width as usize, height as usize)?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && self.handle_key(key) == BrowserAction::Quit {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> BrowserAction {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return BrowserAction::Quit;
+        }
+
+        match self.focus {
+            BrowserFocus::Tree => self.handle_tree_key(key.code),
+            BrowserFocus::Viewer => self.handle_viewer_key(key.code),
+        }
+    }
+
+    fn handle_tree_key(&mut self, code: KeyCode) -> BrowserAction {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if self.viewer.is_some() {
+                    self.viewer = None;
+                } else {
+                    return BrowserAction::Quit;
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(self.page_height as isize)),
+            KeyCode::PageDown => self.move_selection(self.page_height as isize),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(self.rows.len().saturating_sub(1)),
+            KeyCode::Left | KeyCode::Char('h') => self.collapse_or_parent(),
+            KeyCode::Right | KeyCode::Char('l') => self.expand_or_child(),
+            KeyCode::Char(' ') => self.toggle_selected(),
+            KeyCode::Enter => self.activate_selected(),
+            KeyCode::Tab if self.viewer.is_some() => self.focus = BrowserFocus::Viewer,
+            KeyCode::Char('e') => self.expand_to_depth(usize::MAX),
+            KeyCode::Char('c') => self.expand_to_depth(1),
+            KeyCode::Char(c @ '1'..='9') => self.expand_to_depth(c as usize - '0' as usize),
+            _ => {}
+        }
+        BrowserAction::Continue
+    }
+
+    fn handle_viewer_key(&mut self, code: KeyCode) -> BrowserAction {
+        let page = self.page_height.max(1);
+        let Some(viewer) = self.viewer.as_mut() else {
+            self.focus = BrowserFocus::Tree;
+            return BrowserAction::Continue;
+        };
+        let last_line = viewer.lines.len().saturating_sub(1);
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.viewer = None;
+                self.focus = BrowserFocus::Tree;
+            }
+            KeyCode::Tab | KeyCode::Left | KeyCode::Char('h') => self.focus = BrowserFocus::Tree,
+            KeyCode::Up | KeyCode::Char('k') => viewer.scroll = viewer.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                viewer.scroll = (viewer.scroll + 1).min(last_line)
+            }
+            KeyCode::PageUp => viewer.scroll = viewer.scroll.saturating_sub(page),
+            KeyCode::PageDown => viewer.scroll = (viewer.scroll + page).min(last_line),
+            KeyCode::Home | KeyCode::Char('g') => viewer.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => viewer.scroll = last_line,
+            _ => {}
+        }
+        BrowserAction::Continue
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let target = (self.selected as isize + delta).clamp(0, self.rows.len() as isize - 1);
+        self.select(target as usize);
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn collapse_or_parent(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else {
+            return;
+        };
+
+        if row.is_dir && row.expanded {
+            let path = row.path.clone();
+            self.expanded.remove(&path);
+            self.refresh_rows(Some(path));
+        } else if let Some(parent) = row.path.parent() {
+            if let Some(index) = self.rows.iter().position(|r| r.path == parent) {
+                self.selected = index;
+            }
+        }
+    }
+
+    fn expand_or_child(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else {
+            return;
+        };
+
+        if row.is_dir && !row.expanded {
+            let path = row.path.clone();
+            self.expanded.insert(path.clone());
+            self.refresh_rows(Some(path));
+        } else if row.expanded && row.has_children {
+            self.move_selection(1);
+        }
+    }
+
+    fn toggle_selected(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else {
+            return;
+        };
+        if !row.is_dir {
+            return;
+        }
+
+        let path = row.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path.clone());
+        }
+        self.refresh_rows(Some(path));
+    }
+
+    fn activate_selected(&mut self) {
+        let Some(row) = self.rows.get(self.selected) else {
+            return;
+        };
+
+        if row.is_dir {
+            self.toggle_selected();
+        } else {
+            let path = row.path.clone();
+            self.open_viewer(path);
+        }
+    }
+
+    fn open_viewer(&mut self, path: PathBuf) {
+        let content = match FileViewer::new(ViewerFormat::Auto).view_file(&path) {
+            Ok(content) => content,
+            Err(e) => format!("Error viewing file {}: {}", path.display(), e),
+        };
+
+        self.viewer = Some(ViewerPane {
+            path,
+            lines: content
+                .lines()
+                .map(|line| line.replace('\t', "    "))
+                .collect(),
+            scroll: 0,
+        });
+        self.focus = BrowserFocus::Viewer;
+    }
+
+    fn expand_to_depth(&mut self, depth: usize) {
+        let selected = self.selected_path().map(Path::to_path_buf);
+        self.expanded.clear();
+        Self::collect_expanded(&self.nodes, 1, depth, &mut self.expanded);
+        self.refresh_rows(selected);
+    }
+
+    fn collect_expanded(
+        nodes: &[TreeNode],
+        depth: usize,
+        max_depth: usize,
+        expanded: &mut HashSet<PathBuf>,
+    ) {
+        if depth >= max_depth {
+            return;
+        }
+        for node in nodes.iter().filter(|node| node.info.is_dir) {
+            expanded.insert(node.info.path.clone());
+            Self::collect_expanded(&node.children, depth + 1, max_depth, expanded);
+        }
+    }
+
+    fn refresh_rows(&mut self, keep_selected: Option<PathBuf>) {
+        let mut rows = Vec::new();
+        self.flatten(&self.nodes, 0, "", &mut rows);
+        self.rows = rows;
+
+        // keep the cursor on the same entry, or on its closest visible ancestor
+        let mut candidate = keep_selected.as_deref();
+        while let Some(path) = candidate {
+            if let Some(index) = self.rows.iter().position(|row| row.path == path) {
+                self.selected = index;
+                return;
+            }
+            candidate = path.parent().filter(|parent| *parent != self.root);
+        }
+        self.select(self.selected);
+    }
+
+    fn flatten(&self, nodes: &[TreeNode], depth: usize, prefix: &str, rows: &mut Vec<BrowserRow>) {
+        for (i, node) in nodes.iter().enumerate() {
+            let is_last = i == nodes.len() - 1;
+            let connector = if is_last { "'-- " } else { "|-- " };
+            let expanded = node.info.is_dir && self.expanded.contains(&node.info.path);
+
+            let label = self
+                .display
+                .format_file_info(&node.info)
+                .unwrap_or_else(|_| node.info.name.clone());
+
+            rows.push(BrowserRow {
+                path: node.info.path.clone(),
+                depth,
+                is_dir: node.info.is_dir,
+                has_children: !node.children.is_empty(),
+                expanded,
+                prefix: format!("{}{}", prefix, connector),
+                label,
+            });
+
+            if expanded {
+                let child_prefix = format!("{}{}", prefix, if is_last { "   " } else { "|   " });
+                self.flatten(&node.children, depth + 1, &child_prefix, rows);
+            }
+        }
+    }
+
+    fn render(&mut self, out: &mut impl Write, width: usize, height: usize) -> Result<()> {
+        // one line for the header, one for the status bar
+        self.page_height = height.saturating_sub(2).max(1);
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + self.page_height {
+            self.scroll = self.selected + 1 - self.page_height;
+        }
+
+        let tree_width = if self.viewer.is_some() {
+            (width * 2 / 5).max(20).min(width)
+        } else {
+            width
+        };
+
+        queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        queue!(
+            out,
+            SetForegroundColor(Color::Blue),
+            SetAttribute(Attribute::Bold),
+            Print(fit_to_width(&self.root.display().to_string(), width)),
+            SetAttribute(Attribute::Reset),
+            ResetColor
+        )?;
+
+        for (line, row) in self
+            .rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.page_height)
+        {
+            let y = (line - self.scroll + 1) as u16;
+            let marker = match (row.is_dir, row.expanded) {
+                (true, true) => "- ",
+                (true, false) => "+ ",
+                (false, _) => "  ",
+            };
+            let text = fit_to_width(
+                &format!("{}{}{}", row.prefix, marker, row.label),
+                tree_width,
+            );
+
+            queue!(out, cursor::MoveTo(0, y))?;
+            if line == self.selected {
+                let attribute = if self.focus == BrowserFocus::Tree {
+                    Attribute::Reverse
+                } else {
+                    Attribute::Underlined
+                };
+                queue!(out, SetAttribute(attribute))?;
+            }
+            if row.is_dir {
+                queue!(out, SetForegroundColor(Color::Blue))?;
+            }
+            queue!(out, Print(text), SetAttribute(Attribute::Reset), ResetColor)?;
+        }
+
+        if let Some(ref viewer) = self.viewer {
+            let x = tree_width as u16;
+            let pane_width = width.saturating_sub(tree_width + 2);
+
+            for (i, line) in viewer
+                .lines
+                .iter()
+                .skip(viewer.scroll)
+                .take(self.page_height)
+                .enumerate()
+            {
+                queue!(
+                    out,
+                    cursor::MoveTo(x, (i + 1) as u16),
+                    Print("│ "),
+                    Print(fit_to_width(line, pane_width)),
+                    SetAttribute(Attribute::Reset),
+                    ResetColor
+                )?;
+            }
+        }
+
+        let status =
+            match (&self.viewer, self.focus) {
+                (Some(viewer), BrowserFocus::Viewer) => format!(
+                    " {}  line {}/{}  [j/k] scroll  [Tab] tree  [q] close",
+                    viewer.path.display(),
+                    viewer.scroll + 1,
+                    viewer.lines.len()
+                ),
+                _ => format!(
+                " {}/{}  [j/k] move  [h/l] collapse/expand  [Enter] open  [1-9] depth  [q] quit",
+                if self.rows.is_empty() { 0 } else { self.selected + 1 },
+                self.rows.len()
+            ),
+            };
+        queue!(
+            out,
+            cursor::MoveTo(0, height.saturating_sub(1) as u16),
+            SetAttribute(Attribute::Reverse),
+            Print(fit_to_width(
+                &format!("{:<width$}", status, width = width),
+                width
+            )),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+// Truncate a line to `width` visible characters, passing ANSI escape sequences through untouched
+fn fit_to_width(line: &str, width: usize) -> String {
+    let mut output = String::new();
+    let mut visible = 0;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            output.push(c);
+            if chars.peek() == Some(&'[') {
+                for next in chars.by_ref() {
+                    output.push(next);
+                    if next.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+
+        if visible >= width {
+            continue;
+        }
+        if !c.is_control() {
+            output.push(c);
+            visible += 1;
+        }
+    }
+    output
+}
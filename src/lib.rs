@@ -1,15 +1,45 @@
+pub mod checksum_cache;
 pub mod config;
+pub mod diff;
 pub mod display;
+pub mod duplicates;
+pub mod error;
 pub mod git;
+#[cfg(feature = "git")]
+pub mod git_diff;
+pub mod interactive;
+pub mod patterns;
+pub mod query;
+#[cfg(feature = "git")]
+pub mod revision;
+pub mod scan_cache;
+pub mod search;
+pub mod snapshot;
 pub mod stats;
 pub mod tree;
 pub mod utils;
 pub mod viewer;
 
-pub use config::{Config, OutputFormat, SortBy};
+pub use checksum_cache::{ChecksumCache, SharedChecksumCache};
+pub use config::{
+    ChecksumAlgorithm, Config, ConfigLayer, OutputFormat, SortBy, TimeField, WatchMode,
+};
+pub use diff::{DiffEntry, DiffNode, DiffStatus, TreeDiff};
 pub use display::{FileInfo, TreeDisplay};
+pub use duplicates::{DuplicateSet, Duplicates};
+pub use error::{Error, Location};
+#[cfg(feature = "git")]
+pub use git_diff::{GitDiff, GitDiffEntry, GitDiffNode};
+pub use interactive::TreeBrowser;
+pub use patterns::PathFilter;
+pub use query::Query;
+#[cfg(feature = "git")]
+pub use revision::Revision;
+pub use scan_cache::ScanCache;
+pub use search::{ContentMatches, ContentSearch, MatchedLine};
+pub use snapshot::{Manifest, ManifestEntry, Verification};
 pub use stats::TreeStats;
-pub use tree::TreeVisualizer;
+pub use tree::{Tree, TreeNode, TreeVisualizer, Walk, WalkEntry};
 pub use viewer::{FileViewer, ViewerFormat, ViewerStrategy};
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,9 +1,14 @@
-use clap::{Parser, ValueEnum};
+use chrono::{DateTime, Utc};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use denarborea::utils::{self, parse_size, parse_time};
 use denarborea::{
-    utils::parse_size, Config, FileViewer, OutputFormat, Result, SortBy, TreeVisualizer,
-    ViewerFormat, ViewerStrategy,
+    ChecksumAlgorithm, ChecksumCache, Config, ConfigLayer, Error, FileViewer, Manifest,
+    OutputFormat, Result, ScanCache, SortBy, TimeField, TreeDiff, TreeVisualizer, ViewerFormat,
+    ViewerStrategy, WatchMode,
 };
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
 #[derive(ValueEnum, Clone, Debug)]
 enum CliViewerFormat {
@@ -42,16 +47,29 @@ impl From<CliViewerFormat> for ViewerFormat {
     A fast and beautiful directory visualizer with advanced filtering, Git integration, and multiple output formats.
 ")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     ///Directory to visualize
     #[arg(default_value = ".", help = "Path to the directory to visualize")]
     path: PathBuf,
 
     /// Maximum depth to visualize
-    #[arg(short = 'L', long, help = "Limit the depth of recursion")]
+    #[arg(
+        short = 'L',
+        long,
+        global = true,
+        help = "Limit the depth of recursion"
+    )]
     max_depth: Option<usize>,
 
     /// Show hidden files and directories
-    #[arg(short = 'a', long, help = "Show hidden files and directories")]
+    #[arg(
+        short = 'a',
+        long,
+        global = true,
+        help = "Show hidden files and directories"
+    )]
     all: bool,
 
     ///Show file sizes
@@ -71,7 +89,7 @@ struct Cli {
     count: bool,
 
     /// Disable colors
-    #[arg(long, help = "Disable colored output")]
+    #[arg(long, global = true, help = "Disable colored output")]
     no_color: bool,
 
     /// Sort method
@@ -87,21 +105,23 @@ struct Cli {
     #[arg(
         short = 'e',
         long,
+        global = true,
         help = "Filter by file extension (e.g., rs, py, js)"
     )]
     extension: Option<String>,
 
     /// Show only directories
-    #[arg(short = 'd', long, help = "Show only directories")]
+    #[arg(short = 'd', long, global = true, help = "Show only directories")]
     directories_only: bool,
 
     /// Show only files
-    #[arg(short = 'f', long, help = "Show only files")]
+    #[arg(short = 'f', long, global = true, help = "Show only files")]
     files_only: bool,
 
     /// Minimum file size filter
     #[arg(
         long,
+        global = true,
         help = "Show only files larger than specified size (e.g., 1MB, 500KB"
     )]
     min_size: Option<String>,
@@ -109,60 +129,301 @@ struct Cli {
     /// Maximum file size filter
     #[arg(
         long,
+        global = true,
         help = "Show only files smaller than specified size (e.g., 10MB, 2GB"
     )]
     max_size: Option<String>,
 
-    /// Éxclude directories/files matching pattern
+    /// Show only files newer than a time
+    #[arg(
+        long,
+        global = true,
+        value_name = "TIME",
+        help = "Show only files changed after TIME, a duration before now (2d, 1h30m) or a date (2026-01-01)"
+    )]
+    newer: Option<String>,
+
+    /// Show only files older than a time
+    #[arg(
+        long,
+        global = true,
+        value_name = "TIME",
+        help = "Show only files changed before TIME, a duration before now or a date"
+    )]
+    older: Option<String>,
+
+    /// Show only files changed within a duration
     #[arg(
         long,
-        help = "Exclude path matching pattern (can be used multiple times"
+        global = true,
+        value_name = "DURATION",
+        conflicts_with_all = ["newer", "newer_than"],
+        help = "Show only files changed within DURATION (e.g., 1h, 2d)"
+    )]
+    changed_within: Option<String>,
+
+    /// Show only files newer than a reference file
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        conflicts_with = "newer",
+        help = "Show only files changed after FILE was"
+    )]
+    newer_than: Option<PathBuf>,
+
+    /// Timestamp used by the time filters
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value = "mtime",
+        help = "Timestamp compared by --newer, --older, --changed-within and --newer-than"
+    )]
+    time_field: TimeField,
+
+    /// Exclude directories/files matching a glob
+    #[arg(
+        long,
+        global = true,
+        value_name = "GLOB",
+        help = "Exclude paths matching a gitignore-style glob, relative to the root (can be used multiple times)"
     )]
     exclude: Vec<String>,
 
-    /// Include directories/files matching pattern
+    /// Include files matching a glob
     #[arg(
         long,
-        help = "Include path matching pattern (can be used multiple times"
+        global = true,
+        value_name = "GLOB",
+        help = "Show only files matching a gitignore-style glob, relative to the root (can be used multiple times)"
     )]
     include: Vec<String>,
 
+    /// Include files matching a regex
+    #[arg(
+        long,
+        global = true,
+        value_name = "REGEX",
+        conflicts_with = "iregex",
+        help = "Show only files whose path relative to the root matches REGEX"
+    )]
+    regex: Option<String>,
+
+    /// Include files matching a regex, ignoring case
+    #[arg(
+        long,
+        global = true,
+        value_name = "REGEX",
+        help = "Like --regex, ignoring case"
+    )]
+    iregex: Option<String>,
+
+    /// Filter files with an expression
+    #[arg(
+        long = "where",
+        global = true,
+        value_name = "EXPR",
+        help = "Show only files matching an expression, e.g. 'ext in (rs, py) and size > 10KB'"
+    )]
+    where_: Option<String>,
+
+    /// Show only files whose contents match a regex
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATTERN",
+        conflicts_with = "igrep",
+        help = "Show only files whose contents match PATTERN, with the first matching lines"
+    )]
+    grep: Option<String>,
+
+    /// Search contents ignoring case
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATTERN",
+        help = "Like --grep, ignoring case"
+    )]
+    igrep: Option<String>,
+
+    /// Treat the grep pattern as a literal string
+    #[arg(
+        short = 'F',
+        long,
+        global = true,
+        help = "Match the --grep pattern as a literal string instead of a regex"
+    )]
+    fixed_strings: bool,
+
     /// Respect .gitignore files
-    #[arg(long, help = "Respect .gitignore files and global git config")]
+    #[arg(
+        long,
+        global = true,
+        help = "Respect .gitignore files and global git config"
+    )]
     git_ignore: bool,
 
     /// Show git status
     #[arg(long, help = "Shot Git status indicators (requires git repo)")]
     git_status: bool,
 
+    /// Show only changed files
+    #[arg(
+        long,
+        global = true,
+        help = "Show only files with git changes and the directories leading to them"
+    )]
+    git_changed_only: bool,
+
+    /// Show the tree of a git revision
+    #[arg(
+        long,
+        value_name = "COMMIT",
+        conflicts_with_all = ["stream", "watch", "interactive", "duplicates", "git_status", "git_changed_only", "disk_size", "cache"],
+        help = "Show the files of a commit, branch or tag instead of the worktree, or view a file as of that revision with --view"
+    )]
+    rev: Option<String>,
+
+    /// Show the changes between two git revisions
+    #[arg(
+        long,
+        value_name = "RANGE",
+        conflicts_with_all = ["rev", "stream", "watch", "interactive", "duplicates", "git_status", "git_changed_only", "view", "snapshot", "verify"],
+        help = "Show the files changed in RANGE (main..feature, main...feature) or between a revision and the worktree, with lines added and removed"
+    )]
+    git_diff: Option<String>,
+
     /// Limit number of files to show
     #[arg(long, help = "Limit the number of files displayed")]
     limit: Option<usize>,
 
     /// Output format
-    #[arg(long, value_enum, default_value = "tree", help = "Output format")]
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value = "tree",
+        help = "Output format"
+    )]
     format: OutputFormat,
 
+    /// Stream output while walking
+    #[arg(
+        long,
+        help = "Print entries as directories are read instead of buffering the whole tree"
+    )]
+    stream: bool,
+
+    /// Abort on the first unreadable entry
+    #[arg(
+        long,
+        global = true,
+        help = "Stop at the first unreadable file or directory instead of reporting it"
+    )]
+    strict: bool,
+
     /// Output to file instead of stdout
-    #[arg(short = 'o', long, help = "Output to file")]
+    #[arg(short = 'o', long, global = true, help = "Output to file")]
     output: Option<PathBuf>,
 
     /// Follow Symlinks
-    #[arg(long, help = "Follow symbolic links")]
+    #[arg(long, global = true, help = "Follow symbolic links")]
     follow_links: bool,
 
+    /// Number of walker threads
+    #[arg(
+        long,
+        global = true,
+        help = "Number of threads for directory traversal (1 walks serially, default: auto)"
+    )]
+    threads: Option<usize>,
+
     /// Show full paths instead of relative
     #[arg(long, help = "Show full absolute paths")]
     full_path: bool,
 
-    /// Show file checksums (MD5)
-    #[arg(long, help = "Show MD5 checksums for files")]
-    checksum: bool,
+    /// Show file checksums
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ALGORITHM",
+        num_args = 0..=1,
+        require_equals = true,
+        help = "Show file checksums, optionally with an algorithm (--checksum=sha256, default: md5)"
+    )]
+    checksum: Option<Option<ChecksumAlgorithm>>,
+
+    /// Do not use the checksum cache
+    #[arg(
+        long,
+        help = "Do not read or update the checksum cache in the user cache directory"
+    )]
+    no_checksum_cache: bool,
+
+    /// Reuse the last scan of unchanged directories
+    #[arg(
+        long,
+        global = true,
+        help = "Keep an index of directory listings and only re-read directories whose mtime changed"
+    )]
+    cache: bool,
+
+    /// Ignore the scan index
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "cache",
+        help = "Read every directory from disk, even when --cache is set in the config"
+    )]
+    no_cache: bool,
+
+    /// Report duplicate files
+    #[arg(
+        long,
+        conflicts_with_all = ["stream", "interactive"],
+        help = "Show only files with identical content, grouped into sets with the space they waste"
+    )]
+    duplicates: bool,
+
+    /// Keep running and show changes
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = ["stream", "interactive", "duplicates", "output"],
+        help = "Keep running and redraw the tree when files change, or print only the changes (--watch=delta)"
+    )]
+    watch: Option<Option<WatchMode>>,
+
+    /// Checksum prefix length in the tree view
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Number of checksum characters shown in the tree view (default: 8)"
+    )]
+    checksum_length: Option<usize>,
 
     /// Reverse sort order
     #[arg(short = 'r', long, help = "Reverse the sort order")]
     reverse: bool,
 
+    /// Disk usage mode
+    #[arg(
+        long,
+        help = "Show cumulative directory sizes (disk usage mode, implies --size)"
+    )]
+    du: bool,
+
+    /// Show on-disk block usage next to apparent sizes
+    #[arg(
+        long,
+        help = "Show and sort by on-disk block usage instead of apparent size"
+    )]
+    disk_size: bool,
+
     /// Show summar statistics
     #[arg(long, help = "Show summary statistics at the end")]
     stats: bool,
@@ -208,6 +469,53 @@ struct Cli {
     #[arg(long, help = "Force streaming mode for file viewing")]
     streaming: bool,
 
+    /// Browse the tree interactively
+    #[arg(
+        short = 'i',
+        long,
+        help = "Browse the tree in an interactive full-screen view"
+    )]
+    interactive: bool,
+
+    /// Write a manifest of the tree
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "verify",
+        help = "Write a manifest of the tree (paths, sizes, modes, mtimes, checksums with --checksum) to FILE"
+    )]
+    snapshot: Option<PathBuf>,
+
+    /// Compare the tree against a manifest
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Compare the tree against a manifest written by --snapshot, exits with 1 on drift"
+    )]
+    verify: Option<PathBuf>,
+
+    /// Print the effective configuration
+    #[arg(
+        long,
+        help = "Print the merged configuration from config files, environment and flags, then exit"
+    )]
+    print_config: bool,
+
+    /// Named profile from the config files
+    #[arg(
+        long,
+        global = true,
+        help = "Apply a [profile.<name>] section from the config files"
+    )]
+    profile: Option<String>,
+
+    /// Save the given flags to the user config file
+    #[arg(
+        long,
+        help = "Save the flags given on this command line to the user config file, then exit"
+    )]
+    save_config: bool,
+
     /// Preview size for very large files (in KB)
     #[arg(
         long,
@@ -217,9 +525,53 @@ struct Cli {
     preview_size: usize,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// Compare two directory trees
+    #[command(
+        long_about = "Compare two directory trees and render them as one merged tree. Exits with 1 when they differ."
+    )]
+    Diff {
+        #[arg(help = "Directory to compare from")]
+        left: PathBuf,
+        #[arg(help = "Directory to compare against")]
+        right: PathBuf,
+    },
+    /// Manage the checksum cache and the scan indexes of --cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
 
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Remove entries for files that were deleted or changed
+    Prune,
+    /// Remove all checksums and scan indexes
+    Clear,
+}
+
+fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let view_path = cli.view.clone();
+
+    match run(cli, matches) {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            match (&view_path, e.path()) {
+                (Some(path), None) => eprintln!("Error viewing file {}: {}", path.display(), e),
+                (Some(_), Some(_)) => eprintln!("Error viewing file {}", e),
+                (None, _) => eprintln!("Error: {}", e),
+            }
+            std::process::exit(e.exit_code());
+        }
+    }
+}
+
+// Returns the exit code, 1 when diff or --verify found differences
+fn run(cli: Cli, matches: ArgMatches) -> Result<i32> {
     // Handle file viewer mode
     if let Some(file_path) = cli.view {
         let delimiter = cli.delimiter.chars().next().unwrap_or(',');
@@ -238,49 +590,118 @@ fn main() -> Result<()> {
             viewer = viewer.with_preview_size(cli.preview_size * 1024); // Convert KB to bytes
         }
 
-        match viewer.view_file(&file_path) {
-            Ok(content) => {
-                println!("{}", content);
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("Error viewing file {}: {}", file_path.display(), e);
-                std::process::exit(1);
+        let output = match &cli.rev {
+            Some(spec) => view_revision(&viewer, &file_path, spec)?,
+            None => viewer.view_file(&file_path)?,
+        };
+        println!("{}", output);
+        return Ok(0);
+    }
+
+    if let Some(Command::Cache { action }) = &cli.command {
+        return run_cache(action);
+    }
+
+    let cli_layer = cli_layer(&cli, &matches)?;
+
+    if cli.save_config {
+        let path = Config::user_config_path()
+            .ok_or_else(|| Error::Config("No user config directory found".to_string()))?;
+        let mut saved = if path.exists() {
+            ConfigLayer::from_file(&path)?
+        } else {
+            ConfigLayer::default()
+        };
+
+        match cli.profile {
+            // with --profile the flags go into that profile instead
+            Some(ref name) => {
+                let profile = saved.profile.remove(name).unwrap_or_default();
+                let profile = profile.merge(cli_layer)?;
+                saved.profile.insert(name.clone(), profile);
             }
+            None => saved = saved.merge(cli_layer)?,
         }
+        saved.save_to_file(&path)?;
+        println!("Configuration saved to {}", path.display());
+        return Ok(0);
     }
 
-    let config = Config {
-        max_depth: cli.max_depth,
-        show_hidden: cli.all,
-        show_size: cli.size,
-        show_permissions: cli.permissions,
-        show_time: cli.time,
-        show_count: cli.count,
-        use_colors: !cli.no_color,
-        sort_by: cli.sort,
-        reverse_sort: cli.reverse,
-        filter_extension: cli.extension,
-        directories_only: cli.directories_only,
-        files_only: cli.files_only,
-        min_size: parse_size(&cli.min_size)?,
-        max_size: parse_size(&cli.max_size)?,
-        exclude_patterns: cli.exclude,
-        include_patterns: if cli.include.is_empty() {
-            None
-        } else {
-            Some(cli.include.join(","))
-        },
-        git_ignore: cli.git_ignore,
-        git_status: cli.git_status,
-        limit: cli.limit,
-        output_format: cli.format,
-        follow_links: cli.follow_links,
-        full_path: cli.full_path,
-        show_checksum: cli.checksum,
-        show_stats: cli.stats,
-        interactive: false,
+    // project config files are looked up from the left side of a diff
+    let target = match &cli.command {
+        Some(Command::Diff { left, .. }) => left,
+        _ => &cli.path,
     };
+    let mut config = Config::load(target, cli.profile.as_deref(), cli_layer)?;
+    // a mode of its own rather than a setting, only taken from the command line
+    config.git_diff = cli.git_diff.clone();
+    if let Some(reference) = &cli.newer_than {
+        config.newer = Some(newer_than_filter(reference, config.time_field)?);
+    }
+
+    let checksums = ChecksumCache::default_path()
+        .filter(|_| config.checksum_cache)
+        .map(|path| ChecksumCache::new(&path).shared());
+    config.checksums = checksums.clone();
+
+    let result = run_with_config(cli, config);
+
+    // digests calculated before a failure are still valid
+    if let Some(cache) = checksums {
+        if let Err(e) = cache.lock().unwrap_or_else(|e| e.into_inner()).save() {
+            eprintln!("Warning: could not save checksum cache: {}", e);
+        }
+    }
+    result
+}
+
+fn run_with_config(cli: Cli, config: Config) -> Result<i32> {
+    if cli.print_config {
+        print!("{}", toml::to_string_pretty(&config)?);
+        return Ok(0);
+    }
+
+    if config.interactive && !std::io::stdout().is_terminal() {
+        return Err(Error::Config(
+            "Interactive mode requires a terminal".to_string(),
+        ));
+    }
+
+    if let Some(Command::Diff { left, right }) = &cli.command {
+        return run_diff(&config, left, right, cli.output.as_deref());
+    }
+
+    if let Some(range) = &config.git_diff {
+        return run_git_diff(&config, &cli.path, range, cli.output.as_deref());
+    }
+
+    if let Some(manifest_path) = &cli.snapshot {
+        if !cli.path.exists() {
+            return Err(Error::not_found(&cli.path));
+        }
+        let manifest = Manifest::capture(&config, &cli.path)?;
+        manifest.save(manifest_path)?;
+        println!(
+            "Snapshot of {} entries written to {}",
+            manifest.entries.len(),
+            manifest_path.display()
+        );
+        return Ok(0);
+    }
+
+    if let Some(manifest_path) = &cli.verify {
+        if !cli.path.exists() {
+            return Err(Error::not_found(&cli.path));
+        }
+        let verification = Manifest::load(manifest_path)?.verify(&config, &cli.path)?;
+        match config.output_format {
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&verification.to_json())?)
+            }
+            _ => print!("{}", verification.render(config.use_colors)),
+        }
+        return Ok(if verification.is_clean() { 0 } else { 1 });
+    }
 
     let mut visualizer = TreeVisualizer::new(config);
 
@@ -290,5 +711,214 @@ fn main() -> Result<()> {
         visualizer.visualize(&cli.path)?;
     }
 
-    Ok(())
+    Ok(0)
+}
+
+fn run_diff(config: &Config, left: &Path, right: &Path, output: Option<&Path>) -> Result<i32> {
+    for root in [left, right] {
+        if !root.exists() {
+            return Err(Error::not_found(root));
+        }
+    }
+
+    let diff = TreeDiff::compare(config, left, right)?;
+    let rendered = match config.output_format {
+        OutputFormat::Tree => diff.render(config.use_colors && output.is_none()),
+        OutputFormat::Json => serde_json::to_string_pretty(&diff.to_json())? + "\n",
+        _ => {
+            return Err(Error::Config(
+                "diff supports only tree and json output".to_string(),
+            ))
+        }
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered).map_err(|e| Error::io(path, e))?;
+            println!("Output written to {}", path.display());
+        }
+        None => print!("{}", rendered),
+    }
+
+    // like diff(1), 1 tells scripts that the trees differ. An entry that
+    // could not be compared does not count as identical.
+    Ok(if diff.has_changes() || diff.has_errors() {
+        1
+    } else {
+        0
+    })
+}
+
+#[cfg(feature = "git")]
+fn run_git_diff(config: &Config, root: &Path, range: &str, output: Option<&Path>) -> Result<i32> {
+    let diff = denarborea::GitDiff::compare(config, root, range)?;
+    let rendered = match config.output_format {
+        OutputFormat::Tree => diff.render(config.use_colors && output.is_none()),
+        OutputFormat::Json => serde_json::to_string_pretty(&diff.to_json())? + "\n",
+        _ => {
+            return Err(Error::Config(
+                "--git-diff supports only tree and json output".to_string(),
+            ))
+        }
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered).map_err(|e| Error::io(path, e))?;
+            println!("Output written to {}", path.display());
+        }
+        None => print!("{}", rendered),
+    }
+
+    // the same contract as diff
+    Ok(if diff.has_changes() { 1 } else { 0 })
+}
+
+#[cfg(not(feature = "git"))]
+fn run_git_diff(
+    _config: &Config,
+    _root: &Path,
+    _range: &str,
+    _output: Option<&Path>,
+) -> Result<i32> {
+    Err(Error::Config(
+        "--git-diff is not available, denarborea was built without git support".to_string(),
+    ))
+}
+
+fn run_cache(action: &CacheAction) -> Result<i32> {
+    let path = ChecksumCache::default_path()
+        .ok_or_else(|| Error::Config("No user cache directory found".to_string()))?;
+    let mut cache = ChecksumCache::new(&path);
+
+    match action {
+        CacheAction::Prune => {
+            let removed = cache.prune();
+            println!(
+                "Removed {} stale entries, {} left in {}",
+                removed,
+                cache.len(),
+                path.display()
+            );
+        }
+        CacheAction::Clear => {
+            cache.clear();
+            println!("Cleared {}", path.display());
+            let indexes = ScanCache::clear_all()?;
+            if indexes > 0 {
+                println!("Removed {} scan indexes", indexes);
+            }
+        }
+    }
+    cache.save()?;
+    Ok(0)
+}
+
+#[cfg(feature = "git")]
+fn view_revision(viewer: &FileViewer, path: &Path, spec: &str) -> Result<String> {
+    let content = denarborea::Revision::open(path, spec)?.read(path)?;
+    viewer.view_bytes(path, &content)
+}
+
+#[cfg(not(feature = "git"))]
+fn view_revision(_viewer: &FileViewer, _path: &Path, _spec: &str) -> Result<String> {
+    Err(Error::Config(
+        "--rev is not available, denarborea was built without git support".to_string(),
+    ))
+}
+
+// Only flags given on the command line take part, so that clap defaults do
+// not override values from config files or the environment
+fn cli_layer(cli: &Cli, matches: &ArgMatches) -> Result<ConfigLayer> {
+    let explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let flag = |id: &str, value: bool| explicit(id).then_some(value);
+
+    Ok(ConfigLayer {
+        max_depth: cli.max_depth,
+        show_hidden: flag("all", cli.all),
+        show_size: flag("size", cli.size),
+        show_permissions: flag("permissions", cli.permissions),
+        show_time: flag("time", cli.time),
+        show_count: flag("count", cli.count),
+        use_colors: flag("no_color", !cli.no_color),
+        sort_by: explicit("sort").then_some(cli.sort),
+        reverse_sort: flag("reverse", cli.reverse),
+        filter_extension: cli.extension.clone(),
+        directories_only: flag("directories_only", cli.directories_only),
+        files_only: flag("files_only", cli.files_only),
+        min_size: parse_size(&cli.min_size)?,
+        max_size: parse_size(&cli.max_size)?,
+        newer: newer_filter(cli)?,
+        older: cli.older.as_deref().map(parse_time).transpose()?,
+        time_field: explicit("time_field").then_some(cli.time_field),
+        exclude_patterns: explicit("exclude").then(|| cli.exclude.clone()),
+        include_patterns: explicit("include").then(|| cli.include.clone()),
+        regex: cli.regex.clone().or_else(|| cli.iregex.clone()),
+        regex_ignore_case: match (&cli.regex, &cli.iregex) {
+            (Some(_), _) => Some(false),
+            (_, Some(_)) => Some(true),
+            _ => None,
+        },
+        filter_expression: cli.where_.clone(),
+        grep: cli.grep.clone().or_else(|| cli.igrep.clone()),
+        grep_ignore_case: match (&cli.grep, &cli.igrep) {
+            (Some(_), _) => Some(false),
+            (_, Some(_)) => Some(true),
+            _ => None,
+        },
+        grep_fixed_strings: flag("fixed_strings", cli.fixed_strings),
+        git_ignore: flag("git_ignore", cli.git_ignore),
+        git_status: flag("git_status", cli.git_status),
+        git_changed_only: flag("git_changed_only", cli.git_changed_only),
+        limit: cli.limit,
+        output_format: explicit("format").then_some(cli.format),
+        follow_links: flag("follow_links", cli.follow_links),
+        full_path: flag("full_path", cli.full_path),
+        show_checksum: cli.checksum.map(|_| true),
+        checksum_algorithm: cli.checksum.flatten(),
+        checksum_length: cli.checksum_length,
+        checksum_cache: flag("no_checksum_cache", !cli.no_checksum_cache),
+        duplicates: flag("duplicates", cli.duplicates),
+        watch: cli.watch.map(Option::unwrap_or_default),
+        revision: cli.rev.clone(),
+        scan_cache: if cli.no_cache {
+            Some(false)
+        } else {
+            flag("cache", cli.cache)
+        },
+        show_stats: flag("stats", cli.stats),
+        interactive: flag("interactive", cli.interactive),
+        disk_usage: flag("du", cli.du),
+        show_disk_size: flag("disk_size", cli.disk_size),
+        threads: cli.threads,
+        stream: flag("stream", cli.stream),
+        strict: flag("strict", cli.strict),
+        ..ConfigLayer::default()
+    })
+}
+
+// --newer and --changed-within set the lower bound here, --newer-than
+// replaces it once the layers are merged, see newer_than_filter
+fn newer_filter(cli: &Cli) -> Result<Option<DateTime<Utc>>> {
+    if let Some(duration) = &cli.changed_within {
+        let duration = chrono::Duration::from_std(utils::parse_duration(duration)?)
+            .map_err(|e| Error::Config(e.to_string()))?;
+        return Ok(Some(Utc::now() - duration));
+    }
+
+    cli.newer.as_deref().map(parse_time).transpose()
+}
+
+// The reference file is compared by the same timestamp as the other files,
+// which may come from a config file
+fn newer_than_filter(reference: &Path, field: TimeField) -> Result<DateTime<Utc>> {
+    let metadata = std::fs::metadata(reference).map_err(|e| Error::io(reference, e))?;
+    let time = utils::file_time(&metadata, field).ok_or_else(|| {
+        Error::Config(format!(
+            "{}: no {} time available",
+            reference.display(),
+            field.name()
+        ))
+    })?;
+    Ok(time.into())
 }
@@ -0,0 +1,118 @@
+use crate::{Config, Error, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use regex::{Regex, RegexBuilder};
+use std::path::{Path, PathBuf};
+
+// --exclude, --include and --regex, matched against paths relative to the
+// walk root. Globs follow .gitignore rules: a pattern without a slash matches
+// a name at any depth, a slash anchors it to the root, a trailing slash only
+// matches directories, `**` spans directories and `!` negates an earlier
+// pattern. Excluding a directory excludes everything below it, including one
+// includes everything below it unless a negation matches on the way down.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    root: PathBuf,
+    exclude: Option<Gitignore>,
+    include: Option<Gitignore>,
+    regex: Option<Regex>,
+}
+
+impl PathFilter {
+    pub fn new(config: &Config, root: &Path) -> Result<Self> {
+        let regex = match &config.regex {
+            Some(pattern) => Some(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(config.regex_ignore_case)
+                    .build()
+                    .map_err(|e| Error::Config(format!("Invalid regex `{}`: {}", pattern, e)))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            exclude: build_globs(&config.exclude_patterns)?,
+            include: build_globs(&config.include_patterns)?,
+            regex,
+        })
+    }
+
+    // Directories are never dropped for not being included, they are shown
+    // when a file below them is
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(relative) = self.relative(path) else {
+            return true;
+        };
+
+        if let Some(exclude) = &self.exclude {
+            if exclude
+                .matched_path_or_any_parents(relative, is_dir)
+                .is_ignore()
+            {
+                return false;
+            }
+        }
+        if is_dir {
+            return true;
+        }
+
+        if let Some(include) = &self.include {
+            if !Self::is_included(include, relative) {
+                return false;
+            }
+        }
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(&relative.to_string_lossy()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // A file is included when it or one of its directories matches, and
+    // neither it nor any of its directories is negated
+    fn is_included(include: &Gitignore, relative: &Path) -> bool {
+        let directories = relative
+            .ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty());
+
+        let mut included = false;
+        for (path, is_dir) in
+            std::iter::once((relative, false)).chain(directories.map(|dir| (dir, true)))
+        {
+            match include.matched(path, is_dir) {
+                Match::Whitelist(_) => return false,
+                Match::Ignore(_) => included = true,
+                Match::None => {}
+            }
+        }
+        included
+    }
+
+    // None for the root itself and for paths outside of it
+    pub(crate) fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.root)
+            .ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+    }
+}
+
+fn build_globs(patterns: &[String]) -> Result<Option<Gitignore>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    // paths are made relative before matching, so the matcher has no root
+    let mut builder = GitignoreBuilder::new("");
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| Error::Config(format!("Invalid pattern `{}`: {}", pattern, e)))?;
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| Error::Config(e.to_string()))
+}
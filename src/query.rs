@@ -0,0 +1,651 @@
+use crate::config::TimeField;
+use crate::git::{GitState, GitStatus};
+use crate::utils::{parse_duration, parse_size, parse_time};
+use crate::{Error, FileInfo, Location, Result};
+use chrono::{DateTime, Utc};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use regex::Regex;
+use std::path::Path;
+
+// The --where language. Comparisons are combined with `and`, `or`, `not` and
+// parentheses, `and` binding tighter than `or`:
+//
+//   ext in (rs, py) and size > 10KB and mtime < 7d and not path ~ "target/**"
+//
+// Values are bare words or quoted strings. See docs/filtering.md for the
+// fields and operators.
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Test(Test),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    Name,
+    Path,
+    Ext,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn holds<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Cmp::Eq => left == right,
+            Cmp::Ne => left != right,
+            Cmp::Lt => left < right,
+            Cmp::Le => left <= right,
+            Cmp::Gt => left > right,
+            Cmp::Ge => left >= right,
+        }
+    }
+
+    fn flip(self) -> Self {
+        match self {
+            Cmp::Lt => Cmp::Gt,
+            Cmp::Le => Cmp::Ge,
+            Cmp::Gt => Cmp::Lt,
+            Cmp::Ge => Cmp::Le,
+            other => other,
+        }
+    }
+}
+
+// A single comparison, with its value already parsed. `negate` covers `!=`,
+// `!~` and `not in`.
+#[derive(Debug, Clone)]
+pub enum Test {
+    Text {
+        field: TextField,
+        values: Vec<String>,
+        negate: bool,
+    },
+    Glob {
+        field: TextField,
+        glob: Gitignore,
+        negate: bool,
+    },
+    Regex {
+        field: TextField,
+        regex: Regex,
+    },
+    Size {
+        cmp: Cmp,
+        bytes: u64,
+    },
+    // durations are turned into times when parsing, `mtime < 7d` is
+    // `mtime > (now - 7d)`
+    Time {
+        field: TimeField,
+        cmp: Cmp,
+        time: DateTime<Utc>,
+    },
+    Kind {
+        kinds: Vec<Kind>,
+        negate: bool,
+    },
+    Git {
+        statuses: Vec<GitStatus>,
+        negate: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub expr: Expr,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            text,
+            tokens,
+            position: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error_at(token.start, "expected `and`, `or` or the end"));
+        }
+        Ok(Self { expr })
+    }
+
+    // Git status is only looked up for queries that test it
+    pub fn uses_git(&self) -> bool {
+        fn visit(expr: &Expr) -> bool {
+            match expr {
+                Expr::And(left, right) | Expr::Or(left, right) => visit(left) || visit(right),
+                Expr::Not(inner) => visit(inner),
+                Expr::Test(test) => matches!(test, Test::Git { .. }),
+            }
+        }
+        visit(&self.expr)
+    }
+
+    // `relative` is the path below the walk root, `git` is only called for
+    // tests on the git status
+    pub fn matches(
+        &self,
+        info: &FileInfo,
+        relative: &Path,
+        git: &dyn Fn() -> Option<GitState>,
+    ) -> bool {
+        Self::eval(&self.expr, info, relative, git)
+    }
+
+    fn eval(
+        expr: &Expr,
+        info: &FileInfo,
+        relative: &Path,
+        git: &dyn Fn() -> Option<GitState>,
+    ) -> bool {
+        match expr {
+            Expr::And(left, right) => {
+                Self::eval(left, info, relative, git) && Self::eval(right, info, relative, git)
+            }
+            Expr::Or(left, right) => {
+                Self::eval(left, info, relative, git) || Self::eval(right, info, relative, git)
+            }
+            Expr::Not(inner) => !Self::eval(inner, info, relative, git),
+            Expr::Test(test) => test.matches(info, relative, git),
+        }
+    }
+}
+
+impl Test {
+    fn matches(
+        &self,
+        info: &FileInfo,
+        relative: &Path,
+        git: &dyn Fn() -> Option<GitState>,
+    ) -> bool {
+        match self {
+            Test::Text {
+                field,
+                values,
+                negate,
+            } => {
+                let text = text_field(*field, info, relative);
+                let found = match field {
+                    TextField::Ext => values.iter().any(|value| value.eq_ignore_ascii_case(&text)),
+                    _ => values.contains(&text),
+                };
+                found != *negate
+            }
+            Test::Glob {
+                field,
+                glob,
+                negate,
+            } => {
+                let matched = match field {
+                    TextField::Path => glob.matched_path_or_any_parents(relative, info.is_dir),
+                    _ => glob.matched(text_field(*field, info, relative), info.is_dir),
+                };
+                matched.is_ignore() != *negate
+            }
+            Test::Regex { field, regex } => regex.is_match(&text_field(*field, info, relative)),
+            Test::Size { cmp, bytes } => cmp.holds(info.size, *bytes),
+            Test::Time { field, cmp, time } => info
+                .time(*field)
+                .is_some_and(|value| cmp.holds(DateTime::<Utc>::from(value), *time)),
+            Test::Kind { kinds, negate } => {
+                let kind = if info.is_symlink {
+                    Kind::Symlink
+                } else if info.is_dir {
+                    Kind::Dir
+                } else {
+                    Kind::File
+                };
+                kinds.contains(&kind) != *negate
+            }
+            Test::Git { statuses, negate } => git()
+                .is_some_and(|state| statuses.iter().any(|status| state.has(*status)) != *negate),
+        }
+    }
+}
+
+fn text_field(field: TextField, info: &FileInfo, relative: &Path) -> String {
+    match field {
+        TextField::Name => info.name.clone(),
+        TextField::Path => relative.to_string_lossy().to_string(),
+        TextField::Ext => info
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LeftParen,
+    RightParen,
+    Comma,
+    // comparison operators, `=`, `!=`, `~` and so on
+    Op(&'static str),
+    Word(String),
+    Quoted(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    // byte offset in the expression
+    start: usize,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.kind, TokenKind::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn text(&self) -> Option<&str> {
+        match &self.kind {
+            TokenKind::Word(text) | TokenKind::Quoted(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+const OPERATORS: [&str; 12] = [
+    "==", "!=", "<=", ">=", "=~", "!~", "&&", "||", "=", "<", ">", "~",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let kind = match c {
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            ',' => TokenKind::Comma,
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        // only quotes and backslashes are escaped, regexes keep
+                        // their own escapes
+                        Some((_, '\\')) => match chars.peek() {
+                            Some(&(_, escaped)) if escaped == c || escaped == '\\' => {
+                                value.push(escaped);
+                                chars.next();
+                            }
+                            _ => value.push('\\'),
+                        },
+                        Some((_, quote)) if quote == c => {
+                            tokens.push(Token {
+                                kind: TokenKind::Quoted(value),
+                                start,
+                            });
+                            break;
+                        }
+                        Some((_, other)) => value.push(other),
+                        None => return Err(syntax_error(text, start, "unterminated string")),
+                    }
+                }
+                continue;
+            }
+            _ => {
+                if let Some(op) = OPERATORS.iter().find(|op| text[start..].starts_with(**op)) {
+                    for _ in 0..op.len() {
+                        chars.next();
+                    }
+                    tokens.push(Token {
+                        kind: TokenKind::Op(op),
+                        start,
+                    });
+                    continue;
+                }
+                if c == '!' {
+                    TokenKind::Op("!")
+                } else {
+                    let mut word = String::new();
+                    while let Some(&(_, c)) = chars.peek() {
+                        if c.is_whitespace() || "(),=!<>~\"'".contains(c) {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    tokens.push(Token {
+                        kind: TokenKind::Word(word),
+                        start,
+                    });
+                    continue;
+                }
+            }
+        };
+        chars.next();
+        tokens.push(Token { kind, start });
+    }
+    Ok(tokens)
+}
+
+// The message is followed by the expression with a caret under `offset`
+fn syntax_error(text: &str, offset: usize, message: &str) -> Error {
+    let location = Location::from_offset(text, offset);
+    let line = text.lines().nth(location.line - 1).unwrap_or_default();
+    let column = location.column.unwrap_or(1);
+
+    Error::Parse {
+        format: "filter expression",
+        path: None,
+        location: Some(location),
+        message: format!(
+            "{}\n  {}\n  {}^",
+            message,
+            line,
+            " ".repeat(column.saturating_sub(1))
+        ),
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn end(&self) -> usize {
+        self.text.trim_end().len()
+    }
+
+    fn error_at(&self, offset: usize, message: &str) -> Error {
+        syntax_error(self.text, offset, message)
+    }
+
+    fn eat_keyword(&mut self, keywords: &[&str]) -> bool {
+        let found = self
+            .peek()
+            .is_some_and(|token| keywords.iter().any(|keyword| token.is_keyword(keyword)));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        let found = self
+            .peek()
+            .is_some_and(|token| matches!(token.kind, TokenKind::Op(found) if found == op));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword(&["or"]) || self.eat_op("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.eat_keyword(&["and"]) || self.eat_op("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.eat_keyword(&["not"]) || self.eat_op("!") {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::LeftParen => {
+                let start = token.start;
+                self.position += 1;
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(token) if token.kind == TokenKind::RightParen => Ok(expr),
+                    Some(token) => Err(self.error_at(token.start, "expected `)`")),
+                    None => Err(self.error_at(start, "unclosed `(`")),
+                }
+            }
+            _ => self.parse_test().map(Expr::Test),
+        }
+    }
+
+    fn parse_test(&mut self) -> Result<Test> {
+        let Some(token) = self.next() else {
+            return Err(self.error_at(self.end(), "expected a field"));
+        };
+        let field_start = token.start;
+        let field = match &token.kind {
+            TokenKind::Word(word) => word.to_lowercase(),
+            _ => return Err(self.error_at(token.start, "expected a field")),
+        };
+
+        let (op, op_start) = self.parse_operator()?;
+
+        // `in` and `not in` take a list, everything else a single value
+        let values = if op == "in" || op == "not in" {
+            self.parse_list()?
+        } else {
+            let Some(token) = self.next() else {
+                return Err(self.error_at(self.end(), "expected a value"));
+            };
+            match token.text() {
+                Some(text) => vec![(text.to_string(), token.start)],
+                None => return Err(self.error_at(token.start, "expected a value")),
+            }
+        };
+        let negate = matches!(op, "!=" | "!~" | "not in");
+        let (value, value_start) = values[0].clone();
+
+        let text_field = match field.as_str() {
+            "name" => Some(TextField::Name),
+            "path" => Some(TextField::Path),
+            "ext" | "extension" => Some(TextField::Ext),
+            _ => None,
+        };
+        if let Some(field) = text_field {
+            return match op {
+                "=" | "==" | "!=" | "in" | "not in" => Ok(Test::Text {
+                    field,
+                    values: values
+                        .into_iter()
+                        .map(|(value, _)| match field {
+                            TextField::Ext => value.trim_start_matches('.').to_string(),
+                            _ => value,
+                        })
+                        .collect(),
+                    negate,
+                }),
+                "~" | "!~" => {
+                    let mut builder = GitignoreBuilder::new("");
+                    let glob = builder
+                        .add_line(None, &value)
+                        .and_then(|builder| builder.build())
+                        .map_err(|e| self.error_at(value_start, &e.to_string()))?;
+                    Ok(Test::Glob {
+                        field,
+                        glob,
+                        negate,
+                    })
+                }
+                "=~" => {
+                    let regex = Regex::new(&value).map_err(|e| {
+                        let message = e.to_string();
+                        let last = message.lines().last().unwrap_or_default().to_string();
+                        self.error_at(value_start, &last)
+                    })?;
+                    Ok(Test::Regex { field, regex })
+                }
+                _ => Err(self.error_at(op_start, "expected =, !=, in, ~, !~ or =~")),
+            };
+        }
+
+        match field.as_str() {
+            "size" => {
+                let cmp = self.comparison(op, op_start, true)?;
+                let bytes = parse_size(&Some(value.clone()))
+                    .map_err(|_| self.error_at(value_start, "expected a size like 10KB"))?
+                    .unwrap_or_default();
+                Ok(Test::Size { cmp, bytes })
+            }
+            "mtime" | "ctime" | "atime" | "birth" | "btime" => {
+                let field = match field.as_str() {
+                    "mtime" => TimeField::Mtime,
+                    "ctime" => TimeField::Ctime,
+                    "atime" => TimeField::Atime,
+                    _ => TimeField::Birth,
+                };
+                let cmp = self.comparison(op, op_start, false)?;
+                let time_of = |text: &str| -> Option<(Cmp, DateTime<Utc>)> {
+                    if let Ok(duration) = parse_duration(text) {
+                        let duration = chrono::Duration::from_std(duration).ok()?;
+                        return Some((cmp.flip(), Utc::now().checked_sub_signed(duration)?));
+                    }
+                    parse_time(text).ok().map(|time| (cmp, time))
+                };
+                let (cmp, time) = time_of(&value).ok_or_else(|| {
+                    self.error_at(value_start, "expected a duration like 7d or a date")
+                })?;
+                Ok(Test::Time { field, cmp, time })
+            }
+            "type" => {
+                self.membership(op, op_start)?;
+                let kinds = values
+                    .iter()
+                    .map(|(value, start)| match value.to_lowercase().as_str() {
+                        "file" | "f" => Ok(Kind::File),
+                        "symlink" | "link" | "l" => Ok(Kind::Symlink),
+                        // directories are kept for the files below them and
+                        // never reach the expression
+                        "dir" | "directory" | "d" => Err(self.error_at(
+                            *start,
+                            "the expression applies to files, use --directories-only for directories",
+                        )),
+                        _ => Err(self.error_at(*start, "expected file or symlink")),
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Test::Kind { kinds, negate })
+            }
+            "git" => {
+                self.membership(op, op_start)?;
+                let statuses = values
+                    .iter()
+                    .map(|(value, start)| {
+                        GitStatus::from_name(value).ok_or_else(|| {
+                            self.error_at(
+                                *start,
+                                "expected untracked, modified, added, deleted, renamed, typechange, conflicted, dirty-submodule, ignored or clean",
+                            )
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Test::Git { statuses, negate })
+            }
+            _ => Err(self.error_at(
+                field_start,
+                &format!(
+                    "unknown field `{}`, expected name, path, ext, size, mtime, ctime, atime, birth, type or git",
+                    field
+                ),
+            )),
+        }
+    }
+
+    fn parse_operator(&mut self) -> Result<(&'static str, usize)> {
+        let Some(token) = self.next() else {
+            return Err(self.error_at(self.end(), "expected an operator"));
+        };
+        match &token.kind {
+            TokenKind::Op(op) if *op != "&&" && *op != "||" && *op != "!" => Ok((op, token.start)),
+            _ if token.is_keyword("in") => Ok(("in", token.start)),
+            _ if token.is_keyword("not") && self.eat_keyword(&["in"]) => {
+                Ok(("not in", token.start))
+            }
+            _ => Err(self.error_at(token.start, "expected an operator")),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<(String, usize)>> {
+        match self.next() {
+            Some(token) if token.kind == TokenKind::LeftParen => {}
+            Some(token) => return Err(self.error_at(token.start, "expected `(`")),
+            None => return Err(self.error_at(self.end(), "expected `(`")),
+        }
+
+        let mut values = Vec::new();
+        loop {
+            let Some(token) = self.next() else {
+                return Err(self.error_at(self.end(), "expected a value"));
+            };
+            match token.text() {
+                Some(text) => values.push((text.to_string(), token.start)),
+                None => return Err(self.error_at(token.start, "expected a value")),
+            }
+
+            match self.next() {
+                Some(token) if token.kind == TokenKind::Comma => {}
+                Some(token) if token.kind == TokenKind::RightParen => return Ok(values),
+                Some(token) => return Err(self.error_at(token.start, "expected `,` or `)`")),
+                None => return Err(self.error_at(self.end(), "expected `)`")),
+            }
+        }
+    }
+
+    fn comparison(&self, op: &str, op_start: usize, equality: bool) -> Result<Cmp> {
+        match op {
+            "<" => Ok(Cmp::Lt),
+            "<=" => Ok(Cmp::Le),
+            ">" => Ok(Cmp::Gt),
+            ">=" => Ok(Cmp::Ge),
+            "=" | "==" if equality => Ok(Cmp::Eq),
+            "!=" if equality => Ok(Cmp::Ne),
+            _ if equality => Err(self.error_at(op_start, "expected =, !=, <, <=, > or >=")),
+            _ => Err(self.error_at(op_start, "expected <, <=, > or >=")),
+        }
+    }
+
+    fn membership(&self, op: &str, op_start: usize) -> Result<()> {
+        match op {
+            "=" | "==" | "!=" | "in" | "not in" => Ok(()),
+            _ => Err(self.error_at(op_start, "expected =, !=, in or not in")),
+        }
+    }
+}
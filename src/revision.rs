@@ -0,0 +1,314 @@
+use crate::{Error, FileInfo, Result};
+use git2::{ObjectType, Oid, Repository, Tree};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MODE_EXECUTABLE: i32 = 0o100755;
+const MODE_SYMLINK: i32 = 0o120000;
+// git only records the type of trees and symlinks, they are shown with the
+// permissions they usually get on disk
+const MODE_DIRECTORY: u32 = 0o040755;
+const MODE_SYMLINK_PERMISSIONS: u32 = 0o120777;
+
+// --rev: a commit looked up in the repository around a path. Paths are given
+// as they would be on disk and resolved inside the commit's tree, so they do
+// not have to exist in the worktree. Nothing is checked out.
+pub struct Revision {
+    repo: Repository,
+    spec: String,
+    tree: Oid,
+    // the commit time, used as the modification time of every entry
+    time: SystemTime,
+    // canonical
+    workdir: PathBuf,
+}
+
+impl Revision {
+    pub fn open(path: &Path, spec: &str) -> Result<Self> {
+        let (repo, workdir) = open_repository(path)?;
+
+        let (tree, seconds) = {
+            let commit = repo
+                .revparse_single(spec)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| Error::Git(format!("unknown revision `{}`: {}", spec, e.message())))?;
+            (commit.tree_id(), commit.time().seconds().max(0) as u64)
+        };
+
+        Ok(Self {
+            spec: spec.to_string(),
+            tree,
+            time: UNIX_EPOCH + Duration::from_secs(seconds),
+            workdir,
+            repo,
+        })
+    }
+
+    // The entries of a directory sorted by name, each with the id of its
+    // object so contents can be read without resolving the path again.
+    // Submodules are listed as empty directories.
+    pub fn listing(&self, dir: &Path, count_children: bool) -> Result<Vec<(FileInfo, Oid)>> {
+        let Some(tree) = self.tree_at(dir)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut entries = Vec::with_capacity(tree.len());
+        for entry in tree.iter() {
+            let name = String::from_utf8_lossy(entry.name_bytes()).to_string();
+            let path = dir.join(file_name(entry.name_bytes()));
+            let mode = entry.filemode();
+
+            let mut info = FileInfo {
+                path,
+                name,
+                size: 0,
+                disk_size: None,
+                is_dir: false,
+                is_executable: false,
+                is_symlink: false,
+                modified_time: Some(self.time),
+                accessed_time: None,
+                changed_time: None,
+                created_time: None,
+                permissions: None,
+                link_target: None,
+                checksum: None,
+                file_count: None,
+                dir_count: None,
+                error: None,
+                content_matches: None,
+            };
+
+            match entry.kind() {
+                Some(ObjectType::Blob) => {
+                    info.size = self.blob_size(entry.id())?;
+                    info.is_executable = mode == MODE_EXECUTABLE;
+                    info.is_symlink = mode == MODE_SYMLINK;
+                    info.permissions = Some(mode as u32);
+                    if info.is_symlink {
+                        info.permissions = Some(MODE_SYMLINK_PERMISSIONS);
+                        let target = self.repo.find_blob(entry.id())?;
+                        info.link_target = Some(PathBuf::from(
+                            String::from_utf8_lossy(target.content()).as_ref(),
+                        ));
+                    }
+                }
+                kind => {
+                    info.is_dir = true;
+                    info.permissions = Some(MODE_DIRECTORY);
+                    if count_children {
+                        let (files, dirs) = match kind {
+                            Some(ObjectType::Tree) => {
+                                let subtree = self.repo.find_tree(entry.id())?;
+                                count_entries(&subtree)
+                            }
+                            _ => (0, 0),
+                        };
+                        info.file_count = Some(files);
+                        info.dir_count = Some(dirs);
+                    }
+                }
+            }
+            entries.push((info, entry.id()));
+        }
+
+        entries.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    // The contents of a file as of this revision
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let relative = self.relative(path)?;
+        let entry = self
+            .commit_tree()?
+            .get_path(&relative)
+            .map_err(|_| self.missing(path))?;
+        if entry.kind() != Some(ObjectType::Blob) {
+            return Err(Error::Git(format!(
+                "{} is not a file in {}",
+                path.display(),
+                self.spec
+            )));
+        }
+        self.read_blob(entry.id())
+    }
+
+    // The contents of a file by the id from `listing`
+    pub fn read_blob(&self, id: Oid) -> Result<Vec<u8>> {
+        Ok(self.repo.find_blob(id)?.content().to_vec())
+    }
+
+    // Apparent size of every directory below and including `root`, counting
+    // all files in the revision. Git does not know the on-disk size, the
+    // apparent size stands in for it like it does for unknown block counts.
+    pub fn directory_totals(&self, root: &Path) -> Result<HashMap<PathBuf, (u64, u64)>> {
+        let mut totals = HashMap::new();
+        totals.insert(root.to_path_buf(), (0, 0));
+        let Some(tree) = self.tree_at(root)? else {
+            return Ok(totals);
+        };
+
+        let mut sizes = Vec::new();
+        self.collect_sizes(&tree, root, &mut totals, &mut sizes)?;
+
+        for (dir, size) in sizes {
+            for ancestor in dir.ancestors() {
+                let Some(total) = totals.get_mut(ancestor) else {
+                    break;
+                };
+                total.0 += size;
+                total.1 += size;
+            }
+        }
+        Ok(totals)
+    }
+
+    // Registers every directory below `dir` and the size of each file with
+    // the directory that holds it
+    fn collect_sizes(
+        &self,
+        tree: &Tree,
+        dir: &Path,
+        totals: &mut HashMap<PathBuf, (u64, u64)>,
+        sizes: &mut Vec<(PathBuf, u64)>,
+    ) -> Result<()> {
+        for entry in tree.iter() {
+            match entry.kind() {
+                Some(ObjectType::Blob) => {
+                    sizes.push((dir.to_path_buf(), self.blob_size(entry.id())?))
+                }
+                kind => {
+                    let path = dir.join(file_name(entry.name_bytes()));
+                    totals.insert(path.clone(), (0, 0));
+                    if kind == Some(ObjectType::Tree) {
+                        let subtree = self.repo.find_tree(entry.id())?;
+                        self.collect_sizes(&subtree, &path, totals, sizes)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn commit_tree(&self) -> Result<Tree<'_>> {
+        Ok(self.repo.find_tree(self.tree)?)
+    }
+
+    // None for a submodule, which has no tree in this repository
+    fn tree_at(&self, dir: &Path) -> Result<Option<Tree<'_>>> {
+        let relative = self.relative(dir)?;
+        let tree = self.commit_tree()?;
+        if relative.as_os_str().is_empty() {
+            return Ok(Some(tree));
+        }
+
+        let entry = tree.get_path(&relative).map_err(|_| self.missing(dir))?;
+        match entry.kind() {
+            Some(ObjectType::Tree) => Ok(Some(self.repo.find_tree(entry.id())?)),
+            Some(ObjectType::Commit) => Ok(None),
+            _ => Err(Error::Git(format!(
+                "{} is not a directory in {}",
+                dir.display(),
+                self.spec
+            ))),
+        }
+    }
+
+    fn relative(&self, path: &Path) -> Result<PathBuf> {
+        relative_path(&self.workdir, path)
+    }
+
+    // the header is enough, the contents are only read when needed
+    fn blob_size(&self, id: Oid) -> Result<u64> {
+        let (size, _) = self.repo.odb()?.read_header(id)?;
+        Ok(size as u64)
+    }
+
+    fn missing(&self, path: &Path) -> Error {
+        Error::Git(format!(
+            "{} does not exist in {}",
+            path.display(),
+            self.spec
+        ))
+    }
+}
+
+// The repository around `path` and its canonical workdir
+pub(crate) fn open_repository(path: &Path) -> Result<(Repository, PathBuf)> {
+    let resolved = resolve(path)?;
+    let start = resolved
+        .ancestors()
+        .find(|dir| dir.is_dir())
+        .unwrap_or(&resolved);
+    let repo = Repository::discover(start)
+        .map_err(|_| Error::Git(format!("{} is not in a git repository", path.display())))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| Error::Git("bare repositories are not supported".to_string()))?;
+    let workdir = workdir.canonicalize().map_err(|e| Error::io(workdir, e))?;
+    Ok((repo, workdir))
+}
+
+// The path of `path` inside the repository at `workdir`
+pub(crate) fn relative_path(workdir: &Path, path: &Path) -> Result<PathBuf> {
+    let resolved = resolve(path)?;
+    resolved
+        .strip_prefix(workdir)
+        .map(Path::to_path_buf)
+        .map_err(|_| {
+            Error::Git(format!(
+                "{} is outside the repository at {}",
+                path.display(),
+                workdir.display()
+            ))
+        })
+}
+
+// Canonical form of a path that may only partly exist: the longest existing
+// ancestor is canonicalized and the rest appended as given. The last name is
+// never followed, git records symlinks as files of their own.
+fn resolve(path: &Path) -> Result<PathBuf> {
+    let absolute = std::path::absolute(path).map_err(|e| Error::io(path, e))?;
+    let mut missing = Vec::new();
+    let mut existing = absolute.as_path();
+    if let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) {
+        missing.push(name);
+        existing = parent;
+    }
+
+    let mut resolved = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break canonical;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return Err(Error::not_found(path)),
+        }
+    };
+    resolved.extend(missing.into_iter().rev());
+    Ok(resolved)
+}
+
+#[cfg(unix)]
+fn file_name(bytes: &[u8]) -> &std::ffi::OsStr {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::OsStr::from_bytes(bytes)
+}
+
+// Names that are not Unicode stay lossy elsewhere
+#[cfg(not(unix))]
+fn file_name(bytes: &[u8]) -> std::ffi::OsString {
+    String::from_utf8_lossy(bytes).into_owned().into()
+}
+
+fn count_entries(tree: &Tree) -> (usize, usize) {
+    tree.iter()
+        .fold((0, 0), |(files, dirs), entry| match entry.kind() {
+            Some(ObjectType::Blob) => (files + 1, dirs),
+            _ => (files, dirs + 1),
+        })
+}
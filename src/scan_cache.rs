@@ -0,0 +1,425 @@
+use crate::{utils, Error, FileInfo, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Bumped when the format changes, older indexes are then ignored
+const VERSION: u32 = 2;
+
+// A directory modified this recently may still change within the same mtime
+// tick, its listing is read every time until it settles
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+// The listing of a directory as of its mtime. Times are nanoseconds since the
+// epoch to keep the index small.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedDir {
+    mtime: u64,
+    entries: Vec<CachedEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedEntry {
+    name: RawName,
+    size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    disk_size: Option<u64>,
+    is_dir: bool,
+    is_executable: bool,
+    is_symlink: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accessed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    changed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    permissions: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    #[serde(with = "raw_path")]
+    root: PathBuf,
+    // keyed by the path relative to the root, "" for the root itself
+    #[serde(with = "raw_dirs")]
+    dirs: BTreeMap<PathBuf, CachedDir>,
+}
+
+// A file name or path as it is on disk. Most are UTF-8 and stored as strings,
+// the others as their bytes so that a cache hit rebuilds the exact path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawName {
+    Utf8(String),
+    Bytes(Vec<u8>),
+}
+
+// --cache: directory listings of the last scan of a root, keyed by directory
+// mtime. Creating, removing or renaming an entry changes the mtime of its
+// directory, so only the directories where that happened are read again.
+// Files changed in place leave the mtime of their directory alone and keep
+// their cached size and times until something else changes there.
+#[derive(Debug)]
+pub struct ScanCache {
+    path: PathBuf,
+    root: PathBuf,
+    index: Index,
+    dirty: bool,
+    hits: usize,
+    misses: usize,
+}
+
+impl ScanCache {
+    // Each root has its own index in the user cache directory
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("denarborea").join("scans"))
+    }
+
+    pub fn for_root(root: &Path) -> Option<Self> {
+        let absolute = std::path::absolute(root).ok()?;
+        let name = format!(
+            "{:016x}.json",
+            xxhash_rust::xxh3::xxh3_64(absolute.to_string_lossy().as_bytes())
+        );
+        Some(Self::open(&Self::default_dir()?.join(name), root))
+    }
+
+    // A missing, unreadable or outdated index starts empty
+    pub fn open(path: &Path, root: &Path) -> Self {
+        let absolute = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+        let index = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Index>(&content).ok())
+            .filter(|index| index.version == VERSION && index.root == absolute)
+            .unwrap_or_else(|| Index {
+                version: VERSION,
+                root: absolute,
+                dirs: BTreeMap::new(),
+            });
+
+        Self {
+            path: path.to_path_buf(),
+            root: root.to_path_buf(),
+            index,
+            dirty: false,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Directories served from the index and read from disk so far
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    // The entries of `dir` sorted by name, from the index when the directory
+    // has not changed since it was stored
+    pub fn listing(&mut self, dir: &Path, count_children: bool) -> Result<Vec<FileInfo>> {
+        let metadata = fs::metadata(dir).map_err(|e| Error::io(dir, e))?;
+        let modified = metadata.modified().ok();
+        let key = dir.strip_prefix(&self.root).unwrap_or(dir).to_path_buf();
+
+        let mtime = modified.and_then(nanos);
+        if let Some(cached) = self.index.dirs.get(&key) {
+            if mtime == Some(cached.mtime) {
+                self.hits += 1;
+                return Ok(cached
+                    .entries
+                    .iter()
+                    .map(|entry| entry.to_file_info(dir, count_children))
+                    .collect());
+            }
+        }
+        self.misses += 1;
+
+        // entries that cannot be read are kept as placeholders, and keep the
+        // directory out of the index so that they are tried again
+        let mut listing = Vec::new();
+        let mut complete = true;
+        for entry in fs::read_dir(dir).map_err(|e| Error::io(dir, e))? {
+            let entry = entry.map_err(|e| Error::io(dir, e))?;
+            let path = entry.path();
+            match fs::symlink_metadata(&path) {
+                Ok(symlink_metadata) => listing.push(FileInfo::from_metadata(
+                    &path,
+                    symlink_metadata,
+                    count_children,
+                )),
+                Err(e) => {
+                    let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+                    listing.push(FileInfo::from_error(
+                        &path,
+                        is_dir,
+                        Error::io(&path, e).reason(),
+                    ));
+                    complete = false;
+                }
+            }
+        }
+        listing.sort_by(|a, b| a.name.cmp(&b.name));
+
+        self.forget_removed_dirs(&key, &listing);
+        let settled = modified
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age >= SETTLE_TIME);
+        match mtime.filter(|_| settled && complete) {
+            Some(mtime) => {
+                let entries = listing.iter().map(CachedEntry::from_file_info).collect();
+                self.index.dirs.insert(key, CachedDir { mtime, entries });
+            }
+            None => {
+                self.index.dirs.remove(&key);
+            }
+        }
+        self.dirty = true;
+        Ok(listing)
+    }
+
+    // Subdirectories that disappeared take their cached subtree with them
+    fn forget_removed_dirs(&mut self, key: &Path, listing: &[FileInfo]) {
+        let Some(previous) = self.index.dirs.get(key) else {
+            return;
+        };
+        let present: HashSet<&OsStr> = listing
+            .iter()
+            .filter(|info| info.is_dir)
+            .filter_map(|info| info.path.file_name())
+            .collect();
+        let removed: Vec<PathBuf> = previous
+            .entries
+            .iter()
+            .filter(|entry| entry.is_dir)
+            .map(|entry| entry.name.to_os_string())
+            .filter(|name| !present.contains(name.as_os_str()))
+            .map(|name| key.join(name))
+            .collect();
+
+        for dir in removed {
+            let stale: Vec<PathBuf> = self
+                .index
+                .dirs
+                .range(dir.clone()..)
+                .map(|(path, _)| path)
+                .take_while(|path| path.starts_with(&dir))
+                .cloned()
+                .collect();
+            for path in stale {
+                self.index.dirs.remove(&path);
+            }
+        }
+    }
+
+    // Writes the index if it changed, replacing the file atomically like the
+    // checksum cache. The index is streamed to the file, large trees would
+    // otherwise need a second copy of it in memory.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        let temp_path = self
+            .path
+            .with_extension(format!("json.{}.tmp", std::process::id()));
+        let written = Self::write_index(&temp_path, &self.index).and_then(|()| {
+            fs::rename(&temp_path, &self.path).map_err(|e| Error::io(&self.path, e))
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn write_index(path: &Path, index: &Index) -> Result<()> {
+        let file = fs::File::create(path).map_err(|e| Error::io(path, e))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, index)?;
+        writer.flush().map_err(|e| Error::io(path, e))
+    }
+
+    // Removes the indexes of all roots, returns how many there were
+    pub fn clear_all() -> Result<usize> {
+        let Some(dir) = Self::default_dir() else {
+            return Ok(0);
+        };
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(Error::io(&dir, e)),
+        };
+
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry.map_err(|e| Error::io(&dir, e))?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(&path).map_err(|e| Error::io(&path, e))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+impl CachedEntry {
+    fn from_file_info(info: &FileInfo) -> Self {
+        Self {
+            name: RawName::new(info.path.file_name().unwrap_or(info.path.as_os_str())),
+            size: info.size,
+            disk_size: info.disk_size,
+            is_dir: info.is_dir,
+            is_executable: info.is_executable,
+            is_symlink: info.is_symlink,
+            modified: info.modified_time.and_then(nanos),
+            accessed: info.accessed_time.and_then(nanos),
+            changed: info.changed_time.and_then(nanos),
+            created: info.created_time.and_then(nanos),
+            permissions: info.permissions,
+        }
+    }
+
+    // Counts of a subdirectory are read again, it may have changed without
+    // touching the mtime of `dir`
+    fn to_file_info(&self, dir: &Path, count_children: bool) -> FileInfo {
+        let name = self.name.to_os_string();
+        let path = dir.join(&name);
+        let (file_count, dir_count) = if self.is_dir && count_children {
+            let (files, dirs) = utils::count_files_in_dir(&path);
+            (Some(files), Some(dirs))
+        } else {
+            (None, None)
+        };
+
+        FileInfo {
+            name: name.to_string_lossy().to_string(),
+            size: self.size,
+            disk_size: self.disk_size,
+            is_dir: self.is_dir,
+            is_executable: self.is_executable,
+            is_symlink: self.is_symlink,
+            modified_time: self.modified.map(time),
+            accessed_time: self.accessed.map(time),
+            changed_time: self.changed.map(time),
+            created_time: self.created.map(time),
+            permissions: self.permissions,
+            link_target: None,
+            checksum: None,
+            file_count,
+            dir_count,
+            error: None,
+            content_matches: None,
+            path,
+        }
+    }
+}
+
+fn nanos(time: SystemTime) -> Option<u64> {
+    let duration = time.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(duration.as_nanos()).ok()
+}
+
+fn time(nanos: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(nanos)
+}
+
+impl RawName {
+    fn new(name: &OsStr) -> Self {
+        match name.to_str() {
+            Some(name) => Self::Utf8(name.to_string()),
+            None => Self::Bytes(os_bytes(name)),
+        }
+    }
+
+    fn to_os_string(&self) -> OsString {
+        match self {
+            Self::Utf8(name) => OsString::from(name),
+            Self::Bytes(bytes) => os_string(bytes),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn os_bytes(name: &OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    name.as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn os_string(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStrExt;
+    OsStr::from_bytes(bytes).to_os_string()
+}
+
+// Names that are not Unicode stay lossy elsewhere
+#[cfg(not(unix))]
+fn os_bytes(name: &OsStr) -> Vec<u8> {
+    name.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(not(unix))]
+fn os_string(bytes: &[u8]) -> OsString {
+    String::from_utf8_lossy(bytes).into_owned().into()
+}
+
+mod raw_path {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        path: &Path,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        RawName::new(path.as_os_str()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<PathBuf, D::Error> {
+        RawName::deserialize(deserializer).map(|name| PathBuf::from(name.to_os_string()))
+    }
+}
+
+// JSON keys are strings, so the directories are stored as [path, listing]
+// pairs
+mod raw_dirs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        dirs: &BTreeMap<PathBuf, CachedDir>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            dirs.iter()
+                .map(|(path, dir)| (RawName::new(path.as_os_str()), dir)),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<BTreeMap<PathBuf, CachedDir>, D::Error> {
+        let pairs = Vec::<(RawName, CachedDir)>::deserialize(deserializer)?;
+        Ok(pairs
+            .into_iter()
+            .map(|(path, dir)| (PathBuf::from(path.to_os_string()), dir))
+            .collect())
+    }
+}
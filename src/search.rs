@@ -0,0 +1,106 @@
+use crate::viewer::{is_binary, is_binary_file};
+use crate::{Config, Error, Result};
+use regex::bytes::{Regex, RegexBuilder};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// Matching lines shown under a file, the count covers all of them
+pub const PREVIEW_LINES: usize = 3;
+
+// Longer lines are cut when shown
+const PREVIEW_WIDTH: usize = 120;
+
+// --grep: files are searched line by line, binary files never match
+#[derive(Debug, Clone)]
+pub struct ContentSearch {
+    regex: Regex,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentMatches {
+    // number of matching lines
+    pub count: usize,
+    pub lines: Vec<MatchedLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedLine {
+    // 1-based
+    pub number: usize,
+    pub text: String,
+}
+
+impl ContentSearch {
+    pub fn new(config: &Config) -> Result<Option<Self>> {
+        let Some(pattern) = &config.grep else {
+            return Ok(None);
+        };
+
+        let source = if config.grep_fixed_strings {
+            regex::escape(pattern)
+        } else {
+            pattern.clone()
+        };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(config.grep_ignore_case)
+            .build()
+            .map_err(|e| Error::Config(format!("Invalid grep pattern `{}`: {}", pattern, e)))?;
+        Ok(Some(Self { regex }))
+    }
+
+    // Unreadable files are treated like files without a match
+    pub fn search(&self, path: &Path) -> ContentMatches {
+        if is_binary_file(path) {
+            return ContentMatches::default();
+        }
+        match File::open(path) {
+            Ok(file) => self.search_reader(BufReader::new(file)),
+            Err(_) => ContentMatches::default(),
+        }
+    }
+
+    // Contents that are not on disk, like a file in a git revision
+    pub fn search_bytes(&self, content: &[u8]) -> ContentMatches {
+        if is_binary(content) {
+            return ContentMatches::default();
+        }
+        self.search_reader(content)
+    }
+
+    fn search_reader(&self, mut reader: impl BufRead) -> ContentMatches {
+        let mut matches = ContentMatches::default();
+        let mut line = Vec::new();
+        let mut number = 0;
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => number += 1,
+            }
+
+            // `$` has to match before the line ending
+            let text = line.strip_suffix(b"\n").unwrap_or(&line);
+            let text = text.strip_suffix(b"\r").unwrap_or(text);
+            if self.regex.is_match(text) {
+                matches.count += 1;
+                if matches.lines.len() < PREVIEW_LINES {
+                    matches.lines.push(MatchedLine {
+                        number,
+                        text: preview(text),
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+fn preview(line: &[u8]) -> String {
+    let text = String::from_utf8_lossy(line);
+    let text = text.replace('\t', "    ");
+    match text.char_indices().nth(PREVIEW_WIDTH) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
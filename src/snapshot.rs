@@ -0,0 +1,289 @@
+use crate::checksum_cache::calculate_checksum;
+use crate::{ChecksumAlgorithm, Config, Error, FileInfo, Result, TreeVisualizer};
+use chrono::{DateTime, Utc};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Bumped whenever the manifest layout changes incompatibly
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+// Directories only record their mode and symlinks their target. Sizes and
+// mtimes of directories depend on the filesystem and would always drift.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+impl ManifestEntry {
+    // `path` is made relative to `root`. The checksum is taken from `info`, so
+    // it is only recorded when it has been calculated.
+    pub fn from_info(root: &Path, info: &FileInfo) -> Self {
+        let path = info
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&info.path)
+            .to_path_buf();
+
+        if info.is_symlink {
+            return Self {
+                path,
+                kind: EntryKind::Symlink,
+                size: None,
+                mode: None,
+                mtime: None,
+                target: std::fs::read_link(&info.path).ok(),
+                checksum: None,
+            };
+        }
+
+        let is_file = !info.is_dir;
+        Self {
+            path,
+            kind: if is_file {
+                EntryKind::File
+            } else {
+                EntryKind::Dir
+            },
+            size: is_file.then_some(info.size),
+            mode: info.permissions,
+            mtime: info.modified_time.filter(|_| is_file).map(DateTime::from),
+            target: None,
+            checksum: info.checksum.clone(),
+        }
+    }
+
+    // Names of the recorded fields that differ in `live`. A checksum is only
+    // compared when the manifest has one.
+    fn changed_fields(&self, live: &ManifestEntry) -> Vec<&'static str> {
+        if self.kind != live.kind {
+            return vec!["kind"];
+        }
+
+        let mut fields = Vec::new();
+        if self.size != live.size {
+            fields.push("size");
+        }
+        if self.mode != live.mode {
+            fields.push("mode");
+        }
+        if self.mtime != live.mtime {
+            fields.push("mtime");
+        }
+        if self.target != live.target {
+            fields.push("target");
+        }
+        if self.checksum.is_some() && self.checksum != live.checksum {
+            fields.push("checksum");
+        }
+        fields
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub root: PathBuf,
+    pub created: DateTime<Utc>,
+    // algorithm of the entry checksums, manifests without it used MD5
+    #[serde(default)]
+    pub checksum_algorithm: ChecksumAlgorithm,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    // Walks `root` with the filters from `config`. Unreadable entries are an
+    // error, a manifest with holes in it could not be verified. That includes
+    // files whose checksum could not be read.
+    pub fn capture(config: &Config, root: &Path) -> Result<Self> {
+        let checksum_algorithm = config.checksum_algorithm;
+        let config = Config {
+            strict: true,
+            ..config.clone()
+        };
+        let mut visualizer = TreeVisualizer::new(config.clone());
+        let mut entries = Vec::new();
+        for mut info in visualizer.collect_entries(root)? {
+            if config.show_checksum && info.checksum.is_none() && !info.is_dir && !info.is_symlink {
+                // the walker drops the reason, read again to report it
+                info.checksum = Some(calculate_checksum(
+                    &info.path,
+                    checksum_algorithm,
+                    config.checksums.as_deref(),
+                )?);
+            }
+            entries.push(ManifestEntry::from_info(root, &info));
+        }
+        // independent of --sort, so manifests of the same tree compare equal
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            root: root.to_path_buf(),
+            created: Utc::now(),
+            checksum_algorithm,
+            entries,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let value: serde_json::Value =
+            serde_json::from_str(&content).map_err(|e| Error::from(e).with_path(path))?;
+
+        // check the version first so that a newer manifest gets a clear error
+        // rather than a complaint about some unknown field
+        match value.get("version").and_then(|version| version.as_u64()) {
+            Some(version) if version == MANIFEST_VERSION as u64 => {}
+            Some(version) => {
+                return Err(Error::parse(
+                    "manifest",
+                    format!(
+                        "unsupported version {}, expected {}",
+                        version, MANIFEST_VERSION
+                    ),
+                )
+                .with_path(path))
+            }
+            None => {
+                return Err(Error::parse("manifest", "missing version").with_path(path));
+            }
+        }
+
+        serde_json::from_value(value)
+            .map_err(|e| Error::parse("manifest", e.to_string()).with_path(path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content + "\n").map_err(|e| Error::io(path, e))
+    }
+
+    // Compares the live tree at `root` against the manifest. Checksums are
+    // calculated for live files whenever the manifest recorded one.
+    pub fn verify(&self, config: &Config, root: &Path) -> Result<Verification> {
+        let checksums = config.checksums.clone();
+        // checksums are calculated below with the manifest's algorithm
+        let config = Config {
+            strict: true,
+            show_checksum: false,
+            ..config.clone()
+        };
+        let mut visualizer = TreeVisualizer::new(config);
+        let mut live: BTreeMap<PathBuf, FileInfo> = visualizer
+            .collect_entries(root)?
+            .into_iter()
+            .map(|info| {
+                let relative = info.path.strip_prefix(root).unwrap_or(&info.path);
+                (relative.to_path_buf(), info)
+            })
+            .collect();
+
+        let mut verification = Verification {
+            checked: self.entries.len(),
+            ..Verification::default()
+        };
+        for expected in &self.entries {
+            let Some(mut info) = live.remove(&expected.path) else {
+                verification.missing.push(expected.path.clone());
+                continue;
+            };
+            if expected.checksum.is_some() {
+                info.calculate_checksum_cached(self.checksum_algorithm, checksums.as_deref());
+            }
+
+            let fields = expected.changed_fields(&ManifestEntry::from_info(root, &info));
+            if !fields.is_empty() {
+                verification.modified.push((expected.path.clone(), fields));
+            }
+        }
+        verification.extra = live.into_keys().collect();
+
+        Ok(verification)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Verification {
+    // number of entries in the manifest
+    pub checked: usize,
+    pub missing: Vec<PathBuf>,
+    pub extra: Vec<PathBuf>,
+    pub modified: Vec<(PathBuf, Vec<&'static str>)>,
+}
+
+impl Verification {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.modified.is_empty()
+    }
+
+    pub fn render(&self, use_colors: bool) -> String {
+        let paint = |text: String, color: Color| {
+            if use_colors {
+                text.color(color).to_string()
+            } else {
+                text
+            }
+        };
+
+        let mut output = String::new();
+        for path in &self.missing {
+            output.push_str(&paint(format!("missing  {}", path.display()), Color::Red));
+            output.push('\n');
+        }
+        for path in &self.extra {
+            output.push_str(&paint(format!("extra    {}", path.display()), Color::Green));
+            output.push('\n');
+        }
+        for (path, fields) in &self.modified {
+            output.push_str(&paint(
+                format!("modified {} ({})", path.display(), fields.join(", ")),
+                Color::Yellow,
+            ));
+            output.push('\n');
+        }
+
+        output.push_str(&format!(
+            "Verified {} entries: {} missing, {} extra, {} modified\n",
+            self.checked,
+            self.missing.len(),
+            self.extra.len(),
+            self.modified.len()
+        ));
+        output
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "ok": self.is_clean(),
+            "missing": self.missing,
+            "extra": self.extra,
+            "modified": self
+                .modified
+                .iter()
+                .map(|(path, fields)| json!({ "path": path, "fields": fields }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
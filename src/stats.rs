@@ -1,6 +1,7 @@
 use crate::FileInfo;
 use humansize::{format_size, DECIMAL};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Default)]
 pub struct TreeStats {
@@ -12,6 +13,9 @@ pub struct TreeStats {
     pub smallest_file: Option<(String, u64)>,
     pub avg_file_size: u64,
     pub symlinks: usize,
+    pub disk_usage: Option<(u64, u64)>,
+    pub largest_dir: Option<(String, u64)>,
+    pub errors: Vec<(PathBuf, String)>,
 }
 
 impl TreeStats {
@@ -59,6 +63,21 @@ impl TreeStats {
         }
     }
 
+    pub fn add_directory_total(&mut self, name: &str, size: u64) {
+        match &self.largest_dir {
+            Some((_, largest_size)) if *largest_size >= size => {}
+            _ => self.largest_dir = Some((name.to_string(), size)),
+        }
+    }
+
+    pub fn add_error(&mut self, path: &Path, error: &str) {
+        self.errors.push((path.to_path_buf(), error.to_string()));
+    }
+
+    pub fn set_disk_usage(&mut self, apparent_size: u64, disk_size: u64) {
+        self.disk_usage = Some((apparent_size, disk_size));
+    }
+
     pub fn finalize(&mut self) {
         if self.total_files > 0 {
             self.avg_file_size = self.total_size / self.total_files as u64;
@@ -76,6 +95,14 @@ impl TreeStats {
             format_size(self.total_size, DECIMAL)
         ));
 
+        if let Some((apparent_size, disk_size)) = self.disk_usage {
+            output.push_str(&format!(
+                "Disk usage: {} ({} on disk)\n",
+                format_size(apparent_size, DECIMAL),
+                format_size(disk_size, DECIMAL)
+            ));
+        }
+
         if self.total_files > 0 {
             output.push_str(&format!(
                 "Average file size: {}\n",
@@ -99,10 +126,22 @@ impl TreeStats {
             ));
         }
 
+        if let Some((name, size)) = &self.largest_dir {
+            output.push_str(&format!(
+                "Largest directory: {} ({})\n",
+                name,
+                format_size(*size, DECIMAL)
+            ));
+        }
+
         if self.symlinks > 0 {
             output.push_str(&format!("Symlinks: {}\n", self.symlinks));
         }
 
+        if !self.errors.is_empty() {
+            output.push_str(&format!("Errors: {}\n", self.errors.len()));
+        }
+
         if !self.file_types.is_empty() {
             output.push_str("File types:\n");
             let mut types: Vec<_> = self.file_types.iter().collect();
@@ -1,13 +1,43 @@
-use crate::{Config, FileInfo, OutputFormat, Result, SortBy, TreeDisplay, TreeStats};
-use ignore::WalkBuilder;
+use crate::interactive::TreeBrowser;
+use crate::{
+    utils::{self, DiskUsage},
+    Config, ContentSearch, Error, FileInfo, OutputFormat, PathFilter, Query, Result, ScanCache,
+    SortBy, TreeDisplay, TreeStats,
+};
+use ignore::{WalkBuilder, WalkState};
 use serde_json::json;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::fs::write;
+use std::fs::{write, File, Metadata};
+use std::io::BufWriter;
 use std::path::{Path, PathBuf};
-use walkdir::{DirEntry, WalkDir};
+use std::sync::mpsc;
+use walkdir::WalkDir;
 
 #[cfg(feature = "git")]
 use crate::git::GitInfo;
+use crate::git::GitState;
+#[cfg(feature = "git")]
+use std::collections::HashSet;
+
+// What the parallel walk saw of the tree, for the --du totals
+enum UsageVisit {
+    Entry(PathBuf, Metadata),
+    // a path the walk does not enter, added with all of its contents
+    Tree(PathBuf),
+    // the contents of a directory at the depth limit
+    Below(PathBuf),
+}
+
+mod cached;
+mod duplicates;
+#[cfg(feature = "git")]
+mod revision;
+mod stream;
+mod walk;
+mod watch;
+
+pub use walk::{Tree, TreeNode, Walk, WalkEntry};
 
 pub struct TreeVisualizer {
     config: Config,
@@ -15,18 +45,21 @@ pub struct TreeVisualizer {
     #[cfg(feature = "git")]
     git_info: Option<GitInfo>,
     stats: TreeStats,
+    // built from the config for the root of each walk
+    patterns: PathFilter,
+    query: Option<Query>,
+    content_search: Option<ContentSearch>,
 }
 
-#[derive(Debug)]
-struct TreeNode {
-    info: FileInfo,
-    children: Vec<TreeNode>,
-}
 impl TreeVisualizer {
     pub fn new(config: Config) -> Self {
         #[cfg(feature = "git")]
-        let git_info = if config.git_ignore || config.git_status {
-            Some(GitInfo::new(Path::new(".")))
+        let git_info = if config.git_ignore
+            || config.git_status
+            || config.git_changed_only
+            || Self::query_uses_git(&config)
+        {
+            Some(GitInfo::new())
         } else {
             None
         };
@@ -47,12 +80,33 @@ impl TreeVisualizer {
             #[cfg(feature = "git")]
             git_info,
             stats: TreeStats::new(),
+            patterns: PathFilter::default(),
+            query: None,
+            content_search: None,
         }
     }
 
     pub fn visualize(&mut self, root_path: &Path) -> Result<()> {
-        if !root_path.exists() {
-            return Err(format!("Path '{}' does not exist", root_path.display()).into());
+        if self.config.revision.is_none() && !root_path.exists() {
+            return Err(Error::not_found(root_path));
+        }
+
+        if self.config.interactive {
+            return self.browser(root_path)?.run();
+        }
+
+        if self.config.duplicates {
+            print!("{}", self.generate_duplicates_output(root_path)?);
+            return Ok(());
+        }
+
+        if let Some(mode) = self.config.watch {
+            return self.watch(root_path, mode, &mut std::io::stdout().lock());
+        }
+
+        // deleted files are only added once the walk is done
+        if self.config.stream && !self.config.git_changed_only {
+            return self.visualize_stream(root_path, &mut std::io::stdout().lock());
         }
 
         match self.config.output_format {
@@ -61,21 +115,31 @@ impl TreeVisualizer {
             OutputFormat::Csv => self.visualize_csv(root_path),
             OutputFormat::Markdown => self.visualize_markdown(root_path),
             OutputFormat::Xml => self.visualize_xml(root_path),
+            OutputFormat::Ndjson => self.visualize_ndjson(root_path),
         }
     }
 
     pub fn visualize_to_file(&mut self, root_path: &Path, output_path: &Path) -> Result<()> {
-        if !root_path.exists() {
-            return Err(format!("Path '{}' does not exist", root_path.display()).into());
+        if self.config.revision.is_none() && !root_path.exists() {
+            return Err(Error::not_found(root_path));
         }
 
-        let output = self.generate_output(root_path)?;
-        write(output_path, output)?;
+        if self.config.stream && !self.config.duplicates && !self.config.git_changed_only {
+            let mut file = BufWriter::new(File::create(output_path)?);
+            self.visualize_stream(root_path, &mut file)?;
+        } else {
+            let output = self.generate_output(root_path)?;
+            write(output_path, output)?;
+        }
         println!("Output written to {}", output_path.display());
         Ok(())
     }
 
     fn generate_output(&mut self, root_path: &Path) -> Result<String> {
+        if self.config.duplicates {
+            return self.generate_duplicates_output(root_path);
+        }
+
         let mut output = String::new();
 
         match self.config.output_format {
@@ -83,15 +147,7 @@ impl TreeVisualizer {
                 output.push_str(&self.display.format_path(root_path)?);
                 output.push('\n');
 
-                let entries = self.collect_entries(root_path)?;
-                let tree = self.build_tree(entries, root_path)?;
-
-                let filtered_tree = if self.config.filter_extension.is_some() {
-                    self.filter_empty_directories(tree)
-                } else {
-                    tree
-                };
-
+                let filtered_tree = self.filtered_tree(root_path)?;
                 self.append_tree_nodes(&mut output, &filtered_tree, "", true)?;
 
                 if self.config.show_stats {
@@ -111,22 +167,17 @@ impl TreeVisualizer {
             OutputFormat::Xml => {
                 output = self.generate_xml_output(root_path)?;
             }
+            OutputFormat::Ndjson => {
+                output = self.generate_ndjson_output(root_path)?;
+            }
         }
         Ok(output)
     }
 
     fn visualize_tree(&mut self, root_path: &Path) -> Result<()> {
+        let filtered_tree = self.filtered_tree(root_path)?;
         println!("{}", self.display.format_path(root_path)?);
 
-        let entries = self.collect_entries(root_path)?;
-        let tree = self.build_tree(entries, root_path)?;
-
-        let filtered_tree = if self.config.filter_extension.is_some() {
-            self.filter_empty_directories(tree)
-        } else {
-            tree
-        };
-
         self.print_tree_nodes(&filtered_tree, "", true)?;
 
         #[cfg(feature = "git")]
@@ -137,6 +188,59 @@ impl TreeVisualizer {
         Ok(())
     }
 
+    pub fn tree(&mut self, root_path: &Path) -> Result<Tree> {
+        if self.config.revision.is_none() && !root_path.exists() {
+            return Err(Error::not_found(root_path));
+        }
+
+        Ok(Tree {
+            root: root_path.to_path_buf(),
+            nodes: self.filtered_tree(root_path)?,
+        })
+    }
+
+    pub fn walk(&mut self, root_path: &Path) -> Result<Walk> {
+        Ok(self.tree(root_path)?.walk())
+    }
+
+    // Entries that could not be read during the last walk
+    pub fn errors(&self) -> &[(PathBuf, String)] {
+        &self.stats.errors
+    }
+
+    pub fn browser(&mut self, root_path: &Path) -> Result<TreeBrowser> {
+        if !root_path.exists() {
+            return Err(Error::not_found(root_path));
+        }
+
+        // In interactive mode the depth limit only controls the initial expansion,
+        // deeper levels stay reachable by expanding directories
+        let max_depth = self.config.max_depth.take();
+        let filtered_tree = self.filtered_tree(root_path);
+        self.config.max_depth = max_depth;
+        let filtered_tree = filtered_tree?;
+
+        let mut plain_config = self.config.clone();
+        plain_config.use_colors = false;
+        plain_config.output_format = OutputFormat::Tree;
+
+        #[cfg(feature = "git")]
+        let display = match self.git_info {
+            Some(ref git_info) => TreeDisplay::new(plain_config).with_git_info(git_info.clone()),
+            None => TreeDisplay::new(plain_config),
+        };
+
+        #[cfg(not(feature = "git"))]
+        let display = TreeDisplay::new(plain_config);
+
+        Ok(TreeBrowser::new(
+            root_path,
+            filtered_tree,
+            display,
+            max_depth.unwrap_or(1),
+        ))
+    }
+
     fn visualize_json(&mut self, root_path: &Path) -> Result<()> {
         let output = self.generate_json_output(root_path)?;
         println!("{}", output);
@@ -161,7 +265,89 @@ impl TreeVisualizer {
         Ok(())
     }
 
+    fn visualize_ndjson(&mut self, root_path: &Path) -> Result<()> {
+        let output = self.generate_ndjson_output(root_path)?;
+        print!("{}", output);
+        Ok(())
+    }
+
+    fn filtered_tree(&mut self, root_path: &Path) -> Result<Vec<TreeNode>> {
+        let entries = self.collect_entries(root_path)?;
+        let tree = self.build_tree(entries, root_path)?;
+
+        Ok(if self.config.prunes_empty_directories() {
+            self.filter_empty_directories(tree)
+        } else {
+            tree
+        })
+    }
+
     pub fn collect_entries(&mut self, root_path: &Path) -> Result<Vec<FileInfo>> {
+        self.patterns = PathFilter::new(&self.config, root_path)?;
+        self.query = self
+            .config
+            .filter_expression
+            .as_deref()
+            .map(Query::parse)
+            .transpose()?;
+        self.content_search = ContentSearch::new(&self.config)?;
+        let mut entries = if let Some(spec) = self.config.revision.clone() {
+            self.collect_entries_revision(root_path, &spec)?
+        } else {
+            let cache = self.scan_cache(root_path);
+            // The plain walks add up the --du totals as they go. Gitignore
+            // rules and limits leave out entries without the walk seeing
+            // them, those totals come from a walk of their own.
+            let mut usage = (self.config.disk_usage
+                && cache.is_none()
+                && !self.config.git_ignore
+                && self.config.limit.is_none())
+            .then(|| DiskUsage::new(root_path, self.config.follow_links));
+
+            let mut entries = match cache {
+                Some(cache) => self.collect_entries_cached(root_path, cache)?,
+                None if self.config.threads == Some(1) => {
+                    self.collect_entries_serial(root_path, usage.as_mut())?
+                }
+                None => self.collect_entries_parallel(root_path, usage.as_mut())?,
+            };
+            #[cfg(feature = "git")]
+            if self.config.git_changed_only {
+                self.add_missing_changes(root_path, &mut entries);
+            }
+            if self.config.disk_usage {
+                let totals = match usage {
+                    Some(usage) => usage.into_totals(),
+                    None => utils::directory_totals(root_path, self.config.follow_links),
+                };
+                self.apply_directory_totals(root_path, &mut entries, &totals);
+            }
+            entries
+        };
+
+        self.sort_entries(&mut entries);
+        Ok(entries)
+    }
+
+    // Following links could reach a directory through a path the index does
+    // not know, strict mode has to stop on the original error, and watch mode
+    // needs to see files changed in place
+    fn scan_cache(&self, root_path: &Path) -> Option<ScanCache> {
+        if !self.config.scan_cache
+            || self.config.follow_links
+            || self.config.strict
+            || self.config.watch.is_some()
+        {
+            return None;
+        }
+        ScanCache::for_root(root_path)
+    }
+
+    fn collect_entries_serial(
+        &mut self,
+        root_path: &Path,
+        mut usage: Option<&mut DiskUsage>,
+    ) -> Result<Vec<FileInfo>> {
         let mut entries = Vec::new();
         let mut file_count = 0;
 
@@ -174,14 +360,33 @@ impl TreeVisualizer {
                 .build();
 
             for result in walker {
-                let entry = result?;
+                let entry = match result {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        self.record_walk_error(root_path, &mut entries, e.into())?;
+                        continue;
+                    }
+                };
                 let path = entry.path();
 
                 if path == root_path {
                     continue;
                 }
 
-                if !self.should_include_path(path)? {
+                if !Self::matches_name_filters(&self.config, path) || self.is_git_ignored(path) {
+                    continue;
+                }
+
+                let mut file_info =
+                    match Self::stat_entry(&self.config, path, entry.metadata().ok()) {
+                        Ok(file_info) => file_info,
+                        Err(e) => {
+                            self.record_walk_error(root_path, &mut entries, e)?;
+                            continue;
+                        }
+                    };
+                if !self.matches_info_filters(&file_info) || !self.matches_contents(&mut file_info)
+                {
                     continue;
                 }
 
@@ -191,11 +396,12 @@ impl TreeVisualizer {
                     }
                 }
 
-                let mut file_info = FileInfo::from_path(path)?;
-
                 // Calculate checksum if requested
                 if self.config.show_checksum && !file_info.is_dir {
-                    file_info.calculate_checksum();
+                    file_info.calculate_checksum_cached(
+                        self.config.checksum_algorithm,
+                        self.config.checksums.as_deref(),
+                    );
                 }
 
                 #[cfg(feature = "git")]
@@ -205,17 +411,62 @@ impl TreeVisualizer {
                 file_count += 1;
             }
         } else {
-            // use walkdir for standard traversal
+            // use walkdir for standard traversal, hidden directories are not
+            // entered at all
+            let show_hidden = self.config.show_hidden;
+            let skipped = RefCell::new(Vec::new());
             let walker = WalkDir::new(root_path)
                 .min_depth(1)
                 .max_depth(self.config.max_depth.unwrap_or(usize::MAX))
                 .follow_links(self.config.follow_links)
-                .sort_by_file_name();
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(|entry| {
+                    let visible =
+                        show_hidden || entry.depth() == 0 || !utils::is_hidden(entry.path());
+                    if !visible {
+                        skipped.borrow_mut().push(entry.path().to_path_buf());
+                    }
+                    visible
+                });
+
+            if let Some(usage) = usage.as_mut() {
+                if let Ok(metadata) = std::fs::metadata(root_path) {
+                    usage.add(root_path, &metadata);
+                }
+            }
 
             for entry in walker {
-                let entry = entry?;
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        self.record_walk_error(root_path, &mut entries, e.into())?;
+                        continue;
+                    }
+                };
+                let path = entry.path();
+                let metadata = entry.metadata().ok();
 
-                if !self.should_include_entry(&entry)? {
+                if let (Some(usage), Some(metadata)) = (usage.as_mut(), &metadata) {
+                    usage.add(path, metadata);
+                    if metadata.is_dir() && self.config.max_depth == Some(entry.depth()) {
+                        usage.add_below(path);
+                    }
+                }
+
+                if !Self::matches_name_filters(&self.config, path) || self.is_git_ignored(path) {
+                    continue;
+                }
+
+                let mut file_info = match Self::stat_entry(&self.config, path, metadata) {
+                    Ok(file_info) => file_info,
+                    Err(e) => {
+                        self.record_walk_error(root_path, &mut entries, e)?;
+                        continue;
+                    }
+                };
+                if !self.matches_info_filters(&file_info) || !self.matches_contents(&mut file_info)
+                {
                     continue;
                 }
 
@@ -225,11 +476,12 @@ impl TreeVisualizer {
                     }
                 }
 
-                let mut file_info = FileInfo::from_path(entry.path())?;
-
                 // Calculate checksum if requested
                 if self.config.show_checksum && !file_info.is_dir {
-                    file_info.calculate_checksum();
+                    file_info.calculate_checksum_cached(
+                        self.config.checksum_algorithm,
+                        self.config.checksums.as_deref(),
+                    );
                 }
 
                 #[cfg(feature = "git")]
@@ -238,94 +490,415 @@ impl TreeVisualizer {
                 entries.push(file_info);
                 file_count += 1;
             }
+
+            if let Some(usage) = usage {
+                for path in skipped.into_inner() {
+                    usage.add_tree(&path);
+                }
+            }
         }
-        self.sort_entries(&mut entries);
         Ok(entries)
     }
 
-    fn should_include_entry(&self, entry: &DirEntry) -> Result<bool> {
-        let path = entry.path();
-        self.should_include_path(path)
+    fn collect_entries_parallel(
+        &mut self,
+        root_path: &Path,
+        usage: Option<&mut DiskUsage>,
+    ) -> Result<Vec<FileInfo>> {
+        let mut builder = WalkBuilder::new(root_path);
+        builder
+            .max_depth(self.config.max_depth)
+            .follow_links(self.config.follow_links)
+            .threads(self.config.threads.unwrap_or(0));
+
+        if self.config.git_ignore {
+            builder.hidden(!self.config.show_hidden);
+        } else {
+            // behave like walkdir: visit everything and leave filtering to us
+            builder.standard_filters(false);
+        }
+
+        // Checksums are computed on the worker threads unless a limit means
+        // most of them could be thrown away after the merge
+        let checksum_in_workers = self.config.show_checksum && self.config.limit.is_none();
+        // the same goes for content searches, which skip files that the size
+        // filters drop anyway
+        let search_in_workers = self
+            .content_search
+            .as_ref()
+            .filter(|_| self.config.limit.is_none());
+        let config = &self.config;
+        let (tx, rx) = mpsc::channel::<Result<FileInfo>>();
+        // --du: everything the walk sees, also what the filters drop
+        let (usage_tx, usage_rx) = mpsc::channel::<UsageVisit>();
+        let feeds_usage = usage.is_some();
+
+        builder.build_parallel().run(|| {
+            let tx = tx.clone();
+            let usage_tx = feeds_usage.then(|| usage_tx.clone());
+            Box::new(move |result| {
+                let entry = match result {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        let _ = tx.send(Err(e.into()));
+                        return if config.strict {
+                            WalkState::Quit
+                        } else {
+                            WalkState::Continue
+                        };
+                    }
+                };
+                let path = entry.path();
+                let metadata = entry.metadata().ok();
+
+                // keeps the walk out of hidden directories
+                let hidden = entry.depth() > 0 && !Self::matches_name_filters(config, path);
+                if let (Some(usage_tx), Some(metadata)) = (&usage_tx, &metadata) {
+                    let _ = usage_tx.send(if hidden {
+                        UsageVisit::Tree(path.to_path_buf())
+                    } else {
+                        UsageVisit::Entry(path.to_path_buf(), metadata.clone())
+                    });
+                    if !hidden && metadata.is_dir() && config.max_depth == Some(entry.depth()) {
+                        let _ = usage_tx.send(UsageVisit::Below(path.to_path_buf()));
+                    }
+                }
+
+                if entry.depth() == 0 {
+                    return WalkState::Continue;
+                }
+                if hidden {
+                    return WalkState::Skip;
+                }
+
+                match Self::stat_entry(config, path, metadata) {
+                    Ok(mut file_info) => {
+                        if checksum_in_workers && !file_info.is_dir {
+                            file_info.calculate_checksum_cached(
+                                config.checksum_algorithm,
+                                config.checksums.as_deref(),
+                            );
+                        }
+                        if let Some(search) = search_in_workers {
+                            if !file_info.is_dir && config.matches_size_filter(file_info.size) {
+                                file_info.content_matches = Some(search.search(&file_info.path));
+                            }
+                        }
+                        let _ = tx.send(Ok(file_info));
+                        WalkState::Continue
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        if config.strict {
+                            WalkState::Quit
+                        } else {
+                            WalkState::Continue
+                        }
+                    }
+                }
+            })
+        });
+        drop(tx);
+        drop(usage_tx);
+
+        if let Some(usage) = usage {
+            for visit in usage_rx {
+                match visit {
+                    UsageVisit::Entry(path, metadata) => usage.add(&path, &metadata),
+                    UsageVisit::Tree(path) => usage.add_tree(&path),
+                    UsageVisit::Below(path) => usage.add_below(&path),
+                }
+            }
+        }
+
+        let mut collected = Vec::new();
+        let mut errors = Vec::new();
+        for result in rx {
+            match result {
+                Ok(file_info) => collected.push(file_info),
+                Err(e) => errors.push(e),
+            }
+        }
+        // errors on directories are attached to entries that may have been
+        // sent after them, so they are only applied once everything is in
+        for e in errors {
+            self.record_walk_error(root_path, &mut collected, e)?;
+        }
+
+        // Component-wise path ordering is exactly the pre-order, name-sorted
+        // sequence the serial walker produces, so the output stays identical
+        collected.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut entries = Vec::new();
+        for mut file_info in collected {
+            if self.is_git_ignored(&file_info.path)
+                || !self.matches_info_filters(&file_info)
+                || !self.matches_contents(&mut file_info)
+            {
+                continue;
+            }
+
+            if let Some(limit) = self.config.limit {
+                if entries.len() >= limit {
+                    break;
+                }
+            }
+
+            if self.config.show_checksum && !file_info.is_dir {
+                file_info.calculate_checksum_cached(
+                    self.config.checksum_algorithm,
+                    self.config.checksums.as_deref(),
+                );
+            }
+
+            #[cfg(feature = "git")]
+            self.stats.add_file(&file_info);
+
+            entries.push(file_info);
+        }
+        Ok(entries)
     }
 
-    fn should_include_path(&self, path: &Path) -> Result<bool> {
-        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    // Unless running in strict mode, unreadable entries do not stop the walk.
+    // The failure is recorded and shown on the entry itself, or on a
+    // placeholder entry if the path could not be read at all.
+    fn record_walk_error(
+        &mut self,
+        root_path: &Path,
+        entries: &mut Vec<FileInfo>,
+        error: Error,
+    ) -> Result<()> {
+        if self.config.strict {
+            return Err(error);
+        }
 
-        // skip hidden files unless requested
-        if !self.config.show_hidden && file_name.starts_with('.') {
-            return Ok(false);
+        let path = error.path().unwrap_or(root_path).to_path_buf();
+        let reason = error.reason();
+        self.stats.add_error(&path, &reason);
+
+        if path == root_path {
+            return Ok(());
         }
 
-        // check gitignore
-        #[cfg(feature = "git")]
-        if let Some(ref git_info) = self.git_info {
-            if self.config.git_ignore && git_info.is_ignored(path) {
-                return Ok(false);
+        if let Some(entry) = entries.iter_mut().rev().find(|entry| entry.path == path) {
+            entry.error = Some(reason);
+        } else if Self::matches_name_filters(&self.config, &path) && !self.is_git_ignored(&path) {
+            let mut file_info = FileInfo::from_error(&path, path.is_dir(), reason);
+            if self.matches_info_filters(&file_info) && self.matches_contents(&mut file_info) {
+                entries.push(file_info);
             }
         }
+        Ok(())
+    }
 
-        //Check exclude patterns
-        for pattern in &self.config.exclude_patterns {
-            if crate::utils::matches_pattern(path, pattern) {
-                return Ok(false);
+    fn apply_directory_totals(
+        &mut self,
+        root_path: &Path,
+        entries: &mut [FileInfo],
+        totals: &HashMap<PathBuf, (u64, u64)>,
+    ) {
+        for entry in entries.iter_mut().filter(|entry| entry.is_dir) {
+            if let Some(&(size, disk_size)) = totals.get(&entry.path) {
+                entry.size = size;
+                entry.disk_size = Some(disk_size);
+                self.stats
+                    .add_directory_total(&entry.name, self.config.effective_size(entry));
             }
         }
 
-        // Check include patterns
-        if let Some(ref pattern) = self.config.include_patterns {
-            if !crate::utils::matches_pattern(path, pattern) {
-                return Ok(false);
+        if let Some(&(size, disk_size)) = totals.get(root_path) {
+            self.stats.set_disk_usage(size, disk_size);
+        }
+    }
+
+    fn stat_entry(config: &Config, path: &Path, metadata: Option<Metadata>) -> Result<FileInfo> {
+        // Walker metadata is the link itself unless links are followed, which
+        // is exactly what FileInfo needs to detect symlinks
+        let symlink_metadata = match metadata {
+            Some(metadata) if !config.follow_links => metadata,
+            _ => std::fs::symlink_metadata(path).map_err(|e| Error::io(path, e))?,
+        };
+        Ok(FileInfo::from_metadata(
+            path,
+            symlink_metadata,
+            config.counts_children(),
+        ))
+    }
+
+    fn is_git_ignored(&self, _path: &Path) -> bool {
+        #[cfg(feature = "git")]
+        if let Some(ref git_info) = self.git_info {
+            if self.config.git_ignore && git_info.is_ignored(_path) {
+                return true;
             }
         }
+        false
+    }
+
+    fn matches_name_filters(config: &Config, path: &Path) -> bool {
+        // skip hidden files unless requested
+        if !config.show_hidden && utils::is_hidden(path) {
+            return false;
+        }
 
-        let metadata = std::fs::metadata(path).ok();
-        let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
+        true
+    }
+
+    fn matches_info_filters(&self, info: &FileInfo) -> bool {
+        let config = &self.config;
+        if !self.patterns.matches(&info.path, info.is_dir) {
+            return false;
+        }
 
         // Apply files_only and directories_only filters first
-        if self.config.files_only && is_dir {
-            return Ok(false);
+        if config.files_only && info.is_dir {
+            return false;
         }
 
-        if self.config.directories_only && !is_dir {
-            return Ok(false);
+        if config.directories_only && !info.is_dir {
+            return false;
         }
 
         // For directories, we need to check if they should be included
         // (they might contain files we want to show, unless we're in files_only mode)
-        if is_dir && !self.config.files_only {
-            return Ok(true);
+        if info.is_dir {
+            return true;
         }
 
-        // For files, apply remaining filters
-        if !is_dir {
-            // check size filters
-            if let Some(ref metadata) = metadata {
-                if !self.config.matches_size_filter(metadata.len()) {
-                    return Ok(false);
+        // check size filters
+        if !config.matches_size_filter(info.size) {
+            return false;
+        }
+
+        if !config.matches_time_filter(info) {
+            return false;
+        }
+
+        // filter by extension if specified
+        if config.filter_extension.is_some() {
+            let extensions = config.get_extension();
+            if let Some(file_ext) = info.path.extension().and_then(|e| e.to_str()) {
+                if !extensions.contains(&file_ext.to_lowercase()) {
+                    return false;
                 }
+            } else if !extensions.is_empty() {
+                return false;
             }
+        }
 
-            // filter by extension if specified
-            if let Some(ref _ext_list) = self.config.filter_extension {
-                let extensions = self.config.get_extension();
-                if let Some(file_ext) = path.extension().and_then(|e| e.to_str()) {
-                    if !extensions.contains(&file_ext.to_lowercase()) {
-                        return Ok(false);
-                    }
-                } else if !extensions.is_empty() {
-                    return Ok(false);
+        if config.git_changed_only
+            && !self
+                .git_status_of(&info.path)
+                .is_some_and(|state| state.is_change())
+        {
+            return false;
+        }
+
+        match &self.query {
+            Some(query) => {
+                let relative = self.patterns.relative(&info.path).unwrap_or(&info.path);
+                query.matches(info, relative, &|| self.git_status_of(&info.path))
+            }
+            None => true,
+        }
+    }
+
+    // With --grep only files whose contents match are kept, the matches are
+    // stored on the entry for display
+    fn matches_contents(&self, info: &mut FileInfo) -> bool {
+        let Some(ref search) = self.content_search else {
+            return true;
+        };
+        if info.is_dir {
+            return true;
+        }
+
+        let matches = info
+            .content_matches
+            .take()
+            .unwrap_or_else(|| search.search(&info.path));
+        let found = matches.count > 0;
+        info.content_matches = Some(matches);
+        found
+    }
+
+    // Deleted files are not on disk, --git-changed-only adds them from the
+    // repository's status along with the directories that went with them
+    #[cfg(feature = "git")]
+    fn add_missing_changes(&self, root_path: &Path, entries: &mut Vec<FileInfo>) {
+        let Some(ref git_info) = self.git_info else {
+            return;
+        };
+        let mut known: HashSet<PathBuf> = entries.iter().map(|info| info.path.clone()).collect();
+
+        for path in git_info.missing(root_path) {
+            let Ok(relative) = path.strip_prefix(root_path) else {
+                continue;
+            };
+            let depth = relative.components().count();
+            if self
+                .config
+                .max_depth
+                .is_some_and(|max_depth| depth > max_depth)
+                || !path
+                    .ancestors()
+                    .take(depth)
+                    .all(|path| Self::matches_name_filters(&self.config, path))
+            {
+                continue;
+            }
+
+            let mut info = FileInfo::without_metadata(&path, false);
+            if !self.matches_info_filters(&info) || !self.matches_contents(&mut info) {
+                continue;
+            }
+            for dir in path.ancestors().skip(1).take(depth - 1) {
+                if known.insert(dir.to_path_buf()) {
+                    entries.push(FileInfo::without_metadata(dir, true));
                 }
             }
+            if known.insert(path) {
+                entries.push(info);
+            }
         }
+    }
+
+    #[cfg(feature = "git")]
+    fn query_uses_git(config: &Config) -> bool {
+        config
+            .filter_expression
+            .as_deref()
+            .is_some_and(|expr| Query::parse(expr).is_ok_and(|query| query.uses_git()))
+    }
+
+    #[cfg(not(feature = "git"))]
+    fn collect_entries_revision(
+        &mut self,
+        _root_path: &Path,
+        _spec: &str,
+    ) -> Result<Vec<FileInfo>> {
+        Err(Error::Config(
+            "--rev is not available, denarborea was built without git support".to_string(),
+        ))
+    }
 
-        Ok(true)
+    // None when there is no repository to ask
+    fn git_status_of(&self, _path: &Path) -> Option<GitState> {
+        #[cfg(feature = "git")]
+        if let Some(ref git_info) = self.git_info {
+            return git_info.status(_path);
+        }
+        None
     }
 
     fn sort_entries(&self, entries: &mut [FileInfo]) {
         entries.sort_by(|a, b| {
             let ordering = match self.config.sort_by {
                 SortBy::Name => a.name.cmp(&b.name),
-                SortBy::Size => a.size.cmp(&b.size),
+                SortBy::Size => self
+                    .config
+                    .effective_size(a)
+                    .cmp(&self.config.effective_size(b)),
                 SortBy::Time => match (a.modified_time, b.modified_time) {
                     (Some(a_time), Some(b_time)) => a_time.cmp(&b_time),
                     (Some(_), None) => std::cmp::Ordering::Greater,
@@ -403,8 +976,8 @@ impl TreeVisualizer {
                     //recursively filter children
                     node.children = self.filter_empty_directories(node.children);
 
-                    // keep directory if it has children or if we are not filtering by extension
-                    if !node.children.is_empty() || self.config.filter_extension.is_none() {
+                    // keep directory if it has children or if no filter selects files
+                    if !node.children.is_empty() || !self.config.prunes_empty_directories() {
                         Some(node)
                     } else {
                         None
@@ -429,6 +1002,9 @@ impl TreeVisualizer {
                 connector,
                 self.display.format_file_info(&node.info)?
             );
+            for line in self.display.format_match_lines(&node.info) {
+                println!("{}{}  {}", prefix, new_prefix, line);
+            }
 
             if !node.children.is_empty() {
                 let child_prefix = format!("{}{}", prefix, new_prefix);
@@ -456,6 +1032,9 @@ impl TreeVisualizer {
                 connector,
                 self.display.format_file_info(&node.info)?
             ));
+            for line in self.display.format_match_lines(&node.info) {
+                output.push_str(&format!("{}{}  {}\n", prefix, new_prefix, line));
+            }
 
             if !node.children.is_empty() {
                 let child_prefix = format!("{}{}", prefix, new_prefix);
@@ -469,7 +1048,7 @@ impl TreeVisualizer {
         let entries = self.collect_entries(root_path)?;
         let tree = self.build_tree(entries, root_path)?;
 
-        let json_tree = Self::tree_to_json(&tree)?;
+        let json_tree = self.tree_to_json(&tree)?;
 
         let output = json!({
             "root": root_path,
@@ -477,7 +1056,8 @@ impl TreeVisualizer {
             "stats": {
                 "total_files": Self::count_files(&tree),
                 "total_dirs": Self::count_dirs(&tree),
-            }
+            },
+            "errors": self.errors_json(),
         });
 
         Ok(serde_json::to_string_pretty(&output)?)
@@ -485,7 +1065,8 @@ impl TreeVisualizer {
 
     fn generate_csv_output(&mut self, root_path: &Path) -> Result<String> {
         let mut output = String::new();
-        output.push_str("Name,Path,Size,IsDirectory,Modified,Permissions\n");
+        output.push_str(&self.display.csv_header());
+        output.push('\n');
 
         let entries = self.collect_entries(root_path)?;
         for entry in entries {
@@ -525,21 +1106,58 @@ impl TreeVisualizer {
         Ok(output)
     }
 
-    fn tree_to_json(nodes: &[TreeNode]) -> Result<serde_json::Value> {
+    fn generate_ndjson_output(&mut self, root_path: &Path) -> Result<String> {
+        let mut output = String::new();
+
+        let entries = self.collect_entries(root_path)?;
+        for entry in entries {
+            output.push_str(&self.display.format_file_info(&entry)?);
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
+    fn json_node(&self, info: &FileInfo) -> serde_json::Value {
+        let mut node = json!({
+            "name": info.name,
+            "path": info.path,
+            "size": info.size,
+            "disk_size": info.disk_size,
+            "is_dir": info.is_dir,
+            "is_executable": info.is_executable,
+            "is_symlink": info.is_symlink,
+        });
+        if let Some(checksum) = &info.checksum {
+            node["checksum"] = json!(checksum);
+        }
+        if let Some(error) = &info.error {
+            node["error"] = json!(error);
+        }
+        if let Some(matches) = &info.content_matches {
+            node["matches"] = TreeDisplay::matches_json(matches);
+        }
+        if let Some(git) = self.display.git_json(info) {
+            node["git"] = git;
+        }
+        node
+    }
+
+    fn errors_json(&self) -> serde_json::Value {
+        self.stats
+            .errors
+            .iter()
+            .map(|(path, error)| json!({ "path": path, "error": error }))
+            .collect()
+    }
+
+    fn tree_to_json(&self, nodes: &[TreeNode]) -> Result<serde_json::Value> {
         let mut json_nodes = Vec::new();
 
         for node in nodes {
-            let mut json_node = json!({
-                "name": node.info.name,
-                "path": node.info.path,
-                "size": node.info.size,
-                "is_dir": node.info.is_dir,
-                "is_executable": node.info.is_executable,
-                "is_symlink": node.info.is_symlink,
-            });
+            let mut json_node = self.json_node(&node.info);
 
             if !node.children.is_empty() {
-                json_node["children"] = Self::tree_to_json(&node.children)?;
+                json_node["children"] = self.tree_to_json(&node.children)?;
             }
             json_nodes.push(json_node);
         }
@@ -0,0 +1,117 @@
+use super::stream::IgnoreStack;
+use super::TreeVisualizer;
+use crate::{FileInfo, Result, ScanCache};
+use std::path::Path;
+
+// --cache: the same pre-order, name-sorted walk as the serial walker, with
+// directory listings coming from the scan index when their directory has not
+// changed. Filters run on every entry as usual, the index only stores what is
+// on disk.
+
+struct CachedWalk {
+    cache: ScanCache,
+    ignore: Option<IgnoreStack>,
+    entries: Vec<FileInfo>,
+    file_count: usize,
+}
+
+impl TreeVisualizer {
+    pub(super) fn collect_entries_cached(
+        &mut self,
+        root_path: &Path,
+        cache: ScanCache,
+    ) -> Result<Vec<FileInfo>> {
+        let mut walk = CachedWalk {
+            cache,
+            ignore: self.config.git_ignore.then(|| IgnoreStack::new(root_path)),
+            entries: Vec::new(),
+            file_count: 0,
+        };
+
+        let result = self.walk_cached(root_path, root_path, 1, &mut walk);
+        // listings read before a failure are still valid
+        if let Err(e) = walk.cache.save() {
+            eprintln!("Warning: could not save scan cache: {}", e);
+        }
+        result?;
+        Ok(walk.entries)
+    }
+
+    // Returns false once the limit is reached
+    fn walk_cached(
+        &mut self,
+        root_path: &Path,
+        dir: &Path,
+        depth: usize,
+        walk: &mut CachedWalk,
+    ) -> Result<bool> {
+        let listing = match walk.cache.listing(dir, self.config.counts_children()) {
+            Ok(listing) => listing,
+            Err(e) => {
+                self.record_walk_error(root_path, &mut walk.entries, e)?;
+                return Ok(true);
+            }
+        };
+        if let Some(ref mut ignore) = walk.ignore {
+            ignore.push(dir);
+        }
+
+        let mut more = true;
+        for mut file_info in listing {
+            let path = file_info.path.clone();
+            let descend = file_info.is_dir
+                && !file_info.is_symlink
+                && file_info.error.is_none()
+                && self.config.max_depth.is_none_or(|max| depth < max);
+
+            // like the other walkers, hidden and ignored directories are not
+            // entered at all
+            let skipped = !Self::matches_name_filters(&self.config, &path)
+                || self.is_git_ignored(&path)
+                || walk
+                    .ignore
+                    .as_ref()
+                    .is_some_and(|ignore| ignore.is_ignored(&path, file_info.is_dir));
+            if skipped {
+                continue;
+            }
+            if let Some(error) = &file_info.error {
+                self.stats.add_error(&path, error);
+            }
+
+            if self.matches_info_filters(&file_info) && self.matches_contents(&mut file_info) {
+                if self
+                    .config
+                    .limit
+                    .is_some_and(|limit| walk.file_count >= limit)
+                {
+                    more = false;
+                    break;
+                }
+
+                if self.config.show_checksum && !file_info.is_dir {
+                    file_info.calculate_checksum_cached(
+                        self.config.checksum_algorithm,
+                        self.config.checksums.as_deref(),
+                    );
+                }
+
+                #[cfg(feature = "git")]
+                self.stats.add_file(&file_info);
+
+                walk.entries.push(file_info);
+                walk.file_count += 1;
+            }
+
+            if descend && !self.walk_cached(root_path, &path, depth + 1, walk)? {
+                more = false;
+                break;
+            }
+        }
+
+        if let Some(ref mut ignore) = walk.ignore {
+            ignore.pop();
+        }
+        Ok(more)
+    }
+}
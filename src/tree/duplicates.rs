@@ -0,0 +1,181 @@
+use super::{TreeNode, TreeVisualizer};
+use crate::{Duplicates, Error, FileInfo, OutputFormat, Result};
+use colored::*;
+use humansize::{format_size, DECIMAL};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+
+// --duplicates: the tree only shows files that have an identical copy, each
+// tagged with the number of its set, followed by a listing of the sets and
+// the space they waste. Paths in the listing, JSON and CSV are relative to
+// the root.
+
+impl TreeVisualizer {
+    pub(super) fn generate_duplicates_output(&mut self, root_path: &Path) -> Result<String> {
+        let entries = self.collect_entries(root_path)?;
+        let duplicates = Duplicates::find(
+            &entries,
+            self.config.checksum_algorithm,
+            self.config.checksums.as_deref(),
+        );
+
+        match self.config.output_format {
+            OutputFormat::Tree => self.duplicates_tree(root_path, entries, &duplicates),
+            OutputFormat::Json => {
+                let output = Self::duplicates_json(root_path, &duplicates);
+                Ok(serde_json::to_string_pretty(&output)? + "\n")
+            }
+            OutputFormat::Csv => Ok(Self::duplicates_csv(root_path, &duplicates)),
+            _ => Err(Error::Config(
+                "--duplicates supports only tree, json and csv output".to_string(),
+            )),
+        }
+    }
+
+    fn duplicates_tree(
+        &self,
+        root_path: &Path,
+        entries: Vec<FileInfo>,
+        duplicates: &Duplicates,
+    ) -> Result<String> {
+        let set_numbers = duplicates.set_numbers();
+        let entries = entries
+            .into_iter()
+            .filter(|info| info.is_dir || set_numbers.contains_key(info.path.as_path()))
+            .collect();
+        let tree = Self::prune_directories(self.build_tree(entries, root_path)?);
+
+        let mut output = self.display.format_path(root_path)?;
+        output.push('\n');
+        self.append_duplicate_nodes(&mut output, &tree, "", &set_numbers)?;
+
+        if duplicates.sets.is_empty() {
+            output.push_str("\nNo duplicates found\n");
+            return Ok(output);
+        }
+
+        for (index, set) in duplicates.sets.iter().enumerate() {
+            let heading = format!(
+                "Set {}: {} copies of {}, {} wasted",
+                index + 1,
+                set.paths.len(),
+                format_size(set.size, DECIMAL),
+                format_size(set.wasted_bytes(), DECIMAL)
+            );
+            output.push('\n');
+            if self.config.use_colors {
+                output.push_str(&heading.bold().to_string());
+            } else {
+                output.push_str(&heading);
+            }
+            output.push('\n');
+            for path in &set.paths {
+                let relative = path.strip_prefix(root_path).unwrap_or(path);
+                output.push_str(&format!("  {}\n", relative.display()));
+            }
+        }
+
+        output.push_str(&format!(
+            "\nTotal: {} sets, {} wasted\n",
+            duplicates.sets.len(),
+            format_size(duplicates.wasted_bytes(), DECIMAL)
+        ));
+        Ok(output)
+    }
+
+    // Directories that are left without any duplicate below them
+    fn prune_directories(nodes: Vec<TreeNode>) -> Vec<TreeNode> {
+        nodes
+            .into_iter()
+            .filter_map(|mut node| {
+                if !node.info.is_dir {
+                    return Some(node);
+                }
+                node.children = Self::prune_directories(node.children);
+                (!node.children.is_empty()).then_some(node)
+            })
+            .collect()
+    }
+
+    fn append_duplicate_nodes(
+        &self,
+        output: &mut String,
+        nodes: &[TreeNode],
+        prefix: &str,
+        set_numbers: &HashMap<&Path, usize>,
+    ) -> Result<()> {
+        for (i, node) in nodes.iter().enumerate() {
+            let is_last = i == nodes.len() - 1;
+            let connector = if is_last { "'-- " } else { "|-- " };
+            let new_prefix = if is_last { "   " } else { "|   " };
+
+            output.push_str(prefix);
+            output.push_str(connector);
+            output.push_str(&self.display.format_file_info(&node.info)?);
+            if let Some(number) = set_numbers.get(node.info.path.as_path()) {
+                let tag = format!(" [duplicate #{}]", number);
+                if self.config.use_colors {
+                    output.push_str(&tag.yellow().to_string());
+                } else {
+                    output.push_str(&tag);
+                }
+            }
+            output.push('\n');
+
+            if !node.children.is_empty() {
+                let child_prefix = format!("{}{}", prefix, new_prefix);
+                self.append_duplicate_nodes(output, &node.children, &child_prefix, set_numbers)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn duplicates_json(root_path: &Path, duplicates: &Duplicates) -> serde_json::Value {
+        let sets: Vec<_> = duplicates
+            .sets
+            .iter()
+            .enumerate()
+            .map(|(index, set)| {
+                let paths: Vec<_> = set
+                    .paths
+                    .iter()
+                    .map(|path| path.strip_prefix(root_path).unwrap_or(path))
+                    .collect();
+                json!({
+                    "id": index + 1,
+                    "size": set.size,
+                    "checksum": set.checksum,
+                    "wasted": set.wasted_bytes(),
+                    "paths": paths,
+                })
+            })
+            .collect();
+
+        json!({
+            "root": root_path,
+            "sets": sets,
+            "total_sets": duplicates.sets.len(),
+            "total_wasted": duplicates.wasted_bytes(),
+        })
+    }
+
+    // One row per copy, the wasted bytes are those of the whole set
+    fn duplicates_csv(root_path: &Path, duplicates: &Duplicates) -> String {
+        let mut output = String::from("Set,Path,Size,Checksum,Wasted\n");
+        for (index, set) in duplicates.sets.iter().enumerate() {
+            for path in &set.paths {
+                let relative = path.strip_prefix(root_path).unwrap_or(path);
+                output.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    index + 1,
+                    relative.display(),
+                    set.size,
+                    set.checksum,
+                    set.wasted_bytes()
+                ));
+            }
+        }
+        output
+    }
+}
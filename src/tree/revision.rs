@@ -0,0 +1,83 @@
+use super::TreeVisualizer;
+use crate::revision::Revision;
+use crate::utils::checksum_bytes;
+use crate::{FileInfo, Result};
+use std::path::Path;
+
+// --rev: the same pre-order, name-sorted walk as the serial walker over the
+// tree of a commit. Blobs are only read for content searches, checksums and
+// symlink targets.
+
+struct RevisionWalk {
+    revision: Revision,
+    entries: Vec<FileInfo>,
+    file_count: usize,
+}
+
+impl TreeVisualizer {
+    pub(super) fn collect_entries_revision(
+        &mut self,
+        root_path: &Path,
+        spec: &str,
+    ) -> Result<Vec<FileInfo>> {
+        let mut walk = RevisionWalk {
+            revision: Revision::open(root_path, spec)?,
+            entries: Vec::new(),
+            file_count: 0,
+        };
+        self.walk_revision(root_path, 1, &mut walk)?;
+
+        if self.config.disk_usage {
+            let totals = walk.revision.directory_totals(root_path)?;
+            self.apply_directory_totals(root_path, &mut walk.entries, &totals);
+        }
+        Ok(walk.entries)
+    }
+
+    // Returns false once the limit is reached
+    fn walk_revision(&mut self, dir: &Path, depth: usize, walk: &mut RevisionWalk) -> Result<bool> {
+        let listing = walk.revision.listing(dir, self.config.counts_children())?;
+
+        for (mut file_info, id) in listing {
+            let path = file_info.path.clone();
+            // files in a commit are tracked, so there is nothing to ignore,
+            // only hidden directories are left out
+            if !Self::matches_name_filters(&self.config, &path) {
+                continue;
+            }
+            let descend = file_info.is_dir && self.config.max_depth.is_none_or(|max| depth < max);
+
+            if self.matches_info_filters(&file_info) {
+                if let Some(search) = self.content_search.as_ref().filter(|_| !file_info.is_dir) {
+                    let content = walk.revision.read_blob(id)?;
+                    file_info.content_matches = Some(search.search_bytes(&content));
+                }
+
+                if self.matches_contents(&mut file_info) {
+                    if self
+                        .config
+                        .limit
+                        .is_some_and(|limit| walk.file_count >= limit)
+                    {
+                        return Ok(false);
+                    }
+
+                    if self.config.show_checksum && !file_info.is_dir {
+                        let content = walk.revision.read_blob(id)?;
+                        file_info.checksum =
+                            Some(checksum_bytes(&content, self.config.checksum_algorithm));
+                    }
+
+                    self.stats.add_file(&file_info);
+                    walk.entries.push(file_info);
+                    walk.file_count += 1;
+                }
+            }
+
+            if descend && !self.walk_revision(&path, depth + 1, walk)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
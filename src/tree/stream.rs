@@ -0,0 +1,476 @@
+use super::TreeVisualizer;
+use crate::{ContentSearch, Error, FileInfo, OutputFormat, PathFilter, Query, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Streaming traversal: every directory is read, filtered and sorted on its
+// own, and its entries are written out before descending into the next one.
+// Only the listings of the directories on the current path are kept in memory.
+
+pub(super) struct IgnoreStack {
+    in_repo: bool,
+    levels: Vec<Gitignore>,
+}
+
+impl IgnoreStack {
+    pub(super) fn new(root: &Path) -> Self {
+        let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let repo_root = canonical
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .map(Path::to_path_buf);
+
+        let mut stack = Self {
+            in_repo: repo_root.is_some(),
+            levels: Vec::new(),
+        };
+
+        if let Some(ref repo_root) = repo_root {
+            let (global, _) = Gitignore::global();
+            stack.levels.push(global);
+
+            let mut exclude = GitignoreBuilder::new(repo_root);
+            exclude.add(repo_root.join(".git").join("info").join("exclude"));
+            stack
+                .levels
+                .push(exclude.build().unwrap_or_else(|_| Gitignore::empty()));
+
+            // ignore files of the directories between the repository root and the tree root
+            let mut parents: Vec<&Path> = canonical
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(repo_root))
+                .collect();
+            parents.reverse();
+            for dir in parents {
+                stack.push(dir);
+            }
+        }
+        stack
+    }
+
+    pub(super) fn push(&mut self, dir: &Path) {
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut names = vec![".ignore"];
+        if self.in_repo {
+            names.push(".gitignore");
+        }
+        for name in names {
+            let file = dir.join(name);
+            if file.is_file() {
+                builder.add(file);
+            }
+        }
+        self.levels
+            .push(builder.build().unwrap_or_else(|_| Gitignore::empty()));
+    }
+
+    pub(super) fn pop(&mut self) {
+        self.levels.pop();
+    }
+
+    pub(super) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        // the deepest ignore file that has an opinion wins
+        for level in self.levels.iter().rev() {
+            match level.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+struct StreamState {
+    ignore: Option<IgnoreStack>,
+    totals: Option<HashMap<PathBuf, (u64, u64)>>,
+    // canonical paths of the directories being walked, to stop symlink loops
+    ancestors: Vec<PathBuf>,
+    // directories a look-ahead already found a visible entry in, until their
+    // parent's listing is filtered
+    visible: HashSet<PathBuf>,
+    emitted: usize,
+    // whether the next JSON value at each nesting level needs a leading comma
+    json_separators: Vec<bool>,
+}
+
+impl TreeVisualizer {
+    pub fn visualize_stream(&mut self, root_path: &Path, out: &mut dyn Write) -> Result<()> {
+        if !root_path.exists() {
+            return Err(Error::not_found(root_path));
+        }
+        self.patterns = PathFilter::new(&self.config, root_path)?;
+        self.query = self
+            .config
+            .filter_expression
+            .as_deref()
+            .map(Query::parse)
+            .transpose()?;
+        self.content_search = ContentSearch::new(&self.config)?;
+
+        let mut state = StreamState {
+            ignore: self.config.git_ignore.then(|| IgnoreStack::new(root_path)),
+            totals: self
+                .config
+                .disk_usage
+                .then(|| crate::utils::directory_totals(root_path, self.config.follow_links)),
+            ancestors: Vec::new(),
+            visible: HashSet::new(),
+            emitted: 0,
+            json_separators: vec![false],
+        };
+
+        self.write_stream_header(root_path, out)?;
+        let listing = match self.enter_directory(root_path, &mut state) {
+            Ok(listing) => listing,
+            Err(e) if !self.config.strict => {
+                self.stats.add_error(root_path, &e.reason());
+                Vec::new()
+            }
+            Err(e) => return Err(e),
+        };
+        self.stream_directory(listing, 1, "", &mut state, out)?;
+        self.leave_directory(&mut state);
+        self.write_stream_footer(root_path, &mut state, out)?;
+
+        out.flush()?;
+        Ok(())
+    }
+
+    fn enter_directory(&self, dir: &Path, state: &mut StreamState) -> Result<Vec<FileInfo>> {
+        if let Some(ref mut ignore) = state.ignore {
+            ignore.push(dir);
+        }
+        if self.config.follow_links {
+            state
+                .ancestors
+                .push(dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()));
+        }
+        self.read_directory(dir, state)
+    }
+
+    fn leave_directory(&self, state: &mut StreamState) {
+        if let Some(ref mut ignore) = state.ignore {
+            ignore.pop();
+        }
+        if self.config.follow_links {
+            state.ancestors.pop();
+        }
+    }
+
+    fn read_directory(&self, dir: &Path, state: &mut StreamState) -> Result<Vec<FileInfo>> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(dir).map_err(|e| Error::io(dir, e))? {
+            let entry = entry.map_err(|e| Error::io(dir, e))?;
+            let path = entry.path();
+
+            if !Self::matches_name_filters(&self.config, &path) || self.is_git_ignored(&path) {
+                continue;
+            }
+
+            let mut info = match Self::stat_entry(&self.config, &path, entry.metadata().ok()) {
+                Ok(info) => info,
+                Err(e) if !self.config.strict => {
+                    let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+                    FileInfo::from_error(&path, is_dir, e.reason())
+                }
+                Err(e) => return Err(e),
+            };
+
+            if let Some(ref ignore) = state.ignore {
+                if ignore.is_ignored(&path, info.is_dir) {
+                    continue;
+                }
+            }
+            if !self.matches_info_filters(&info) || !self.matches_contents(&mut info) {
+                continue;
+            }
+
+            if info.is_dir {
+                if let Some(&(size, disk_size)) =
+                    state.totals.as_ref().and_then(|totals| totals.get(&path))
+                {
+                    info.size = size;
+                    info.disk_size = Some(disk_size);
+                }
+            }
+            entries.push(info);
+        }
+
+        // read_dir order is arbitrary; start from name order so that ties in
+        // the requested sort come out the same way as in the buffered path
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        self.sort_entries(&mut entries);
+        Ok(entries)
+    }
+
+    fn can_descend(&self, info: &FileInfo, depth: usize, state: &StreamState) -> bool {
+        if !info.is_dir || self.config.max_depth.is_some_and(|max| depth >= max) {
+            return false;
+        }
+        if info.is_symlink && !self.config.follow_links {
+            return false;
+        }
+        if self.config.follow_links {
+            let canonical = info
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| info.path.clone());
+            return !state.ancestors.contains(&canonical);
+        }
+        true
+    }
+
+    // With extension filters, directories without a single matching file are
+    // hidden. That needs a look-ahead scan, since nothing is buffered. The
+    // scan stops at the first match and remembers the directories on the way
+    // to it, so the levels below do not scan the same entries again. Only
+    // positive results are kept: they are few and used up as soon as the
+    // parent is streamed, while an empty directory is not streamed at all.
+    fn has_visible_descendant(
+        &self,
+        info: &FileInfo,
+        depth: usize,
+        state: &mut StreamState,
+    ) -> Result<bool> {
+        if state.visible.remove(&info.path) {
+            return Ok(true);
+        }
+        if !self.can_descend(info, depth, state) {
+            return Ok(false);
+        }
+
+        let mut found = Ok(false);
+        if let Ok(listing) = self.enter_directory(&info.path, state) {
+            for child in &listing {
+                found = if child.is_dir {
+                    self.has_visible_descendant(child, depth + 1, state)
+                } else {
+                    Ok(true)
+                };
+                if matches!(found, Ok(true)) && child.is_dir {
+                    state.visible.insert(child.path.clone());
+                }
+                if !matches!(found, Ok(false)) {
+                    break;
+                }
+            }
+        }
+        self.leave_directory(state);
+        found
+    }
+
+    fn stream_directory(
+        &mut self,
+        mut listing: Vec<FileInfo>,
+        depth: usize,
+        prefix: &str,
+        state: &mut StreamState,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        if self.config.prunes_empty_directories() {
+            let mut visible = Vec::with_capacity(listing.len());
+            for info in listing {
+                if !info.is_dir || self.has_visible_descendant(&info, depth, state)? {
+                    visible.push(info);
+                }
+            }
+            listing = visible;
+        }
+
+        let count = listing.len();
+        for (i, mut info) in listing.into_iter().enumerate() {
+            if self
+                .config
+                .limit
+                .is_some_and(|limit| state.emitted >= limit)
+            {
+                return Ok(());
+            }
+            state.emitted += 1;
+
+            if self.config.show_checksum && !info.is_dir {
+                info.calculate_checksum_cached(
+                    self.config.checksum_algorithm,
+                    self.config.checksums.as_deref(),
+                );
+            }
+            self.stats.add_file(&info);
+
+            let is_last = i == count - 1;
+            let children = if self.can_descend(&info, depth, state) {
+                match self.enter_directory(&info.path, state) {
+                    Ok(children) => Some(children),
+                    Err(e) if !self.config.strict => {
+                        self.leave_directory(state);
+                        info.error = Some(e.reason());
+                        None
+                    }
+                    Err(e) => {
+                        self.leave_directory(state);
+                        return Err(e);
+                    }
+                }
+            } else {
+                None
+            };
+            let has_children = children.as_ref().is_some_and(|c| !c.is_empty());
+            if let Some(error) = &info.error {
+                self.stats.add_error(&info.path, error);
+            }
+
+            self.write_stream_entry(&info, prefix, is_last, has_children, state, out)?;
+
+            if let Some(children) = children {
+                let child_prefix = format!("{}{}", prefix, if is_last { "   " } else { "|   " });
+                let result = self.stream_directory(children, depth + 1, &child_prefix, state, out);
+                self.leave_directory(state);
+                result?;
+
+                if has_children {
+                    self.close_stream_entry(&info, state, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_stream_header(&self, root_path: &Path, out: &mut dyn Write) -> Result<()> {
+        match self.config.output_format {
+            OutputFormat::Tree => writeln!(out, "{}", self.display.format_path(root_path)?)?,
+            OutputFormat::Json => {
+                writeln!(out, "{{")?;
+                writeln!(out, "  \"root\": {},", json!(root_path))?;
+                write!(out, "  \"tree\": [")?;
+            }
+            OutputFormat::Csv => writeln!(out, "{}", self.display.csv_header())?,
+            OutputFormat::Markdown => {
+                writeln!(out, "Directory Tree: {}\n", root_path.display())?;
+                writeln!(out, "|  Name  |  Size |  Modified  |")?;
+                writeln!(out, "|--------|-------|------------|")?;
+            }
+            OutputFormat::Xml => {
+                writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+                writeln!(out, "<tree root=\"{}\">", root_path.display())?;
+            }
+            OutputFormat::Ndjson => {}
+        }
+        Ok(())
+    }
+
+    fn write_stream_entry(
+        &self,
+        info: &FileInfo,
+        prefix: &str,
+        is_last: bool,
+        has_children: bool,
+        state: &mut StreamState,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        match self.config.output_format {
+            OutputFormat::Tree => {
+                let connector = if is_last { "'-- " } else { "|-- " };
+                let new_prefix = if is_last { "   " } else { "|   " };
+                writeln!(
+                    out,
+                    "{}{}{}",
+                    prefix,
+                    connector,
+                    self.display.format_file_info(info)?
+                )?;
+                for line in self.display.format_match_lines(info) {
+                    writeln!(out, "{}{}  {}", prefix, new_prefix, line)?;
+                }
+            }
+            OutputFormat::Json => {
+                let indent = "  ".repeat(2 * state.json_separators.len());
+                if let Some(separator) = state.json_separators.last_mut() {
+                    write!(out, "{}", if *separator { ",\n" } else { "\n" })?;
+                    *separator = true;
+                }
+
+                if has_children {
+                    write!(out, "{}{{\n{}  \"children\": [", indent, indent)?;
+                    state.json_separators.push(false);
+                } else {
+                    let node = serde_json::to_string(&self.json_node(info))?;
+                    write!(out, "{}{}", indent, node)?;
+                }
+            }
+            OutputFormat::Csv | OutputFormat::Markdown | OutputFormat::Ndjson => {
+                writeln!(out, "{}", self.display.format_file_info(info)?)?;
+            }
+            OutputFormat::Xml => writeln!(out, "  {}", self.display.format_file_info(info)?)?,
+        }
+        Ok(())
+    }
+
+    fn close_stream_entry(
+        &self,
+        info: &FileInfo,
+        state: &mut StreamState,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        if let OutputFormat::Json = self.config.output_format {
+            state.json_separators.pop();
+            let indent = "  ".repeat(2 * state.json_separators.len());
+
+            // the remaining fields of a directory node follow its children
+            let node = self.json_node(info);
+            let fields = node
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(key, value)| Ok(format!("{}:{}", json!(key), serde_json::to_string(value)?)))
+                .collect::<Result<Vec<_>>>()?;
+            write!(out, "\n{}  ],\n{}  {}", indent, indent, fields.join(","))?;
+            write!(out, "\n{}}}", indent)?;
+        }
+        Ok(())
+    }
+
+    fn write_stream_footer(
+        &mut self,
+        root_path: &Path,
+        state: &mut StreamState,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        if self.config.disk_usage {
+            if let Some(&(size, disk_size)) = state
+                .totals
+                .as_ref()
+                .and_then(|totals| totals.get(root_path))
+            {
+                self.stats.set_disk_usage(size, disk_size);
+            }
+        }
+
+        match self.config.output_format {
+            OutputFormat::Tree => {
+                if self.config.show_stats {
+                    self.stats.finalize();
+                    writeln!(out, "{}", self.stats.display())?;
+                }
+            }
+            OutputFormat::Json => {
+                writeln!(out, "\n  ],")?;
+                writeln!(out, "  \"stats\": {{")?;
+                writeln!(out, "    \"total_dirs\": {},", self.stats.total_dirs)?;
+                writeln!(out, "    \"total_files\": {}", self.stats.total_files)?;
+                writeln!(out, "  }},")?;
+                writeln!(out, "  \"errors\": {}", self.errors_json())?;
+                writeln!(out, "}}")?;
+            }
+            OutputFormat::Xml => writeln!(out, "</tree>")?,
+            OutputFormat::Csv | OutputFormat::Markdown | OutputFormat::Ndjson => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::FileInfo;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::vec::IntoIter;
+
+#[derive(Debug, Clone)]
+pub struct TreeNode {
+    pub info: FileInfo,
+    pub children: Vec<TreeNode>,
+}
+
+// A filtered and sorted tree, as rendered by TreeVisualizer. The root itself
+// is not part of `nodes`.
+#[derive(Debug, Clone)]
+pub struct Tree {
+    pub root: PathBuf,
+    pub nodes: Vec<TreeNode>,
+}
+
+impl Tree {
+    pub fn walk(self) -> Walk {
+        Walk::new(self)
+    }
+}
+
+impl IntoIterator for Tree {
+    type Item = WalkEntry;
+    type IntoIter = Walk;
+
+    fn into_iter(self) -> Walk {
+        Walk::new(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    pub info: FileInfo,
+    // 1 for direct children of the root
+    pub depth: usize,
+    // one flag per level from the root down to this entry, telling whether the
+    // entry (or its ancestor at that level) is the last of its siblings
+    pub last_flags: Vec<bool>,
+    pub parent: PathBuf,
+}
+
+impl WalkEntry {
+    pub fn is_last(&self) -> bool {
+        self.last_flags.last().copied().unwrap_or(true)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.info.path
+    }
+}
+
+// Depth-first, pre-order iterator over a Tree
+pub struct Walk {
+    stack: Vec<(PathBuf, Peekable<IntoIter<TreeNode>>)>,
+    last_flags: Vec<bool>,
+}
+
+impl Walk {
+    fn new(tree: Tree) -> Self {
+        Self {
+            stack: vec![(tree.root, tree.nodes.into_iter().peekable())],
+            last_flags: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for Walk {
+    type Item = WalkEntry;
+
+    fn next(&mut self) -> Option<WalkEntry> {
+        loop {
+            let depth = self.stack.len();
+            let (parent, siblings) = self.stack.last_mut()?;
+
+            let Some(node) = siblings.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            let is_last = siblings.peek().is_none();
+            let parent = parent.clone();
+            self.last_flags.truncate(depth - 1);
+            self.last_flags.push(is_last);
+
+            let TreeNode { info, children } = node;
+            if !children.is_empty() {
+                self.stack
+                    .push((info.path.clone(), children.into_iter().peekable()));
+            }
+
+            return Some(WalkEntry {
+                info,
+                depth,
+                last_flags: self.last_flags.clone(),
+                parent,
+            });
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use super::{TreeNode, TreeVisualizer};
+use crate::{Error, OutputFormat, Result, TreeStats, WatchMode};
+use colored::*;
+use crossterm::cursor::MoveTo;
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType};
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+
+// --watch: filesystem events only trigger a new scan, which is compared with
+// the previous one. Filters and --git-ignore decide what counts as a change
+// the same way they decide what is shown, so a rebuild under an ignored
+// target directory redraws nothing.
+
+// Events less than this apart belong to the same burst
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// A steady stream of events still gets a scan this often
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+// How long changed entries stay highlighted in the tree
+const HIGHLIGHT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Change {
+    Added,
+    Modified,
+    Removed,
+}
+
+impl Change {
+    fn symbol(self) -> &'static str {
+        match self {
+            Change::Added => "+",
+            Change::Modified => "~",
+            Change::Removed => "-",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Change::Added => "new",
+            Change::Modified => "modified",
+            Change::Removed => "removed",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Change::Added => Color::Green,
+            Change::Modified => Color::Yellow,
+            Change::Removed => Color::Red,
+        }
+    }
+}
+
+// What is compared between two scans. Directories only count as added or
+// removed, their own mtime changes with every file created in them.
+#[derive(Debug, PartialEq)]
+struct Fingerprint {
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+type Scan = HashMap<PathBuf, Fingerprint>;
+
+impl TreeVisualizer {
+    pub(super) fn watch(
+        &mut self,
+        root_path: &Path,
+        mode: WatchMode,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        if !matches!(self.config.output_format, OutputFormat::Tree) {
+            return Err(Error::Config(
+                "--watch supports only tree output".to_string(),
+            ));
+        }
+        let clear_screen = std::io::stdout().is_terminal();
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(root_path, RecursiveMode::Recursive)?;
+
+        let nodes = self.watch_scan(root_path)?;
+        let mut previous = Self::fingerprints(&nodes);
+        let mut highlights: HashMap<PathBuf, (Change, Instant)> = HashMap::new();
+        match mode {
+            WatchMode::Tree => {
+                self.render_watch_tree(root_path, &nodes, &highlights, clear_screen, out)?
+            }
+            WatchMode::Delta => writeln!(out, "Watching {}", root_path.display())?,
+        }
+        out.flush()?;
+
+        loop {
+            // wake up to clear highlights that ran out even when nothing changes
+            let expiry = highlights
+                .values()
+                .map(|(_, since)| *since + HIGHLIGHT)
+                .min();
+            let received = match expiry {
+                Some(at) => rx.recv_timeout(at.saturating_duration_since(Instant::now())),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                // event errors such as a full inotify queue only mean that
+                // changes may have been missed, the scan catches up with them
+                Ok(_) => {
+                    let burst = Instant::now();
+                    while burst.elapsed() < MAX_DELAY && rx.recv_timeout(DEBOUNCE).is_ok() {}
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+
+            let nodes = self.watch_scan(root_path)?;
+            let current = Self::fingerprints(&nodes);
+            let changes = Self::changes(&previous, &current);
+            previous = current;
+
+            match mode {
+                WatchMode::Tree => {
+                    let now = Instant::now();
+                    let count = highlights.len();
+                    highlights.retain(|_, (_, since)| now.duration_since(*since) < HIGHLIGHT);
+                    if changes.is_empty() && highlights.len() == count {
+                        continue;
+                    }
+                    for (path, change) in changes {
+                        highlights.insert(path, (change, now));
+                    }
+                    self.render_watch_tree(root_path, &nodes, &highlights, clear_screen, out)?;
+                }
+                WatchMode::Delta => {
+                    let time = chrono::Local::now().format("%H:%M:%S");
+                    for (path, change) in changes {
+                        let relative = path.strip_prefix(root_path).unwrap_or(&path);
+                        let symbol = if self.config.use_colors {
+                            change.symbol().color(change.color()).to_string()
+                        } else {
+                            change.symbol().to_string()
+                        };
+                        writeln!(out, "[{}] {} {}", time, symbol, relative.display())?;
+                    }
+                }
+            }
+            out.flush()?;
+        }
+    }
+
+    // Statistics would otherwise add up over the scans
+    fn watch_scan(&mut self, root_path: &Path) -> Result<Vec<TreeNode>> {
+        self.stats = TreeStats::new();
+        self.filtered_tree(root_path)
+    }
+
+    fn fingerprints(nodes: &[TreeNode]) -> Scan {
+        fn visit(nodes: &[TreeNode], scan: &mut Scan) {
+            for node in nodes {
+                scan.insert(
+                    node.info.path.clone(),
+                    Fingerprint {
+                        is_dir: node.info.is_dir,
+                        size: if node.info.is_dir { 0 } else { node.info.size },
+                        modified: (!node.info.is_dir)
+                            .then_some(node.info.modified_time)
+                            .flatten(),
+                    },
+                );
+                visit(&node.children, scan);
+            }
+        }
+
+        let mut scan = Scan::new();
+        visit(nodes, &mut scan);
+        scan
+    }
+
+    fn changes(previous: &Scan, current: &Scan) -> Vec<(PathBuf, Change)> {
+        let mut changes: Vec<_> = current
+            .iter()
+            .filter_map(|(path, fingerprint)| match previous.get(path) {
+                None => Some((path.clone(), Change::Added)),
+                Some(before) if before != fingerprint => Some((path.clone(), Change::Modified)),
+                Some(_) => None,
+            })
+            .chain(
+                previous
+                    .keys()
+                    .filter(|path| !current.contains_key(*path))
+                    .map(|path| (path.clone(), Change::Removed)),
+            )
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    fn render_watch_tree(
+        &self,
+        root_path: &Path,
+        nodes: &[TreeNode],
+        highlights: &HashMap<PathBuf, (Change, Instant)>,
+        clear_screen: bool,
+        mut out: &mut dyn Write,
+    ) -> Result<()> {
+        if clear_screen {
+            execute!(&mut out, Clear(ClearType::All), MoveTo(0, 0))?;
+        }
+
+        writeln!(out, "{}", self.display.format_path(root_path)?)?;
+        self.write_watch_nodes(out, nodes, "", highlights)?;
+
+        let mut removed: Vec<_> = highlights
+            .iter()
+            .filter(|(_, (change, _))| *change == Change::Removed)
+            .map(|(path, _)| path.strip_prefix(root_path).unwrap_or(path))
+            .collect();
+        if !removed.is_empty() {
+            removed.sort();
+            writeln!(out)?;
+            for path in removed {
+                let line = format!("{} {}", Change::Removed.symbol(), path.display());
+                if self.config.use_colors {
+                    writeln!(out, "{}", line.red())?;
+                } else {
+                    writeln!(out, "{}", line)?;
+                }
+            }
+        }
+
+        let footer = "Watching for changes, press Ctrl+C to stop";
+        if self.config.use_colors {
+            writeln!(out, "\n{}", footer.dimmed())?;
+        } else {
+            writeln!(out, "\n{}", footer)?;
+        }
+        Ok(())
+    }
+
+    fn write_watch_nodes(
+        &self,
+        out: &mut dyn Write,
+        nodes: &[TreeNode],
+        prefix: &str,
+        highlights: &HashMap<PathBuf, (Change, Instant)>,
+    ) -> Result<()> {
+        for (i, node) in nodes.iter().enumerate() {
+            let is_last = i == nodes.len() - 1;
+            let connector = if is_last { "'-- " } else { "|-- " };
+            let new_prefix = if is_last { "   " } else { "|   " };
+
+            write!(
+                out,
+                "{}{}{}",
+                prefix,
+                connector,
+                self.display.format_file_info(&node.info)?
+            )?;
+            if let Some((change, _)) = highlights.get(&node.info.path) {
+                let tag = format!(" [{}]", change.label());
+                if self.config.use_colors {
+                    write!(out, "{}", tag.color(change.color()).bold())?;
+                } else {
+                    write!(out, "{}", tag)?;
+                }
+            }
+            writeln!(out)?;
+
+            if !node.children.is_empty() {
+                let child_prefix = format!("{}{}", prefix, new_prefix);
+                self.write_watch_nodes(out, &node.children, &child_prefix, highlights)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
-use regex::Regex;
-use std::path::Path;
+use crate::config::TimeField;
+use crate::{ChecksumAlgorithm, Config, Error, PathFilter};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
 
 pub fn is_hidden(path: &Path) -> bool {
     path.file_name()
@@ -35,37 +41,97 @@ pub fn format_permissions(mode: u32) -> String {
     perms
 }
 
+// Matches `pattern` as a regex against the whole path, or as a glob when it
+// is not a valid regex
+#[deprecated(note = "use PathFilter, which matches --include/--exclude globs and --regex")]
 pub fn matches_pattern(path: &Path, pattern: &str) -> bool {
-    if let Ok(regex) = Regex::new(pattern) {
-        if let Some(path_str) = path.to_str() {
-            return regex.is_match(path_str);
-        }
-    }
+    let as_regex = Config {
+        regex: Some(pattern.to_string()),
+        ..Config::default()
+    };
+    let as_glob = Config {
+        include_patterns: vec![pattern.to_string()],
+        ..Config::default()
+    };
+
+    // an empty root leaves the path as it is
+    PathFilter::new(&as_regex, Path::new(""))
+        .or_else(|_| PathFilter::new(&as_glob, Path::new("")))
+        .is_ok_and(|filter| filter.matches(path, false))
+}
+
+pub fn calculate_md5(path: &Path) -> crate::Result<String> {
+    calculate_checksum(path, ChecksumAlgorithm::Md5)
+}
+
+// Hex digest of the file contents, read in fixed-size chunks so that memory
+// use does not grow with the file size
+pub fn calculate_checksum(path: &Path, algorithm: ChecksumAlgorithm) -> crate::Result<String> {
+    digest(algorithm, |consume| read_chunks(path, consume))
+}
+
+// Hex digest of contents already in memory, like a file in a git revision
+pub fn checksum_bytes(content: &[u8], algorithm: ChecksumAlgorithm) -> String {
+    let digest = digest(algorithm, |consume| {
+        consume(content);
+        Ok(())
+    });
+    digest.expect("hashing a buffer cannot fail")
+}
 
-    // Fallback to simple glob-like matching
-    let path_str = path.to_string_lossy().to_lowercase();
-    let pattern = pattern.to_lowercase();
+// `feed` passes the contents to the hasher, in as many pieces as it likes
+fn digest(
+    algorithm: ChecksumAlgorithm,
+    feed: impl FnOnce(&mut dyn FnMut(&[u8])) -> crate::Result<()>,
+) -> crate::Result<String> {
+    use sha1::Digest;
 
-    if pattern.contains("*") {
-        let pattern_parts: Vec<&str> = pattern.split("*").collect();
-        if pattern_parts.len() == 2 {
-            return path_str.starts_with(pattern_parts[0]) && path_str.ends_with(pattern_parts[1]);
+    match algorithm {
+        ChecksumAlgorithm::Md5 => {
+            let mut context = md5::Context::new();
+            feed(&mut |chunk| context.consume(chunk))?;
+            Ok(format!("{:x}", context.finalize()))
+        }
+        ChecksumAlgorithm::Sha1 => {
+            let mut hasher = sha1::Sha1::new();
+            feed(&mut |chunk| hasher.update(chunk))?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+        ChecksumAlgorithm::Sha256 => {
+            let mut hasher = sha2::Sha256::new();
+            feed(&mut |chunk| hasher.update(chunk))?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+        ChecksumAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            feed(&mut |chunk| {
+                hasher.update(chunk);
+            })?;
+            Ok(hasher.finalize().to_hex().to_string())
+        }
+        ChecksumAlgorithm::Xxh3 => {
+            let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+            feed(&mut |chunk| hasher.update(chunk))?;
+            Ok(format!("{:016x}", hasher.digest()))
         }
     }
-
-    path_str.contains(&pattern)
 }
 
-pub fn calculate_md5(path: &Path) -> crate::Result<String> {
-    use std::fs::File;
-    use std::io::Read;
+const CHUNK_SIZE: usize = 64 * 1024;
 
-    let mut file = File::open(path)?;
-    let mut contents = Vec::new();
-    file.read_to_end(&mut contents)?;
+fn read_chunks(path: &Path, consume: &mut dyn FnMut(&[u8])) -> crate::Result<()> {
+    use std::io::Read;
 
-    let digest = md5::compute(&contents);
-    Ok(format!("{:x}", digest))
+    let mut file = std::fs::File::open(path).map_err(|e| Error::io(path, e))?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => consume(&buffer[..read]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::io(path, e)),
+        }
+    }
 }
 
 pub fn format_time(timestamp: std::time::SystemTime) -> String {
@@ -93,12 +159,122 @@ pub fn count_files_in_dir(path: &Path) -> (usize, usize) {
     (file_count, dir_count)
 }
 
+#[cfg(unix)]
+pub fn disk_size(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks is always counted in 512-byte units
+    Some(metadata.blocks() * 512)
+}
+
+#[cfg(not(unix))]
+pub fn disk_size(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn is_repeated_hardlink(metadata: &Metadata, seen: &mut HashSet<(u64, u64)>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1 && !seen.insert((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn is_repeated_hardlink(_metadata: &Metadata, _seen: &mut HashSet<(u64, u64)>) -> bool {
+    false
+}
+
+// Sum apparent and on-disk sizes for every directory under root, like `du`.
+// Hardlinked files are only counted once.
+pub fn directory_totals(root: &Path, follow_links: bool) -> HashMap<PathBuf, (u64, u64)> {
+    let mut usage = DiskUsage::new(root, follow_links);
+    usage.add_tree(root);
+    usage.into_totals()
+}
+
+// Directory totals built up from the entries of a walk. Whatever the walk
+// does not enter, such as hidden directories or the levels below -L, is
+// added with add_tree or add_below.
+pub struct DiskUsage {
+    root: PathBuf,
+    follow_links: bool,
+    totals: HashMap<PathBuf, (u64, u64)>,
+    seen_inodes: HashSet<(u64, u64)>,
+}
+
+impl DiskUsage {
+    pub fn new(root: &Path, follow_links: bool) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            follow_links,
+            totals: HashMap::new(),
+            seen_inodes: HashSet::new(),
+        }
+    }
+
+    // `metadata` follows links the same way as the walk
+    pub fn add(&mut self, path: &Path, metadata: &Metadata) {
+        if is_repeated_hardlink(metadata, &mut self.seen_inodes) {
+            return;
+        }
+
+        let apparent = if metadata.is_dir() { 0 } else { metadata.len() };
+        let on_disk = disk_size(metadata).unwrap_or(apparent);
+
+        let mut current = if metadata.is_dir() {
+            Some(path)
+        } else {
+            path.parent()
+        };
+        while let Some(dir) = current {
+            match self.totals.get_mut(dir) {
+                Some(total) => {
+                    total.0 += apparent;
+                    total.1 += on_disk;
+                }
+                None => {
+                    self.totals.insert(dir.to_path_buf(), (apparent, on_disk));
+                }
+            }
+            if dir == self.root {
+                break;
+            }
+            current = dir.parent();
+        }
+    }
+
+    // `path` and everything below it
+    pub fn add_tree(&mut self, path: &Path) {
+        self.walk(path, 0);
+    }
+
+    // everything below the directory `dir`, which has been added already
+    pub fn add_below(&mut self, dir: &Path) {
+        self.walk(dir, 1);
+    }
+
+    fn walk(&mut self, path: &Path, min_depth: usize) {
+        for entry in WalkDir::new(path)
+            .min_depth(min_depth)
+            .follow_links(self.follow_links)
+            .into_iter()
+            .flatten()
+        {
+            if let Ok(metadata) = entry.metadata() {
+                self.add(entry.path(), &metadata);
+            }
+        }
+    }
+
+    pub fn into_totals(self) -> HashMap<PathBuf, (u64, u64)> {
+        self.totals
+    }
+}
+
 pub fn parse_size(size_str: &Option<String>) -> crate::Result<Option<u64>> {
     match size_str {
         Some(s) => {
             let s = s.trim().to_uppercase();
             if s.is_empty() {
-                return Err("Invalid size format ".into());
+                return Err(Error::Config("Invalid size format ".to_string()));
             }
 
             let (number_part, unit) = if s.ends_with("TB") || s.ends_with("T") {
@@ -139,14 +315,116 @@ pub fn parse_size(size_str: &Option<String>) -> crate::Result<Option<u64>> {
             match number_part.parse::<f64>() {
                 Ok(num) => {
                     if num < 0.0 {
-                        Err(format!("Invalid size format {}", s).into())
+                        Err(Error::Config(format!("Invalid size format {}", s)))
                     } else {
                         Ok(Some((num * unit as f64) as u64))
                     }
                 }
-                Err(_) => Err(format!("Invalid size format {}", s).into()),
+                Err(_) => Err(Error::Config(format!("Invalid size format {}", s))),
             }
         }
         None => Ok(None),
     }
 }
+
+// A number followed by s, m, h, d or w. Units can be chained, as in 1h30m.
+pub fn parse_duration(value: &str) -> crate::Result<Duration> {
+    let invalid = || {
+        Error::Config(format!(
+            "Invalid duration `{}`, expected e.g. 30m, 2d or 1h30m",
+            value
+        ))
+    };
+
+    let text = value.trim();
+    if text.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let count: u64 = number.parse().map_err(|_| invalid())?;
+        total = count
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+// A duration before now (2d), a local date (2026-01-01), a local date and
+// time (2026-01-01 12:00) or an RFC 3339 timestamp
+pub fn parse_time(value: &str) -> crate::Result<DateTime<Utc>> {
+    let invalid = || {
+        Error::Config(format!(
+            "Invalid time `{}`, expected a duration like 2d or a date like 2026-01-01",
+            value
+        ))
+    };
+
+    let text = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let local = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(|date| date.and_time(NaiveTime::MIN))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M"))
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M"));
+    if let Ok(local) = local {
+        return Local
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+            .ok_or_else(invalid);
+    }
+
+    let duration = parse_duration(text).map_err(|_| invalid())?;
+    chrono::Duration::from_std(duration)
+        .ok()
+        .and_then(|duration| Utc::now().checked_sub_signed(duration))
+        .ok_or_else(invalid)
+}
+
+pub fn file_time(metadata: &Metadata, field: TimeField) -> Option<SystemTime> {
+    match field {
+        TimeField::Mtime => metadata.modified().ok(),
+        TimeField::Atime => metadata.accessed().ok(),
+        // not every filesystem records it
+        TimeField::Birth => metadata.created().ok(),
+        TimeField::Ctime => change_time(metadata),
+    }
+}
+
+#[cfg(unix)]
+fn change_time(metadata: &Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    let seconds = u64::try_from(metadata.ctime()).ok()?;
+    let nanos = u32::try_from(metadata.ctime_nsec()).ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::new(seconds, nanos))
+}
+
+#[cfg(not(unix))]
+fn change_time(_metadata: &Metadata) -> Option<SystemTime> {
+    None
+}
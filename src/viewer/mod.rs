@@ -5,7 +5,7 @@ pub mod parquet_viewer;
 pub mod structured;
 pub mod text;
 
-use crate::Result;
+use crate::{Error, Result};
 use std::path::Path;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -98,7 +98,7 @@ impl FileViewer {
         };
 
         // Determine strategy based on file size and user preference
-        let metadata = std::fs::metadata(path)?;
+        let metadata = std::fs::metadata(path).map_err(|e| Error::io(path, e))?;
         let file_size = metadata.len();
 
         let strategy = match self.strategy {
@@ -106,7 +106,7 @@ impl FileViewer {
             _ => self.strategy.clone(),
         };
 
-        match format {
+        let result = match format {
             ViewerFormat::Text => self.view_text_with_strategy(path, strategy),
             ViewerFormat::Binary => binary::view_binary_file(path, self.max_bytes),
             ViewerFormat::Json => self.view_json_with_strategy(path, strategy),
@@ -115,6 +115,39 @@ impl FileViewer {
             ViewerFormat::Csv => self.view_csv_with_strategy(path, strategy),
             ViewerFormat::Parquet => parquet_viewer::view_parquet_file(path, self.max_lines),
             ViewerFormat::Auto => unreachable!(),
+        };
+
+        result.map_err(|e| e.with_path(path))
+    }
+
+    // Contents that are not on disk, like a file in a git revision. The
+    // viewers read from a path, so the contents go to a file of the same name
+    // in a private temporary directory, which also keeps the format detection
+    // by extension working.
+    pub fn view_bytes(&self, path: &Path, content: &[u8]) -> Result<String> {
+        let name = path.file_name().ok_or_else(|| Error::not_found(path))?;
+        let dir = tempfile::Builder::new()
+            .prefix("denarborea-view-")
+            .tempdir()
+            .map_err(|e| Error::io(&std::env::temp_dir(), e))?;
+        let temp_path = dir.path().join(name);
+
+        let result = std::fs::write(&temp_path, content)
+            .map_err(|e| Error::io(&temp_path, e))
+            .and_then(|()| self.view_file(&temp_path));
+
+        // headers and errors name the file that was asked for
+        match result {
+            Ok(output) => Ok(output.replace(
+                &temp_path.display().to_string(),
+                &path.display().to_string(),
+            )),
+            Err(mut e) => {
+                if let Error::Io { path: p, .. } | Error::Parse { path: p, .. } = &mut e {
+                    *p = Some(path.to_path_buf());
+                }
+                Err(e)
+            }
         }
     }
 
@@ -203,13 +236,18 @@ pub fn is_binary_file(path: &Path) -> bool {
     if let Ok(mut file) = File::open(path) {
         let mut buffer = [0; 512];
         if let Ok(bytes_read) = file.read(&mut buffer) {
-            // Check for null bytes (common in binary files)
-            return buffer[..bytes_read].contains(&0);
+            return is_binary(&buffer[..bytes_read]);
         }
     }
     false
 }
 
+// Same check on contents already in memory, only the start is looked at
+pub fn is_binary(content: &[u8]) -> bool {
+    // Check for null bytes (common in binary files)
+    content[..content.len().min(512)].contains(&0)
+}
+
 pub fn format_file_size(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     const THRESHOLD: f64 = 1024.0;
@@ -1,11 +1,12 @@
-use crate::Result;
+use crate::{Error, Result};
 use serde_json::Value as JsonValue;
 use std::fs;
 use std::path::Path;
 
 pub fn view_json_file(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path)?;
-    let json: JsonValue = serde_json::from_str(&content)?;
+    let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    let json: JsonValue =
+        serde_json::from_str(&content).map_err(|e| Error::from(e).with_path(path))?;
 
     let mut output = String::new();
     output.push_str(&format!("📋 JSON File: {}\n", path.display()));
@@ -25,7 +26,7 @@ pub fn view_json_file(path: &Path) -> Result<String> {
 }
 
 pub fn view_yaml_file(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path)?;
+    let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
 
     let mut output = String::new();
     output.push_str(&format!("📄 YAML File: {}\n", path.display()));
@@ -55,7 +56,7 @@ pub fn view_yaml_file(path: &Path) -> Result<String> {
 }
 
 pub fn view_toml_file(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path)?;
+    let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
 
     let mut output = String::new();
     output.push_str(&format!("⚙️  TOML File: {}\n", path.display()));
@@ -204,6 +204,35 @@ fn test_cli_invalid_path() {
         .stderr(predicate::str::contains("does not exist"));
 }
 
+#[test]
+fn test_cli_exit_codes() {
+    let fixture = TestFixture::new();
+    fixture.create_file("bad.json", r#"{"incomplete": true"#);
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path().join("missing"))
+        .assert()
+        .code(3);
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg("--view")
+        .arg(fixture.path().join("bad.json"))
+        .assert()
+        .code(6)
+        .stderr(predicate::str::contains("invalid JSON at line 1"));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg("--min-size")
+        .arg("1XB")
+        .arg(fixture.path())
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains("Invalid size format"));
+}
+
 #[test]
 fn test_cli_invalid_extension() {
     let fixture = TestFixture::new();
@@ -227,3 +256,1071 @@ fn test_cli_conflicting_flags() {
         .assert()
         .success(); // Should handle conflicting flags gracefully
 }
+
+#[test]
+fn test_cli_interactive_requires_terminal() {
+    let fixture = TestFixture::new();
+    fixture.create_file("file.txt", "content");
+
+    let mut cmd = Command::cargo_bin("denarborea").unwrap();
+    cmd.arg("--interactive")
+        .arg(fixture.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("requires a terminal"));
+}
+
+#[test]
+fn test_cli_disk_usage_mode() {
+    let fixture = TestFixture::new();
+    fixture.create_file("small/a.txt", "x");
+    fixture.create_file("big/b.txt", &"x".repeat(5000));
+    fixture.create_file("big/nested/c.txt", &"x".repeat(5000));
+
+    let mut cmd = Command::cargo_bin("denarborea").unwrap();
+    let output = cmd
+        .arg("--du")
+        .arg("--sort")
+        .arg("size")
+        .arg("--reverse")
+        .arg("--no-color")
+        .arg("-L")
+        .arg("1")
+        .arg(fixture.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("big[10 kB]"));
+    assert!(stdout.contains("small[1 B]"));
+    assert!(stdout.find("big").unwrap() < stdout.find("small").unwrap());
+}
+
+#[test]
+fn test_cli_disk_usage_stats() {
+    let fixture = TestFixture::new();
+    fixture.create_file("dir/a.txt", "hello");
+
+    let mut cmd = Command::cargo_bin("denarborea").unwrap();
+    cmd.arg("--du")
+        .arg("--stats")
+        .arg(fixture.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Disk usage: 5 B"))
+        .stdout(predicate::str::contains("Largest directory: dir"));
+}
+
+#[test]
+fn test_cli_disk_usage_counts_what_the_walk_skips() {
+    let fixture = TestFixture::new();
+    fixture.create_file("dir/a.txt", "x");
+    fixture.create_file("dir/.cache/b.txt", &"x".repeat(100));
+    fixture.create_file("dir/nested/deep/c.txt", &"x".repeat(1000));
+    fixture.create_file("dir/skip.log", &"x".repeat(10));
+
+    // hidden entries, excluded ones and the levels below -L are left out of
+    // the listing but still part of the totals
+    for threads in ["1", "4"] {
+        let stdout = run_denarborea(
+            &[
+                "--du",
+                "--no-color",
+                "-L",
+                "2",
+                "--exclude",
+                "*.log",
+                "--threads",
+                threads,
+            ],
+            fixture.path(),
+        );
+        assert!(
+            stdout.contains("dir[1.11 kB]"),
+            "threads {}: {}",
+            threads,
+            stdout
+        );
+        assert!(
+            stdout.contains("nested[1 kB]"),
+            "threads {}: {}",
+            threads,
+            stdout
+        );
+        assert!(!stdout.contains(".cache"));
+        assert!(!stdout.contains("deep"));
+    }
+}
+
+fn run_denarborea(args: &[&str], path: &std::path::Path) -> String {
+    let output = Command::cargo_bin("denarborea")
+        .unwrap()
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn create_stream_fixture() -> TestFixture {
+    let fixture = TestFixture::new();
+    fixture.create_file("src/main.rs", "fn main() {}");
+    fixture.create_file("src/lib/mod.rs", "pub mod a;");
+    fixture.create_file("docs/guide.md", "# Guide");
+    fixture.create_file("docs/empty/notes.txt", "notes");
+    fixture.create_file("README.md", "readme");
+    fixture
+}
+
+#[test]
+fn test_cli_stream_matches_buffered_tree() {
+    let fixture = create_stream_fixture();
+    fixture.create_file(".hidden/secret.rs", "secret");
+
+    for args in [
+        vec!["--no-color"],
+        vec!["--no-color", "-a"],
+        vec!["--no-color", "-e", "rs"],
+        vec!["--no-color", "-L", "1"],
+        vec!["--no-color", "--sort", "size", "-s"],
+    ] {
+        let buffered = run_denarborea(&args, fixture.path());
+        let mut stream_args = args.clone();
+        stream_args.push("--stream");
+        let streamed = run_denarborea(&stream_args, fixture.path());
+        assert_eq!(buffered, streamed, "output differs for {:?}", args);
+    }
+}
+
+#[test]
+fn test_cli_stream_json_matches_buffered() {
+    let fixture = create_stream_fixture();
+    // directory nodes then carry a nested `git` object
+    #[cfg(feature = "git")]
+    git2::Repository::init(fixture.path()).unwrap();
+
+    for args in [
+        vec!["--format", "json"],
+        vec!["--format", "json", "--git-status"],
+    ] {
+        let buffered: serde_json::Value =
+            serde_json::from_str(&run_denarborea(&args, fixture.path())).unwrap();
+        let mut stream_args = args.clone();
+        stream_args.push("--stream");
+        let streamed: serde_json::Value =
+            serde_json::from_str(&run_denarborea(&stream_args, fixture.path())).unwrap();
+
+        assert_eq!(buffered, streamed, "output differs for {:?}", args);
+    }
+}
+
+#[test]
+fn test_cli_ndjson_counts_children() {
+    let fixture = TestFixture::new();
+    fixture.create_file("src/main.rs", "fn main() {}");
+    fixture.create_file("src/lib.rs", "");
+    fixture.create_dir("src/bin");
+
+    for args in [
+        vec!["--format", "ndjson"],
+        vec!["--format", "ndjson", "--stream"],
+    ] {
+        let output = run_denarborea(&args, fixture.path());
+        let src = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .find(|entry| entry["name"] == "src")
+            .unwrap();
+        assert_eq!(src["file_count"], 2, "output differs for {:?}", args);
+        assert_eq!(src["dir_count"], 1, "output differs for {:?}", args);
+    }
+}
+
+#[test]
+fn test_cli_ndjson_format() {
+    let fixture = create_stream_fixture();
+
+    for args in [
+        vec!["--format", "ndjson"],
+        vec!["--format", "ndjson", "--stream"],
+    ] {
+        let output = run_denarborea(&args, fixture.path());
+        let names: Vec<String> = output
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                value["name"].as_str().unwrap().to_string()
+            })
+            .collect();
+
+        assert_eq!(names.len(), 9);
+        assert!(names.contains(&"mod.rs".to_string()));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unreadable_entries_do_not_abort() {
+    let fixture = TestFixture::new();
+    fixture.create_file("sub/ok.txt", "fine");
+    std::os::unix::fs::symlink("/nonexistent/target", fixture.path().join("sub/broken")).unwrap();
+
+    let tree = run_denarborea(&["--no-color", "--follow-links", "--stats"], fixture.path());
+    assert!(tree.contains("broken[error: entity not found]"));
+    assert!(tree.contains("ok.txt"));
+    assert!(tree.contains("Errors: 1"));
+
+    let json = run_denarborea(&["--format", "json", "--follow-links"], fixture.path());
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let errors = json["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["error"], "entity not found");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .args(["--follow-links", "--strict"])
+        .arg(fixture.path())
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn test_layered_config_precedence() {
+    let fixture = TestFixture::new();
+    fixture.create_file(
+        "home/denarborea/config.toml",
+        "max_depth = 5\nshow_size = true\n",
+    );
+    fixture.create_file(
+        "project/.denarborea.toml",
+        "max_depth = 4\nsort_by = \"size\"\n",
+    );
+    fixture.create_file("project/src/main.rs", "fn main() {}");
+
+    let print_config = |envs: &[(&str, &str)], args: &[&str]| {
+        let output = Command::cargo_bin("denarborea")
+            .unwrap()
+            .env("XDG_CONFIG_HOME", fixture.path().join("home"))
+            .envs(envs.iter().copied())
+            .args(args)
+            .arg("--print-config")
+            .arg(fixture.path().join("project/src"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let config = print_config(&[], &[]);
+    assert!(config.contains("max_depth = 4"));
+    assert!(config.contains("show_size = true"));
+    assert!(config.contains("sort_by = \"size\""));
+
+    let config = print_config(&[("DENARBOREA_MAX_DEPTH", "3")], &[]);
+    assert!(config.contains("max_depth = 3"));
+
+    let config = print_config(
+        &[("DENARBOREA_MAX_DEPTH", "3")],
+        &["-L", "2", "--sort", "name"],
+    );
+    assert!(config.contains("max_depth = 2"));
+    assert!(config.contains("sort_by = \"name\""));
+}
+
+#[test]
+fn test_save_config_persists_flags() {
+    let fixture = TestFixture::new();
+    let config_file = fixture.path().join("home/denarborea/config.toml");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", fixture.path().join("home"))
+        .args(["--save-config", "-a", "--min-size", "1KB"])
+        .assert()
+        .success();
+
+    let saved = std::fs::read_to_string(&config_file).unwrap();
+    assert!(saved.contains("show_hidden = true"));
+    assert!(saved.contains("min_size = 1024"));
+    assert!(!saved.contains("show_size"));
+}
+
+#[test]
+fn test_profile_selection() {
+    let fixture = TestFixture::new();
+    fixture.create_file(
+        "home/denarborea/config.toml",
+        "[profile.audit]\npermissions = true\n\n[profile.strict-audit]\ninherits = \"audit\"\nstrict = true\n",
+    );
+    fixture.create_file("project/file.txt", "content");
+
+    let output = Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", fixture.path().join("home"))
+        .args(["--profile", "strict-audit", "--print-config"])
+        .arg(fixture.path().join("project"))
+        .output()
+        .unwrap();
+    let config = String::from_utf8(output.stdout).unwrap();
+    assert!(config.contains("show_permissions = true"));
+    assert!(config.contains("strict = true"));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CONFIG_HOME", fixture.path().join("home"))
+        .args(["--profile", "missing"])
+        .arg(fixture.path().join("project"))
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains("Unknown profile `missing`"));
+}
+
+#[test]
+fn test_diff_subcommand() {
+    let fixture = TestFixture::new();
+    // keep the checksum cache out of the user's cache dir
+    let cache_home = fixture.path().join("cache");
+    fixture.create_file("left/kept.txt", "same");
+    fixture.create_file("left/old.txt", "old");
+    fixture.create_file("right/new.txt", "new");
+    std::fs::copy(
+        fixture.path().join("left/kept.txt"),
+        fixture.path().join("right/kept.txt"),
+    )
+    .unwrap();
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("diff")
+        .arg(fixture.path().join("left"))
+        .arg(fixture.path().join("right"))
+        .arg("--no-color")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("A new.txt"))
+        .stdout(predicate::str::contains("D old.txt"))
+        .stdout(predicate::str::contains("1 added, 1 removed"));
+
+    let output = Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("diff")
+        .arg(fixture.path().join("left"))
+        .arg(fixture.path().join("right"))
+        .args(["--format", "json", "--exclude", "old.txt"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["summary"]["added"], 1);
+    assert_eq!(json["summary"]["removed"], 0);
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("diff")
+        .arg(fixture.path().join("left"))
+        .arg(fixture.path().join("left"))
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_snapshot_and_verify() {
+    let fixture = TestFixture::new();
+    // keep the checksum cache out of the user's cache dir
+    let cache_home = fixture.path().join("cache");
+    fixture.create_file("tree/app.bin", "binary");
+    let manifest = fixture.path().join("manifest.json");
+    let tree = fixture.path().join("tree");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg(&tree)
+        .arg("--checksum")
+        .arg("--snapshot")
+        .arg(&manifest)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Snapshot of 1 entries"));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg(&tree)
+        .arg("--verify")
+        .arg(&manifest)
+        .assert()
+        .success();
+
+    fixture.create_file("tree/extra.txt", "extra");
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg(&tree)
+        .args(["--no-color", "--verify"])
+        .arg(&manifest)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("extra    extra.txt"));
+}
+
+#[test]
+fn test_checksum_algorithm_flag() {
+    let fixture = TestFixture::new();
+    fixture.create_file("hello.txt", "hello");
+
+    let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .args([
+            "--checksum=sha256",
+            "--format",
+            "json",
+            "--no-checksum-cache",
+        ])
+        .arg(fixture.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(sha256));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .args(["--checksum=sha256", "--no-color", "--no-checksum-cache"])
+        .arg(fixture.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("<2cf24dba>"));
+
+    // without `=` the next argument is the path, not an algorithm
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .args(["--no-color", "--no-checksum-cache", "--checksum"])
+        .arg(fixture.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("<5d41402a>"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_checksum_cache_and_prune() {
+    let fixture = TestFixture::new();
+    fixture.create_file("tree/a.txt", "alpha");
+    let cache_home = fixture.path().join("cache");
+    let cache_file = cache_home.join("denarborea/checksums.json");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .args(["--checksum", "--no-checksum-cache"])
+        .arg(fixture.path().join("tree"))
+        .assert()
+        .success();
+    assert!(!cache_file.exists());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("--checksum=sha256")
+        .arg(fixture.path().join("tree"))
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(&cache_file)
+        .unwrap()
+        .contains(":sha256"));
+
+    std::fs::remove_file(fixture.path().join("tree/a.txt")).unwrap();
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .args(["cache", "prune"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 1 stale entries, 0 left"));
+}
+
+#[test]
+fn test_scan_cache() {
+    let fixture = TestFixture::new();
+    fixture.create_file("tree/src/a.rs", "fn a() {}");
+    fixture.create_file("tree/b.txt", "b");
+    let cache_home = fixture.path().join("cache");
+    let scans = cache_home.join("denarborea/scans");
+    let run = |args: &[&str]| {
+        let output = Command::cargo_bin("denarborea")
+            .unwrap()
+            .env("XDG_CACHE_HOME", &cache_home)
+            .arg(fixture.path().join("tree"))
+            .args(args)
+            .arg("--no-color")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let uncached = run(&["-s"]);
+    assert!(!scans.exists());
+    assert_eq!(run(&["-s", "--cache"]), uncached);
+    assert_eq!(std::fs::read_dir(&scans).unwrap().count(), 1);
+    assert_eq!(run(&["-s", "--cache"]), uncached);
+
+    fixture.create_file("tree/src/c.rs", "fn c() {}");
+    assert!(run(&["--cache"]).contains("c.rs"));
+    assert!(run(&["--no-cache"]).contains("c.rs"));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("XDG_CACHE_HOME", &cache_home)
+        .args(["cache", "clear"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 1 scan indexes"));
+}
+
+#[cfg(feature = "git")]
+#[test]
+fn test_git_changed_only() {
+    let fixture = TestFixture::new();
+    let repo = fixture.create_dir("repo");
+    git2::Repository::init(&repo).unwrap();
+    fixture.create_file("repo/clean/old.txt", "old");
+    fixture.create_file("repo/lib/edited.rs", "old");
+    fixture.create_file("repo/src/main.rs", "fn main() {}");
+    let git_repo = git2::Repository::open(&repo).unwrap();
+    let mut index = git_repo.index().unwrap();
+    index
+        .add_path(std::path::Path::new("clean/old.txt"))
+        .unwrap();
+    index
+        .add_path(std::path::Path::new("lib/edited.rs"))
+        .unwrap();
+    index.write().unwrap();
+    let tree = git_repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    git_repo
+        .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+        .unwrap();
+    fixture.create_file("repo/lib/edited.rs", "changed");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(&repo)
+        .args(["--git-changed-only", "--git-status", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("M lib[1 modified]"))
+        .stdout(predicate::str::contains("M edited.rs"))
+        .stdout(predicate::str::contains("'-- ?? src\n"))
+        .stdout(predicate::str::contains("?? main.rs"))
+        .stdout(predicate::str::contains("clean").not());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(&repo)
+        .args(["--git-changed-only", "--git-status", "--format", "ndjson"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""git":{"index":"clean","worktree":"modified"}"#,
+        ));
+}
+
+#[cfg(feature = "git")]
+#[test]
+fn test_git_changed_only_shows_deleted_files() {
+    let fixture = TestFixture::new();
+    let repo = fixture.create_dir("repo");
+    let git_repo = git2::Repository::init(&repo).unwrap();
+    fixture.create_file("repo/lib/kept.rs", "kept");
+    fixture.create_file("repo/lib/removed.rs", "removed");
+    fixture.create_file("repo/gone/old.rs", "old");
+    fixture.create_file("repo/staged.txt", "staged");
+    let mut index = git_repo.index().unwrap();
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+    let tree = git_repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    git_repo
+        .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+        .unwrap();
+
+    fs::remove_file(repo.join("lib/removed.rs")).unwrap();
+    fs::remove_dir_all(repo.join("gone")).unwrap();
+    // deleted from the index as well, as `git rm` does
+    fs::remove_file(repo.join("staged.txt")).unwrap();
+    index
+        .remove_path(std::path::Path::new("staged.txt"))
+        .unwrap();
+    index.write().unwrap();
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(&repo)
+        .args(["--git-changed-only", "--git-status", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" D lib[1 deleted]"))
+        .stdout(predicate::str::contains(" D removed.rs"))
+        .stdout(predicate::str::contains(" D gone[1 deleted]"))
+        .stdout(predicate::str::contains(" D old.rs"))
+        .stdout(predicate::str::contains("D  staged.txt"))
+        .stdout(predicate::str::contains("kept.rs").not());
+}
+
+#[cfg(feature = "git")]
+#[test]
+fn test_rev_shows_and_views_a_past_commit() {
+    let fixture = TestFixture::new();
+    let repo = fixture.create_dir("repo");
+    let git_repo = git2::Repository::init(&repo).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    let commit = |message: &str| {
+        let mut index = git_repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.update_all(["*"], None).unwrap();
+        index.write().unwrap();
+        let tree = git_repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = git_repo
+            .head()
+            .ok()
+            .map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        git_repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .unwrap();
+    };
+    fixture.create_file("repo/docs/notes.json", r#"{"draft": true}"#);
+    fixture.create_file("repo/main.rs", "fn main() {}");
+    commit("one");
+    fs::remove_dir_all(repo.join("docs")).unwrap();
+    commit("two");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(&repo)
+        .args(["--rev", "HEAD~1", "--size", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("notes.json[15 B]"))
+        .stdout(predicate::str::contains("main.rs[12 B]"));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg("--view")
+        .arg(repo.join("docs/notes.json"))
+        .args(["--rev", "HEAD~1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("JSON File"))
+        .stdout(predicate::str::contains("\"draft\": true"));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(&repo)
+        .args(["--rev", "no-such-branch"])
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "unknown revision `no-such-branch`",
+        ));
+
+    // the conflicts of --rev also hold when it comes from the environment
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("DENARBOREA_REVISION", "HEAD~1")
+        .arg(&repo)
+        .arg("--stream")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "`revision` cannot be combined with `stream`",
+        ));
+}
+
+#[cfg(feature = "git")]
+#[test]
+fn test_git_diff_between_revisions() {
+    let fixture = TestFixture::new();
+    let repo = fixture.create_dir("repo");
+    let git_repo = git2::Repository::init(&repo).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    let commit = |message: &str| {
+        let mut index = git_repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = git_repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = git_repo
+            .head()
+            .ok()
+            .map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        git_repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .unwrap();
+    };
+    fixture.create_file("repo/src/main.rs", "fn main() {}\n");
+    commit("one");
+    fixture.create_file("repo/src/main.rs", "fn main() {\n    run();\n}\n");
+    fixture.create_file("repo/README.md", "# repo\n");
+    commit("two");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(&repo)
+        .args(["--git-diff", "HEAD~1..HEAD", "--no-color"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("HEAD~1 -> HEAD"))
+        .stdout(predicate::str::contains("|-- A README.md[+1 -0]"))
+        .stdout(predicate::str::contains("'--   src[+3 -1]"))
+        .stdout(predicate::str::contains("'-- M main.rs[+3 -1]"));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(&repo)
+        .args(["--git-diff", "HEAD", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""to": "worktree""#))
+        .stdout(predicate::str::contains(r#""changes": []"#));
+
+    // only taken from the command line, a plain run stays a plain run
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .env("DENARBOREA_GIT_DIFF", "HEAD~1..HEAD")
+        .arg(&repo)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("DENARBOREA_GIT_DIFF"));
+}
+
+#[test]
+fn test_duplicates_flag() {
+    let fixture = TestFixture::new();
+    fixture.create_file("a/one.txt", "duplicate");
+    fixture.create_file("b/two.txt", "duplicate");
+    fixture.create_file("c/unique.txt", "unique");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--duplicates", "--no-color", "--no-checksum-cache"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("one.txt [duplicate #1]"))
+        .stdout(predicate::str::contains("two.txt [duplicate #1]"))
+        .stdout(predicate::str::contains("unique.txt").not())
+        .stdout(predicate::str::contains("Total: 1 sets, 9 B wasted"));
+
+    let output = Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--duplicates", "--format", "json", "--no-checksum-cache"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total_sets"], 1);
+    assert_eq!(json["total_wasted"], 9);
+    assert_eq!(json["sets"][0]["paths"][1], "b/two.txt");
+}
+
+#[test]
+fn test_duplicates_skip_hidden_directories() {
+    let fixture = TestFixture::new();
+    fixture.create_file(".git/logs/HEAD", "0000 1111 commit");
+    fixture.create_file(".git/logs/refs/heads/master", "0000 1111 commit");
+    fixture.create_file("src/main.rs", "fn main() {}");
+
+    for threads in ["1", "4"] {
+        Command::cargo_bin("denarborea")
+            .unwrap()
+            .arg(fixture.path())
+            .args(["--duplicates", "--no-color", "--no-checksum-cache"])
+            .args(["--threads", threads])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("HEAD").not())
+            .stdout(predicate::str::contains("No duplicates found"));
+    }
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--duplicates", "-a", "--no-color", "--no-checksum-cache"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("HEAD [duplicate #1]"));
+}
+
+#[test]
+fn test_time_filters() {
+    let fixture = TestFixture::new();
+    let old = fixture.create_file("archive/old.txt", "old");
+    let reference = fixture.create_file("reference.txt", "reference");
+    fixture.create_file("recent/new.txt", "new");
+    let past = std::time::SystemTime::now() - std::time::Duration::from_secs(30 * 24 * 60 * 60);
+    std::fs::File::options()
+        .write(true)
+        .open(&old)
+        .unwrap()
+        .set_modified(past)
+        .unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&reference)
+        .unwrap()
+        .set_modified(past + std::time::Duration::from_secs(60))
+        .unwrap();
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--changed-within", "1d", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("new.txt"))
+        .stdout(predicate::str::contains("archive").not());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--older", "1w", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old.txt"))
+        .stdout(predicate::str::contains("recent").not());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .arg("--newer-than")
+        .arg(&old)
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("reference.txt"))
+        .stdout(predicate::str::contains("old.txt").not());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--newer", "last week"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid time `last week`"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_newer_than_uses_configured_time_field() {
+    let fixture = TestFixture::new();
+    let past = std::time::SystemTime::now() - std::time::Duration::from_secs(30 * 24 * 60 * 60);
+    let reference = fixture.create_file("reference.txt", "reference");
+    std::fs::File::options()
+        .write(true)
+        .open(&reference)
+        .unwrap()
+        .set_modified(past)
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    // older mtime than the reference, but a newer ctime
+    let touched = fixture.create_file("tree/touched.txt", "touched");
+    std::fs::File::options()
+        .write(true)
+        .open(&touched)
+        .unwrap()
+        .set_modified(past - std::time::Duration::from_secs(60))
+        .unwrap();
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path().join("tree"))
+        .arg("--newer-than")
+        .arg(&reference)
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("touched.txt").not());
+
+    fixture.create_file("tree/.denarborea.toml", "time_field = \"ctime\"\n");
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path().join("tree"))
+        .arg("--newer-than")
+        .arg(&reference)
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("touched.txt"));
+}
+
+#[test]
+fn test_multiple_include_globs() {
+    let fixture = TestFixture::new();
+    fixture.create_file("src/main.rs", "fn main() {}");
+    fixture.create_file("src/util/test_io.rs", "");
+    fixture.create_file("Cargo.toml", "");
+    fixture.create_file("notes.txt", "");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--include", "src/**/test_*.rs", "--include", "*.toml"])
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("test_io.rs"))
+        .stdout(predicate::str::contains("Cargo.toml"))
+        .stdout(predicate::str::contains("main.rs").not())
+        .stdout(predicate::str::contains("notes.txt").not());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--iregex", "MAIN\\.RS$", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("main.rs"))
+        .stdout(predicate::str::contains("Cargo.toml").not());
+}
+
+#[test]
+fn test_where_expression() {
+    let fixture = TestFixture::new();
+    fixture.create_file("src/main.rs", &"x".repeat(2048));
+    fixture.create_file("src/small.rs", "");
+    fixture.create_file("target/debug/big.rs", &"x".repeat(2048));
+    fixture.create_file("notes.txt", &"x".repeat(2048));
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args([
+            "--where",
+            "ext in (rs, py) and size > 1KB and not path ~ 'target/**'",
+        ])
+        .arg("--no-color")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("main.rs"))
+        .stdout(predicate::str::contains("small.rs").not())
+        .stdout(predicate::str::contains("big.rs").not())
+        .stdout(predicate::str::contains("target").not())
+        .stdout(predicate::str::contains("notes.txt").not());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--where", "size > 1KB or"])
+        .assert()
+        .code(6)
+        .stderr(predicate::str::contains("column 14: expected a field"))
+        .stderr(predicate::str::contains("             ^"));
+}
+
+#[test]
+fn test_grep_contents() {
+    let fixture = TestFixture::new();
+    fixture.create_file("src/main.rs", "use std::io;\nfn main() {\n    todo!()\n}\n");
+    fixture.create_file("src/lib.rs", "pub fn lib() {}\n");
+    fixture.create_file("docs/notes.md", "TODO: write docs\n");
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--grep", "todo!()", "-F", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("main.rs[1 match]"))
+        .stdout(predicate::str::contains("3:     todo!()"))
+        .stdout(predicate::str::contains("lib.rs").not())
+        .stdout(predicate::str::contains("docs").not());
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--igrep", "^todo", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("notes.md[1 match]"))
+        .stdout(predicate::str::contains("src").not());
+}
+
+#[test]
+fn test_watch_reports_changes() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let fixture = TestFixture::new();
+    fixture.create_file("src/main.rs", "fn main() {}");
+    fixture.create_file("notes.txt", "");
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("denarborea"))
+        .arg(fixture.path())
+        .args(["--watch=delta", "--exclude", "*.txt", "--no-color"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if tx.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    let mut next_line = || rx.recv_timeout(Duration::from_secs(10)).ok();
+
+    assert!(next_line().is_some_and(|line| line.starts_with("Watching")));
+    fixture.create_file("notes.txt", "excluded, not reported");
+    fixture.create_file("src/lib.rs", "pub mod x;");
+    let line = next_line();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    let line = line.expect("no change reported");
+    assert!(line.ends_with(" + src/lib.rs"), "{}", line);
+}
+
+#[test]
+fn test_watch_requires_tree_output() {
+    let fixture = TestFixture::new();
+
+    Command::cargo_bin("denarborea")
+        .unwrap()
+        .arg(fixture.path())
+        .args(["--watch", "--format", "json"])
+        .assert()
+        .code(8)
+        .stderr(predicate::str::contains(
+            "--watch supports only tree output",
+        ));
+}
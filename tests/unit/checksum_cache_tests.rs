@@ -0,0 +1,121 @@
+use denarborea::{ChecksumAlgorithm, ChecksumCache, FileInfo};
+use std::fs;
+
+use crate::common::test_helpers::TestFixture;
+
+#[cfg(unix)]
+#[test]
+fn test_cache_key_follows_file_changes() {
+    let fixture = TestFixture::new();
+    let file = fixture.create_file("a.txt", "one");
+    let mut cache = ChecksumCache::new(&fixture.path().join("cache.json"));
+
+    let metadata = fs::metadata(&file).unwrap();
+    cache.insert(
+        &file,
+        &metadata,
+        ChecksumAlgorithm::Md5,
+        "digest".to_string(),
+    );
+    assert_eq!(
+        cache.get(&metadata, ChecksumAlgorithm::Md5).as_deref(),
+        Some("digest")
+    );
+    assert_eq!(cache.get(&metadata, ChecksumAlgorithm::Sha256), None);
+
+    fs::write(&file, "longer").unwrap();
+    assert_eq!(
+        cache.get(&fs::metadata(&file).unwrap(), ChecksumAlgorithm::Md5),
+        None
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cache_save_and_prune() {
+    let fixture = TestFixture::new();
+    let kept = fixture.create_file("kept.txt", "kept");
+    let changed = fixture.create_file("changed.txt", "before");
+    let deleted = fixture.create_file("deleted.txt", "deleted");
+    let cache_path = fixture.path().join("cache/checksums.json");
+
+    let mut cache = ChecksumCache::new(&cache_path);
+    for path in [&kept, &changed, &deleted] {
+        let metadata = fs::metadata(path).unwrap();
+        cache.insert(path, &metadata, ChecksumAlgorithm::Md5, "x".to_string());
+    }
+    cache.save().unwrap();
+
+    fs::write(&changed, "after, and longer").unwrap();
+    fs::remove_file(&deleted).unwrap();
+
+    let mut reloaded = ChecksumCache::new(&cache_path);
+    assert_eq!(reloaded.len(), 3);
+    assert_eq!(reloaded.prune(), 2);
+    reloaded.save().unwrap();
+    assert_eq!(ChecksumCache::new(&cache_path).len(), 1);
+}
+
+#[test]
+fn test_corrupt_cache_starts_empty() {
+    let fixture = TestFixture::new();
+    let cache_path = fixture.create_file("checksums.json", "{ not json");
+    assert!(ChecksumCache::new(&cache_path).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shared_cache_is_used_by_fileinfo() {
+    let fixture = TestFixture::new();
+    let file = fixture.create_file("hello.txt", "hello");
+    let metadata = fs::metadata(&file).unwrap();
+
+    // a cached digest is returned as is, without reading the file
+    let mut cache = ChecksumCache::new(&fixture.path().join("cache.json"));
+    cache.insert(
+        &file,
+        &metadata,
+        ChecksumAlgorithm::Sha1,
+        "cached".to_string(),
+    );
+    let cache = cache.shared();
+
+    let mut info = FileInfo::from_path(&file).unwrap();
+    let cached = info
+        .calculate_checksum_cached(ChecksumAlgorithm::Sha1, Some(&cache))
+        .cloned();
+    let mut info = FileInfo::from_path(&file).unwrap();
+    let computed = info
+        .calculate_checksum_cached(ChecksumAlgorithm::Md5, Some(&cache))
+        .cloned();
+
+    let mut cache = cache.lock().unwrap();
+    assert_eq!(cached.as_deref(), Some("cached"));
+    assert_eq!(
+        computed.as_deref(),
+        Some("5d41402abc4b2a76b9719d911017c592")
+    );
+    assert_eq!(
+        cache.get(&metadata, ChecksumAlgorithm::Md5).as_deref(),
+        Some("5d41402abc4b2a76b9719d911017c592")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cache_saves_non_utf8_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let fixture = TestFixture::new();
+    let file = fixture.path().join(OsStr::from_bytes(b"bad\xffname"));
+    fs::write(&file, "content").unwrap();
+    let cache_path = fixture.path().join("checksums.json");
+
+    let mut cache = ChecksumCache::new(&cache_path);
+    let metadata = fs::metadata(&file).unwrap();
+    cache.insert(&file, &metadata, ChecksumAlgorithm::Md5, "x".to_string());
+    cache.save().unwrap();
+
+    assert_eq!(ChecksumCache::new(&cache_path).len(), 1);
+}
@@ -1,4 +1,6 @@
-use denarborea::{Config, OutputFormat, SortBy};
+use denarborea::config::PROJECT_CONFIG_FILE;
+use denarborea::{ChecksumAlgorithm, Config, ConfigLayer, OutputFormat, SortBy, TimeField};
+use std::collections::BTreeMap;
 use std::fs;
 use tempfile::TempDir;
 
@@ -21,7 +23,7 @@ fn test_config_default() {
     assert_eq!(config.min_size, None);
     assert_eq!(config.max_size, None);
     assert!(config.exclude_patterns.is_empty());
-    assert_eq!(config.include_patterns, None);
+    assert!(config.include_patterns.is_empty());
     assert!(!config.git_ignore);
     assert!(!config.git_status);
     assert_eq!(config.limit, None);
@@ -139,3 +141,243 @@ fn test_config_save_and_load() {
     assert_eq!(loaded_config.max_depth, original_config.max_depth);
     assert!(matches!(loaded_config.sort_by, SortBy::Size));
 }
+
+#[test]
+fn test_layer_apply_overrides_only_set_fields() {
+    let layer = ConfigLayer {
+        show_size: Some(true),
+        max_depth: Some(2),
+        sort_by: Some(SortBy::Size),
+        ..ConfigLayer::default()
+    };
+
+    let config = layer.apply(Config::default()).unwrap();
+    assert!(config.show_size);
+    assert_eq!(config.max_depth, Some(2));
+    assert!(matches!(config.sort_by, SortBy::Size));
+    assert!(config.use_colors);
+}
+
+#[test]
+fn test_layer_merge_later_wins() {
+    let lower = ConfigLayer {
+        max_depth: Some(5),
+        show_hidden: Some(true),
+        ..ConfigLayer::default()
+    };
+    let upper = ConfigLayer {
+        max_depth: Some(1),
+        ..ConfigLayer::default()
+    };
+
+    let merged = lower.merge(upper).unwrap();
+    assert_eq!(merged.max_depth, Some(1));
+    assert_eq!(merged.show_hidden, Some(true));
+}
+
+#[test]
+fn test_layer_from_file_accepts_sizes_and_pattern_strings() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join(".denarborea.toml");
+    fs::write(
+        &path,
+        "min_size = \"1KB\"\nmax_size = 4096\nexclude_patterns = \"target, node_modules\"\ninclude = \"*.{rs,toml}, docs/**\"\noutput_format = \"json\"\n",
+    )
+    .unwrap();
+
+    let layer = ConfigLayer::from_file(&path).unwrap();
+    assert_eq!(layer.min_size, Some(1024));
+    assert_eq!(layer.max_size, Some(4096));
+    assert_eq!(
+        layer.exclude_patterns,
+        Some(vec!["target".to_string(), "node_modules".to_string()])
+    );
+    assert_eq!(
+        layer.include_patterns,
+        Some(vec!["*.{rs,toml}".to_string(), "docs/**".to_string()])
+    );
+    assert!(matches!(layer.output_format, Some(OutputFormat::Json)));
+}
+
+#[test]
+fn test_layer_from_file_accepts_times() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join(".denarborea.toml");
+    fs::write(
+        &path,
+        "newer = 2026-01-01T00:00:00Z\nolder = \"2026-02-01T00:00:00Z\"\ntime_field = \"ctime\"\n",
+    )
+    .unwrap();
+
+    let config = ConfigLayer::from_file(&path)
+        .unwrap()
+        .apply(Config::default())
+        .unwrap();
+    assert_eq!(
+        config.newer.unwrap().to_rfc3339(),
+        "2026-01-01T00:00:00+00:00"
+    );
+    assert_eq!(
+        config.older.unwrap().to_rfc3339(),
+        "2026-02-01T00:00:00+00:00"
+    );
+    assert_eq!(config.time_field, TimeField::Ctime);
+
+    fs::write(&path, "newer = \"soon\"\n").unwrap();
+    assert!(ConfigLayer::from_file(&path).is_err());
+}
+
+#[test]
+fn test_layer_from_file_unknown_key() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join(".denarborea.toml");
+    fs::write(&path, "show_size = true\nshow_sise = true\n").unwrap();
+
+    let err = ConfigLayer::from_file(&path).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains(".denarborea.toml:2:1"));
+    assert!(message.contains("unknown key `show_sise`, did you mean `show_size`?"));
+}
+
+#[test]
+fn test_layer_from_vars() {
+    let vars = [
+        ("DENARBOREA_SHOW_HIDDEN", "true"),
+        ("DENARBOREA_MAX_DEPTH", "3"),
+        ("DENARBOREA_SORT_BY", "time"),
+        ("DENARBOREA_EXCLUDE_PATTERNS", "a,b"),
+        ("DENARBOREA_MIN_SIZE", "2K"),
+        ("DENARBOREA_CHECKSUM_ALGORITHM", "sha256"),
+        ("UNRELATED", "value"),
+    ]
+    .map(|(name, value)| (name.to_string(), value.to_string()));
+
+    let layer = ConfigLayer::from_vars(vars).unwrap();
+    assert_eq!(layer.show_hidden, Some(true));
+    assert_eq!(layer.max_depth, Some(3));
+    assert!(matches!(layer.sort_by, Some(SortBy::Time)));
+    assert_eq!(
+        layer.exclude_patterns,
+        Some(vec!["a".to_string(), "b".to_string()])
+    );
+    assert_eq!(layer.min_size, Some(2048));
+    assert_eq!(layer.checksum_algorithm, Some(ChecksumAlgorithm::Sha256));
+}
+
+#[test]
+fn test_layer_from_vars_names_bad_variable() {
+    let vars = [("DENARBOREA_SHOW_SIZE".to_string(), "maybe".to_string())];
+    let err = ConfigLayer::from_vars(vars).unwrap_err();
+    assert!(err.to_string().contains("DENARBOREA_SHOW_SIZE"));
+}
+
+#[test]
+fn test_find_project_config_walks_up() {
+    let temp_dir = TempDir::new().unwrap();
+    let nested = temp_dir.path().join("a/b/c");
+    fs::create_dir_all(&nested).unwrap();
+    assert_eq!(Config::find_project_config(&nested), None);
+
+    let project_file = temp_dir.path().join("a").join(PROJECT_CONFIG_FILE);
+    fs::write(&project_file, "show_size = true\n").unwrap();
+
+    assert_eq!(
+        Config::find_project_config(&nested),
+        Some(project_file.canonicalize().unwrap())
+    );
+}
+
+fn write_profiles(temp_dir: &TempDir) -> ConfigLayer {
+    let path = temp_dir.path().join("config.toml");
+    fs::write(
+        &path,
+        r#"
+show_size = true
+
+[profile.audit]
+permissions = true
+checksum = true
+git_status = true
+
+[profile.deep-audit]
+inherits = "audit"
+max_depth = 10
+checksum = false
+
+[profile.docs]
+format = "markdown"
+"#,
+    )
+    .unwrap();
+    ConfigLayer::from_file(&path).unwrap()
+}
+
+#[test]
+fn test_profile_inheritance() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = write_profiles(&temp_dir);
+    assert_eq!(file.show_size, Some(true));
+    assert_eq!(file.profile.len(), 3);
+
+    let profile = ConfigLayer::resolve_profile("deep-audit", &file.profile).unwrap();
+    assert_eq!(profile.show_permissions, Some(true));
+    assert_eq!(profile.git_status, Some(true));
+    assert_eq!(profile.show_checksum, Some(false));
+    assert_eq!(profile.max_depth, Some(10));
+
+    let docs = ConfigLayer::resolve_profile("docs", &file.profile).unwrap();
+    assert!(matches!(docs.output_format, Some(OutputFormat::Markdown)));
+}
+
+#[test]
+fn test_profile_errors() {
+    let mut profiles = BTreeMap::new();
+    let err = ConfigLayer::resolve_profile("missing", &profiles).unwrap_err();
+    assert!(err.to_string().contains("Unknown profile `missing`"));
+
+    for (name, parent) in [("a", "b"), ("b", "a"), ("c", "gone")] {
+        profiles.insert(
+            name.to_string(),
+            ConfigLayer {
+                inherits: Some(parent.to_string()),
+                ..ConfigLayer::default()
+            },
+        );
+    }
+
+    let err = ConfigLayer::resolve_profile("a", &profiles).unwrap_err();
+    assert!(err.to_string().contains("a -> b -> a"));
+
+    let err = ConfigLayer::resolve_profile("c", &profiles).unwrap_err();
+    assert!(err.to_string().contains("unknown profile `gone`"));
+}
+
+#[test]
+fn test_profile_unknown_key_names_line() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("config.toml");
+    fs::write(
+        &path,
+        "show_size = true\n\n[profile.audit]\npermisions = true\n",
+    )
+    .unwrap();
+
+    let message = ConfigLayer::from_file(&path).unwrap_err().to_string();
+    assert!(message.contains("config.toml:4:1"));
+    assert!(message.contains("did you mean `permissions`?"));
+}
+
+#[test]
+fn test_merge_keeps_profiles() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = write_profiles(&temp_dir);
+
+    let merged = file
+        .merge(ConfigLayer {
+            max_depth: Some(1),
+            ..ConfigLayer::default()
+        })
+        .unwrap();
+    assert_eq!(merged.max_depth, Some(1));
+    assert_eq!(merged.profile.len(), 3);
+}
@@ -0,0 +1,169 @@
+use denarborea::{Config, DiffStatus, TreeDiff};
+use std::path::Path;
+
+use crate::common::test_helpers::TestFixture;
+
+fn status_of(diff: &TreeDiff, path: &str) -> DiffStatus {
+    diff.entries()
+        .into_iter()
+        .find(|entry| entry.path == Path::new(path))
+        .map(|entry| entry.status)
+        .unwrap()
+}
+
+fn create_pair() -> (TestFixture, TestFixture) {
+    let left = TestFixture::new();
+    let right = TestFixture::new();
+
+    left.create_file("same.txt", "same");
+    right.create_file("same.txt", "same");
+    left.create_file("gone.txt", "gone");
+    right.create_file("new.txt", "new");
+    left.create_file("sub/content.txt", "abc");
+    right.create_file("sub/content.txt", "abd");
+    left.create_file("size.txt", "1");
+    right.create_file("size.txt", "22");
+    left.create_file("kind", "file");
+    right.create_dir("kind");
+
+    // identical mtimes, so unchanged files are not reported as touched
+    let time = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+    for fixture in [&left, &right] {
+        for name in ["same.txt", "sub/content.txt"] {
+            std::fs::File::options()
+                .write(true)
+                .open(fixture.path().join(name))
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        }
+    }
+    (left, right)
+}
+
+#[test]
+fn test_diff_statuses() {
+    let (left, right) = create_pair();
+    let diff = TreeDiff::compare(&Config::default(), left.path(), right.path()).unwrap();
+
+    assert_eq!(status_of(&diff, "same.txt"), DiffStatus::Unchanged);
+    assert_eq!(status_of(&diff, "gone.txt"), DiffStatus::Removed);
+    assert_eq!(status_of(&diff, "new.txt"), DiffStatus::Added);
+    assert_eq!(status_of(&diff, "sub"), DiffStatus::Unchanged);
+    assert_eq!(
+        status_of(&diff, "sub/content.txt"),
+        DiffStatus::ContentChanged
+    );
+    assert_eq!(status_of(&diff, "size.txt"), DiffStatus::SizeChanged);
+    assert_eq!(status_of(&diff, "kind"), DiffStatus::TypeChanged);
+    assert!(diff.has_changes());
+}
+
+#[test]
+fn test_diff_mtime_only() {
+    let left = TestFixture::new();
+    let right = TestFixture::new();
+    left.create_file("a.txt", "same");
+    right.create_file("a.txt", "same");
+
+    let file = std::fs::File::options()
+        .write(true)
+        .open(right.path().join("a.txt"))
+        .unwrap();
+    file.set_modified(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap();
+
+    let diff = TreeDiff::compare(&Config::default(), left.path(), right.path()).unwrap();
+    assert_eq!(status_of(&diff, "a.txt"), DiffStatus::TimeChanged);
+}
+
+#[test]
+fn test_diff_identical_trees() {
+    let (left, _) = create_pair();
+    let diff = TreeDiff::compare(&Config::default(), left.path(), left.path()).unwrap();
+    assert!(!diff.has_changes());
+    assert!(diff.render(false).contains("0 added, 0 removed"));
+}
+
+#[test]
+fn test_diff_merged_tree_order() {
+    let (left, right) = create_pair();
+    let diff = TreeDiff::compare(&Config::default(), left.path(), right.path()).unwrap();
+
+    let names: Vec<_> = diff
+        .nodes
+        .iter()
+        .map(|node| node.entry.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["gone.txt", "kind", "new.txt", "same.txt", "size.txt", "sub"]
+    );
+    assert_eq!(diff.nodes[5].children.len(), 1);
+}
+
+#[test]
+fn test_diff_applies_filters() {
+    let (left, right) = create_pair();
+    let config = Config {
+        filter_extension: Some("txt".to_string()),
+        exclude_patterns: vec!["sub".to_string()],
+        ..Config::default()
+    };
+    let diff = TreeDiff::compare(&config, left.path(), right.path()).unwrap();
+
+    let paths: Vec<_> = diff
+        .entries()
+        .into_iter()
+        .map(|entry| &entry.path)
+        .collect();
+    assert!(!paths.iter().any(|path| path.starts_with("sub")));
+    assert!(!paths.iter().any(|path| *path == Path::new("kind")));
+}
+
+#[test]
+fn test_diff_json() {
+    let (left, right) = create_pair();
+    let json = TreeDiff::compare(&Config::default(), left.path(), right.path())
+        .unwrap()
+        .to_json();
+
+    assert_eq!(json["summary"]["added"], 1);
+    assert_eq!(json["summary"]["removed"], 1);
+    assert_eq!(json["summary"]["content"], 1);
+    assert_eq!(json["summary"]["size"], 1);
+    assert_eq!(json["summary"]["type"], 1);
+
+    let changes = json["changes"].as_array().unwrap();
+    assert!(changes
+        .iter()
+        .any(|change| change["path"] == "sub/content.txt" && change["status"] == "content"));
+    assert!(changes.iter().all(|change| change["path"] != "same.txt"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_diff_unreadable_files_are_not_unchanged() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (left, right) = create_pair();
+    let locked = [
+        left.create_file("locked.txt", "same"),
+        right.create_file("locked.txt", "same"),
+    ];
+    for path in &locked {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o000)).unwrap();
+    }
+    // permissions do not stop root
+    if std::fs::File::open(&locked[0]).is_ok() {
+        return;
+    }
+
+    let diff = TreeDiff::compare(&Config::default(), left.path(), right.path()).unwrap();
+    assert_eq!(status_of(&diff, "locked.txt"), DiffStatus::Unreadable);
+    assert_eq!(diff.errors.len(), 2);
+    assert!(diff.render(false).contains("Errors: 2"));
+    for path in &locked {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use denarborea::FileInfo;
+use denarborea::{ChecksumAlgorithm, Config, FileInfo, OutputFormat, TreeDisplay};
 use std::fs;
 use std::path::Path;
 
@@ -197,6 +197,47 @@ fn test_fileinfo_calculate_checksum() {
     assert_eq!(checksum.unwrap(), "5d41402abc4b2a76b9719d911017c592"); // MD5 of "hello"
 }
 
+#[test]
+fn test_fileinfo_calculate_checksum_with_algorithm() {
+    let fixture = TestFixture::new();
+    let file_path = fixture.create_file("test.txt", "hello");
+
+    let mut file_info = FileInfo::from_path(&file_path).unwrap();
+    let checksum = file_info.calculate_checksum_with(ChecksumAlgorithm::Sha256);
+    assert_eq!(
+        checksum.unwrap(),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+}
+
+#[test]
+fn test_checksum_prefix_and_full_digest() {
+    let fixture = TestFixture::new();
+    let file_path = fixture.create_file("test.txt", "hello");
+    let mut file_info = FileInfo::from_path(&file_path).unwrap();
+    file_info.calculate_checksum();
+
+    let tree = TreeDisplay::new(Config {
+        show_checksum: true,
+        use_colors: false,
+        checksum_length: 12,
+        ..Config::default()
+    });
+    let line = tree.format_file_info(&file_info).unwrap();
+    assert!(line.ends_with("<5d41402abc4b>"));
+
+    let csv = TreeDisplay::new(Config {
+        show_checksum: true,
+        output_format: OutputFormat::Csv,
+        ..Config::default()
+    });
+    assert!(csv.csv_header().ends_with(",Checksum"));
+    assert!(csv
+        .format_file_info(&file_info)
+        .unwrap()
+        .ends_with(",5d41402abc4b2a76b9719d911017c592"));
+}
+
 #[test]
 fn test_fileinfo_calculate_checksum_directory() {
     let fixture = TestFixture::new();
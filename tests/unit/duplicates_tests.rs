@@ -0,0 +1,102 @@
+use denarborea::{ChecksumAlgorithm, Config, Duplicates, TreeVisualizer};
+use std::path::{Path, PathBuf};
+
+use crate::common::test_helpers::TestFixture;
+
+fn find(root: &Path) -> Duplicates {
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let entries = visualizer.collect_entries(root).unwrap();
+    Duplicates::find(&entries, ChecksumAlgorithm::Md5, None)
+}
+
+fn relative_paths(root: &Path, paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.strip_prefix(root).unwrap().display().to_string())
+        .collect()
+}
+
+#[test]
+fn test_groups_identical_files() {
+    let fixture = TestFixture::new();
+    fixture.create_file("a.txt", "same content");
+    fixture.create_file("sub/b.txt", "same content");
+    fixture.create_file("c.txt", "same length!");
+    fixture.create_file("d.txt", "x");
+    fixture.create_file("e.txt", "y");
+    fixture.create_file("empty1", "");
+    fixture.create_file("empty2", "");
+
+    let duplicates = find(fixture.path());
+    assert_eq!(duplicates.sets.len(), 1);
+    let set = &duplicates.sets[0];
+    assert_eq!(set.size, 12);
+    assert_eq!(
+        relative_paths(fixture.path(), &set.paths),
+        ["a.txt", "sub/b.txt"]
+    );
+    assert_eq!(set.wasted_bytes(), 12);
+}
+
+#[test]
+fn test_large_files_differing_in_the_middle_or_at_the_ends() {
+    let fixture = TestFixture::new();
+    let content = "x".repeat(20_000);
+    let mut middle = content.clone();
+    middle.replace_range(10_000..10_001, "y");
+    let mut end = content.clone();
+    end.replace_range(19_999.., "y");
+
+    fixture.create_file("a.bin", &content);
+    fixture.create_file("b.bin", &content);
+    fixture.create_file("middle.bin", &middle);
+    fixture.create_file("end.bin", &end);
+
+    let duplicates = find(fixture.path());
+    assert_eq!(duplicates.sets.len(), 1);
+    assert_eq!(
+        relative_paths(fixture.path(), &duplicates.sets[0].paths),
+        ["a.bin", "b.bin"]
+    );
+}
+
+#[test]
+fn test_sets_are_ordered_by_wasted_bytes() {
+    let fixture = TestFixture::new();
+    for name in ["s1", "s2", "s3"] {
+        fixture.create_file(name, "small");
+    }
+    fixture.create_file("l1", &"large".repeat(100));
+    fixture.create_file("l2", &"large".repeat(100));
+
+    let duplicates = find(fixture.path());
+    let wasted: Vec<u64> = duplicates
+        .sets
+        .iter()
+        .map(|set| set.wasted_bytes())
+        .collect();
+    assert_eq!(wasted, [500, 10]);
+    assert_eq!(duplicates.wasted_bytes(), 510);
+
+    let numbers = duplicates.set_numbers();
+    assert_eq!(numbers[fixture.path().join("l2").as_path()], 1);
+    assert_eq!(numbers[fixture.path().join("s3").as_path()], 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hardlinks_are_not_duplicates() {
+    let fixture = TestFixture::new();
+    let original = fixture.create_file("a.txt", "linked");
+    std::fs::hard_link(&original, fixture.path().join("b.txt")).unwrap();
+
+    assert!(find(fixture.path()).sets.is_empty());
+
+    fixture.create_file("c.txt", "linked");
+    let duplicates = find(fixture.path());
+    assert_eq!(duplicates.sets.len(), 1);
+    assert_eq!(
+        relative_paths(fixture.path(), &duplicates.sets[0].paths),
+        ["a.txt", "c.txt"]
+    );
+}
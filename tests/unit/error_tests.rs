@@ -0,0 +1,73 @@
+use denarborea::viewer::structured::view_json_file;
+use denarborea::{
+    utils::parse_size, Config, Error, FileViewer, Location, TreeVisualizer, ViewerFormat,
+};
+
+use crate::common::test_helpers::TestFixture;
+
+#[test]
+fn test_missing_root_is_not_found() {
+    let fixture = TestFixture::new();
+    let missing = fixture.path().join("missing");
+
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let err = visualizer.visualize(&missing).unwrap_err();
+
+    assert!(err.is_not_found());
+    assert_eq!(err.path(), Some(missing.as_path()));
+    assert_eq!(err.exit_code(), 3);
+}
+
+#[test]
+fn test_json_parse_error_has_location() {
+    let fixture = TestFixture::new();
+    let path = fixture.create_file("bad.json", "{\n  \"a\": 1,\n  \"b\": }\n");
+
+    match view_json_file(&path).unwrap_err() {
+        Error::Parse {
+            format,
+            path: error_path,
+            location,
+            ..
+        } => {
+            assert_eq!(format, "JSON");
+            assert_eq!(error_path, Some(path));
+            assert_eq!(location.map(|l| l.line), Some(3));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_viewer_attaches_path() {
+    let fixture = TestFixture::new();
+    let path = fixture.create_file("bad.json", "{\"incomplete\": true");
+
+    let err = FileViewer::new(ViewerFormat::Json)
+        .view_file(&path)
+        .unwrap_err();
+
+    assert_eq!(err.path(), Some(path.as_path()));
+    assert_eq!(err.exit_code(), 6);
+    assert!(err.to_string().contains("invalid JSON at line 1"));
+}
+
+#[test]
+fn test_invalid_size_is_config_error() {
+    let err = parse_size(&Some("1XB".to_string())).unwrap_err();
+    assert!(matches!(err, Error::Config(_)));
+    assert_eq!(err.exit_code(), 8);
+}
+
+#[test]
+fn test_location_from_offset() {
+    let text = "first\nsecond\nthird";
+    assert_eq!(
+        Location::from_offset(text, 9),
+        Location {
+            line: 2,
+            column: Some(4)
+        }
+    );
+    assert_eq!(Location::from_offset(text, 0).line, 1);
+}
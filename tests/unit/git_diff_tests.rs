@@ -0,0 +1,129 @@
+#![cfg(feature = "git")]
+
+use denarborea::git::GitStatus;
+use denarborea::{Config, GitDiff};
+use git2::{Repository, Signature};
+use std::path::{Path, PathBuf};
+
+use crate::common::test_helpers::TestFixture;
+
+// Commits the whole worktree, removed files included
+fn commit_all(repo_dir: &Path, message: &str) {
+    let repo = Repository::open(repo_dir).unwrap();
+    let mut index = repo.index().unwrap();
+    index.clear().unwrap();
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("test", "test@example.com").unwrap();
+    let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parent.iter().collect::<Vec<_>>(),
+    )
+    .unwrap();
+}
+
+// `base` and HEAD: a file added, one modified, one deleted and one renamed
+fn repository(fixture: &TestFixture) -> &Path {
+    let root = fixture.path();
+    let repo = Repository::init(root).unwrap();
+    fixture.create_file("src/lib.rs", "one\ntwo\nthree\n");
+    fixture.create_file("src/helpers.rs", "a\nb\nc\nd\ne\nf\ng\nh\n");
+    fixture.create_file("docs/old.md", "notes\n");
+    commit_all(root, "one");
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    repo.branch("base", &head, false).unwrap();
+
+    fixture.create_file("src/lib.rs", "one\n2\nthree\nfour\n");
+    std::fs::rename(root.join("src/helpers.rs"), root.join("src/util.rs")).unwrap();
+    std::fs::remove_dir_all(root.join("docs")).unwrap();
+    fixture.create_file("NOTICE", "new\n");
+    commit_all(root, "two");
+    root
+}
+
+#[test]
+fn test_changes_between_two_revisions() {
+    let fixture = TestFixture::new();
+    let root = repository(&fixture);
+
+    let diff = GitDiff::compare(&Config::default(), root, "base..HEAD").unwrap();
+    assert_eq!((diff.from.as_str(), diff.to.as_str()), ("base", "HEAD"));
+
+    let changes: Vec<_> = diff
+        .changes()
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry.status.unwrap()))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (PathBuf::from("NOTICE"), GitStatus::Added),
+            (PathBuf::from("docs/old.md"), GitStatus::Deleted),
+            (PathBuf::from("src/lib.rs"), GitStatus::Modified),
+            (PathBuf::from("src/util.rs"), GitStatus::Renamed),
+        ]
+    );
+
+    let entries = diff.entries();
+    let lib = entries.iter().find(|e| e.name == "lib.rs").unwrap();
+    assert_eq!((lib.lines_added, lib.lines_removed), (2, 1));
+    let util = entries.iter().find(|e| e.name == "util.rs").unwrap();
+    assert_eq!(util.old_path, Some(PathBuf::from("src/helpers.rs")));
+    // directories carry the totals of their files
+    let src = entries.iter().find(|e| e.name == "src").unwrap();
+    assert!(src.is_dir && src.status.is_none());
+    assert_eq!((src.lines_added, src.lines_removed), (2, 1));
+    assert_eq!(diff.totals(), (3, 2));
+
+    let rendered = diff.render(false);
+    assert!(rendered.contains("   '-- R util.rs[+0 -0]<- src/helpers.rs"));
+    assert!(rendered.contains("1 added, 1 modified, 1 deleted, 1 renamed"));
+}
+
+#[test]
+fn test_worktree_changes_below_a_subdirectory() {
+    let fixture = TestFixture::new();
+    let root = repository(&fixture);
+    fixture.create_file("src/lib.rs", "changed\n");
+    fixture.create_file("NOTICE", "changed too\n");
+
+    let diff = GitDiff::compare(&Config::default(), &root.join("src"), "HEAD").unwrap();
+    assert_eq!(diff.to, "worktree");
+    let paths: Vec<_> = diff.changes().into_iter().map(|e| e.path.clone()).collect();
+    assert_eq!(paths, vec![PathBuf::from("lib.rs")]);
+
+    let config = Config {
+        filter_extension: Some("md".to_string()),
+        ..Config::default()
+    };
+    let diff = GitDiff::compare(&config, root, "base..HEAD").unwrap();
+    let paths: Vec<_> = diff.changes().into_iter().map(|e| e.path.clone()).collect();
+    assert_eq!(paths, vec![PathBuf::from("docs/old.md")]);
+
+    assert!(GitDiff::compare(&Config::default(), root, "HEAD..HEAD")
+        .unwrap()
+        .nodes
+        .is_empty());
+    assert!(GitDiff::compare(&Config::default(), root, "nope..HEAD").is_err());
+}
+
+#[test]
+fn test_hidden_changes_are_part_of_the_diff() {
+    let fixture = TestFixture::new();
+    let root = repository(&fixture);
+    fixture.create_file(".github/ci.yml", "on: push\n");
+    commit_all(root, "three");
+
+    let diff = GitDiff::compare(&Config::default(), root, "HEAD~1..HEAD").unwrap();
+    let paths: Vec<_> = diff.changes().into_iter().map(|e| e.path.clone()).collect();
+    assert_eq!(paths, vec![PathBuf::from(".github/ci.yml")]);
+    assert!(diff.render(false).contains("1 added"));
+}
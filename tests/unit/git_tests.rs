@@ -0,0 +1,138 @@
+#![cfg(feature = "git")]
+
+use denarborea::git::{GitInfo, GitState, GitStatus};
+use git2::Repository;
+use std::path::Path;
+
+use crate::common::test_helpers::TestFixture;
+
+fn state(index: GitStatus, worktree: GitStatus) -> Option<GitState> {
+    Some(GitState { index, worktree })
+}
+
+fn stage(repo_dir: &Path, file: &str) {
+    let repo = Repository::open(repo_dir).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(file)).unwrap();
+    index.write().unwrap();
+}
+
+#[test]
+fn test_status_comes_from_the_repository_of_each_path() {
+    let fixture = TestFixture::new();
+    let outer = fixture.create_dir("outer");
+    Repository::init(&outer).unwrap();
+    fixture.create_file("outer/a.txt", "a");
+    fixture.create_file("outer/new/b.txt", "b");
+    stage(&outer, "a.txt");
+
+    let inner = fixture.create_dir("outer/inner");
+    Repository::init(&inner).unwrap();
+    fixture.create_file("outer/inner/z.txt", "z");
+    stage(&inner, "z.txt");
+
+    let git = GitInfo::new();
+    assert_eq!(
+        git.status(&outer.join("a.txt")),
+        state(GitStatus::Added, GitStatus::Clean)
+    );
+    // contents of an untracked directory
+    assert_eq!(
+        git.status(&outer.join("new/b.txt")),
+        Some(GitState::both(GitStatus::Untracked))
+    );
+    // the outer repository only sees an untracked directory
+    assert_eq!(
+        git.status(&inner),
+        Some(GitState::both(GitStatus::Untracked))
+    );
+    assert_eq!(
+        git.status(&inner.join("z.txt")),
+        state(GitStatus::Added, GitStatus::Clean)
+    );
+    // reached through a path with `..` from a subdirectory
+    let through_parent = outer.join("new/../inner/z.txt");
+    assert_eq!(
+        git.status(&through_parent),
+        state(GitStatus::Added, GitStatus::Clean)
+    );
+}
+
+#[test]
+fn test_directories_summarize_the_changes_below_them() {
+    let fixture = TestFixture::new();
+    let repo = fixture.create_dir("repo");
+    Repository::init(&repo).unwrap();
+    fixture.create_file("repo/src/a.rs", "a");
+    fixture.create_file("repo/src/deep/b.rs", "b");
+    fixture.create_file("repo/src/deep/new/c.rs", "c");
+    fixture.create_file("repo/docs/readme.md", "r");
+    stage(&repo, "src/a.rs");
+    stage(&repo, "src/deep/b.rs");
+
+    let git = GitInfo::new();
+    let src = git.summary(&repo.join("src")).unwrap();
+    assert_eq!(src.count(GitStatus::Added), 2);
+    // an untracked directory counts once
+    assert_eq!(src.count(GitStatus::Untracked), 1);
+    assert_eq!(src.total(), 3);
+    assert_eq!(src.mark().symbol(), "A?");
+    assert_eq!(git.summary(&repo).unwrap().total(), 4);
+    assert_eq!(git.summary(&repo.join("src/deep/new")), None);
+}
+
+#[test]
+fn test_paths_outside_a_repository_have_no_status() {
+    let fixture = TestFixture::new();
+    let file = fixture.create_file("plain/a.txt", "a");
+
+    let git = GitInfo::new();
+    if Repository::discover(fixture.path()).is_err() {
+        assert_eq!(git.status(&file), None);
+        assert_eq!(git.get_status(&file), GitState::CLEAN);
+    }
+}
+
+#[test]
+fn test_staged_and_unstaged_changes_are_kept_apart() {
+    let fixture = TestFixture::new();
+    let repo = fixture.create_dir("repo");
+    Repository::init(&repo).unwrap();
+    fixture.create_file("repo/both.txt", "staged");
+    stage(&repo, "both.txt");
+    fixture.create_file("repo/both.txt", "changed again");
+
+    let git = GitInfo::new();
+    let both = git.status(&repo.join("both.txt")).unwrap();
+    assert_eq!(
+        both,
+        GitState {
+            index: GitStatus::Added,
+            worktree: GitStatus::Modified,
+        }
+    );
+    assert_eq!(both.symbol(), "AM");
+    assert!(both.has(GitStatus::Added) && both.has(GitStatus::Modified));
+    assert!(!both.has(GitStatus::Clean));
+    assert_eq!(git.summary(&repo).unwrap().mark().symbol(), "AM");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_typechange() {
+    let fixture = TestFixture::new();
+    let repo = fixture.create_dir("repo");
+    Repository::init(&repo).unwrap();
+    fixture.create_file("repo/target.txt", "t");
+    fixture.create_file("repo/link", "was a file");
+    stage(&repo, "target.txt");
+    stage(&repo, "link");
+    std::fs::remove_file(repo.join("link")).unwrap();
+    std::os::unix::fs::symlink("target.txt", repo.join("link")).unwrap();
+
+    let git = GitInfo::new();
+    assert_eq!(
+        git.status(&repo.join("link")),
+        state(GitStatus::Added, GitStatus::TypeChange)
+    );
+}
@@ -0,0 +1,160 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use denarborea::interactive::{BrowserAction, BrowserFocus};
+use denarborea::{Config, TreeVisualizer};
+
+use crate::common::test_helpers::TestFixture;
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn visible_names(browser: &denarborea::TreeBrowser) -> Vec<String> {
+    browser
+        .rows()
+        .iter()
+        .map(|row| row.path.file_name().unwrap().to_string_lossy().to_string())
+        .collect()
+}
+
+fn create_fixture() -> TestFixture {
+    let fixture = TestFixture::new();
+    fixture.create_file("a_dir/inner.txt", "inner");
+    fixture.create_file("a_dir/nested/deep.txt", "deep");
+    fixture.create_file("b.txt", "hello");
+    fixture
+}
+
+#[test]
+fn test_browser_starts_collapsed() {
+    let fixture = create_fixture();
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let browser = visualizer.browser(fixture.path()).unwrap();
+
+    assert_eq!(visible_names(&browser), vec!["a_dir", "b.txt"]);
+    assert_eq!(
+        browser.selected_path(),
+        Some(fixture.path().join("a_dir").as_path())
+    );
+}
+
+#[test]
+fn test_browser_max_depth_sets_initial_expansion() {
+    let fixture = create_fixture();
+    let config = Config {
+        max_depth: Some(2),
+        ..Config::default()
+    };
+    let mut visualizer = TreeVisualizer::new(config);
+    let browser = visualizer.browser(fixture.path()).unwrap();
+
+    assert_eq!(
+        visible_names(&browser),
+        vec!["a_dir", "inner.txt", "nested", "b.txt"]
+    );
+}
+
+#[test]
+fn test_browser_prefixes_match_the_tree_output() {
+    let fixture = create_fixture();
+    let config = Config {
+        max_depth: Some(3),
+        ..Config::default()
+    };
+    let mut visualizer = TreeVisualizer::new(config);
+    let browser = visualizer.browser(fixture.path()).unwrap();
+
+    let prefixes: Vec<_> = browser
+        .rows()
+        .iter()
+        .map(|row| row.prefix.as_str())
+        .collect();
+    assert_eq!(
+        prefixes,
+        vec!["|-- ", "|   |-- ", "|   '-- ", "|      '-- ", "'-- "]
+    );
+}
+
+#[test]
+fn test_browser_expand_and_collapse() {
+    let fixture = create_fixture();
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let mut browser = visualizer.browser(fixture.path()).unwrap();
+
+    browser.handle_key(key(KeyCode::Right));
+    assert_eq!(
+        visible_names(&browser),
+        vec!["a_dir", "inner.txt", "nested", "b.txt"]
+    );
+
+    // moving into a child and pressing left jumps back to the parent
+    browser.handle_key(key(KeyCode::Char('j')));
+    browser.handle_key(key(KeyCode::Char('h')));
+    assert_eq!(
+        browser.selected_path(),
+        Some(fixture.path().join("a_dir").as_path())
+    );
+
+    browser.handle_key(key(KeyCode::Left));
+    assert_eq!(visible_names(&browser), vec!["a_dir", "b.txt"]);
+}
+
+#[test]
+fn test_browser_depth_keys() {
+    let fixture = create_fixture();
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let mut browser = visualizer.browser(fixture.path()).unwrap();
+
+    browser.handle_key(key(KeyCode::Char('e')));
+    assert_eq!(browser.rows().len(), 5);
+
+    browser.handle_key(key(KeyCode::Char('c')));
+    assert_eq!(browser.rows().len(), 2);
+}
+
+#[test]
+fn test_browser_enter_opens_viewer() {
+    let fixture = create_fixture();
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let mut browser = visualizer.browser(fixture.path()).unwrap();
+
+    browser.handle_key(key(KeyCode::Char('G')));
+    browser.handle_key(key(KeyCode::Enter));
+
+    assert_eq!(
+        browser.viewer_path(),
+        Some(fixture.path().join("b.txt").as_path())
+    );
+    assert_eq!(browser.focus(), BrowserFocus::Viewer);
+
+    // q closes the viewer first, then quits
+    assert_eq!(
+        browser.handle_key(key(KeyCode::Char('q'))),
+        BrowserAction::Continue
+    );
+    assert!(browser.viewer_path().is_none());
+    assert_eq!(
+        browser.handle_key(key(KeyCode::Char('q'))),
+        BrowserAction::Quit
+    );
+}
+
+#[test]
+fn test_browser_selection_stays_in_bounds() {
+    let fixture = create_fixture();
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let mut browser = visualizer.browser(fixture.path()).unwrap();
+
+    browser.handle_key(key(KeyCode::Up));
+    assert_eq!(
+        browser.selected_path(),
+        Some(fixture.path().join("a_dir").as_path())
+    );
+
+    for _ in 0..10 {
+        browser.handle_key(key(KeyCode::Down));
+    }
+    assert_eq!(
+        browser.selected_path(),
+        Some(fixture.path().join("b.txt").as_path())
+    );
+}
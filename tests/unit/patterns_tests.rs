@@ -0,0 +1,94 @@
+use denarborea::{Config, PathFilter};
+use std::path::Path;
+
+fn filter(exclude: &[&str], include: &[&str]) -> PathFilter {
+    let config = Config {
+        exclude_patterns: exclude.iter().map(|p| p.to_string()).collect(),
+        include_patterns: include.iter().map(|p| p.to_string()).collect(),
+        ..Config::default()
+    };
+    PathFilter::new(&config, Path::new("root")).unwrap()
+}
+
+fn matches(filter: &PathFilter, path: &str) -> bool {
+    filter.matches(&Path::new("root").join(path), false)
+}
+
+#[test]
+fn test_globs_are_not_regexes() {
+    let filter = filter(&[], &["*.rs"]);
+    assert!(matches(&filter, "main.rs"));
+    assert!(matches(&filter, "src/deep/lib.rs"));
+    assert!(!matches(&filter, "main.rsx"));
+    assert!(!matches(&filter, "mainrs"));
+}
+
+#[test]
+fn test_glob_syntax() {
+    let filter = filter(&[], &["src/**/test_*.rs", "*.{md,toml}", "file?.[ch]"]);
+    assert!(matches(&filter, "src/test_a.rs"));
+    assert!(matches(&filter, "src/a/b/test_b.rs"));
+    assert!(!matches(&filter, "other/test_c.rs"));
+    assert!(matches(&filter, "README.md"));
+    assert!(matches(&filter, "Cargo.toml"));
+    assert!(matches(&filter, "file1.c"));
+    assert!(matches(&filter, "fileX.h"));
+    assert!(!matches(&filter, "file10.c"));
+    assert!(!matches(&filter, "file1.o"));
+}
+
+#[test]
+fn test_patterns_are_relative_to_the_root() {
+    // "root" is the walk root, not part of the relative path
+    let filter = filter(&["/root"], &[]);
+    assert!(matches(&filter, "a.txt"));
+    assert!(!matches(&filter, "root"));
+    assert!(matches(&filter, "sub/root"));
+}
+
+#[test]
+fn test_exclude_directories_and_negation() {
+    let filter = filter(&["target", "*.log", "!keep.log"], &[]);
+    assert!(!matches(&filter, "target/debug/app"));
+    assert!(!matches(&filter, "crates/x/target/out.txt"));
+    assert!(!matches(&filter, "error.log"));
+    assert!(matches(&filter, "keep.log"));
+    assert!(matches(&filter, "src/main.rs"));
+    assert!(!filter.matches(Path::new("root/target"), true));
+}
+
+#[test]
+fn test_include_directory_with_negation() {
+    let filter = filter(&[], &["src", "!src/generated/", "!*.bak"]);
+    assert!(matches(&filter, "src/main.rs"));
+    assert!(!matches(&filter, "src/generated/schema.rs"));
+    assert!(!matches(&filter, "src/main.rs.bak"));
+    assert!(!matches(&filter, "docs/index.md"));
+    // directories stay so that files below them can be shown
+    assert!(filter.matches(Path::new("root/docs"), true));
+}
+
+#[test]
+fn test_regex_matches_relative_path() {
+    let config = Config {
+        regex: Some(r"^src/.*_test\.rs$".to_string()),
+        ..Config::default()
+    };
+    let filter = PathFilter::new(&config, Path::new("root")).unwrap();
+    assert!(matches(&filter, "src/a/io_test.rs"));
+    assert!(!matches(&filter, "tests/io_test.rs"));
+    assert!(!matches(&filter, "src/IO_TEST.rs"));
+
+    let config = Config {
+        regex_ignore_case: true,
+        ..config
+    };
+    let filter = PathFilter::new(&config, Path::new("root")).unwrap();
+    assert!(matches(&filter, "src/IO_TEST.RS"));
+
+    let config = Config {
+        regex: Some("(".to_string()),
+        ..Config::default()
+    };
+    assert!(PathFilter::new(&config, Path::new("root")).is_err());
+}
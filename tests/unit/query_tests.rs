@@ -0,0 +1,116 @@
+use denarborea::git::{GitState, GitStatus};
+use denarborea::{Error, FileInfo, Query};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+fn file(path: &str, size: u64, age_days: u64) -> FileInfo {
+    let mut info = FileInfo::from_error(Path::new(path), false, String::new());
+    info.error = None;
+    info.size = size;
+    info.modified_time = Some(SystemTime::now() - Duration::from_secs(age_days * 86_400));
+    info
+}
+
+fn matches(query: &str, info: &FileInfo) -> bool {
+    Query::parse(query).unwrap().matches(info, &info.path, &|| {
+        Some(GitState {
+            index: GitStatus::Added,
+            worktree: GitStatus::Modified,
+        })
+    })
+}
+
+#[test]
+fn test_request_example() {
+    let query = "ext in (rs, py) and size > 10KB and mtime < 7d and not path ~ \"target/**\"";
+    assert!(matches(query, &file("src/main.rs", 20_000, 1)));
+    assert!(matches(query, &file("tool.PY", 20_000, 1)));
+    assert!(!matches(query, &file("src/main.rs", 5_000, 1)));
+    assert!(!matches(query, &file("src/main.rs", 20_000, 30)));
+    assert!(!matches(query, &file("target/debug/build.rs", 20_000, 1)));
+    assert!(!matches(query, &file("notes.txt", 20_000, 1)));
+}
+
+#[test]
+fn test_precedence_and_grouping() {
+    let info = file("a.md", 100, 0);
+    // `and` binds tighter than `or`
+    assert!(matches("ext = md or ext = rs and size > 1MB", &info));
+    assert!(!matches("(ext = md or ext = rs) and size > 1MB", &info));
+    assert!(matches("not (ext = rs) && !size > 1KB", &info));
+    assert!(matches("EXT = md AND name != b.md", &info));
+}
+
+#[test]
+fn test_text_operators() {
+    let info = file("src/lib/test_io.rs", 0, 0);
+    assert!(matches("name ~ 'test_*'", &info));
+    assert!(matches("path ~ src", &info));
+    assert!(matches("path !~ 'target/**'", &info));
+    assert!(matches(r#"name =~ "^test_\w+\.rs$""#, &info));
+    assert!(matches("ext not in (py, '.md')", &info));
+    assert!(matches("ext = .rs and type = file", &info));
+}
+
+#[test]
+fn test_git_status() {
+    let info = file("a.rs", 0, 0);
+    assert!(matches("git in (modified, untracked)", &info));
+    assert!(!matches("git = clean", &info));
+    // either column
+    assert!(matches("git = added", &info));
+    assert!(!matches("git in (deleted, conflicted)", &info));
+    assert!(Query::parse("git = clean").unwrap().uses_git());
+    assert!(!Query::parse("size > 1").unwrap().uses_git());
+
+    // without a repository no status matches
+    let query = Query::parse("git != clean").unwrap();
+    assert!(!query.matches(&info, &info.path, &|| None));
+}
+
+#[test]
+fn test_error_points_at_token() {
+    let Err(Error::Parse {
+        format,
+        location,
+        message,
+        ..
+    }) = Query::parse("size > 1KB and colour = red")
+    else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(format, "filter expression");
+    assert_eq!(location.unwrap().column, Some(16));
+    assert!(message.starts_with("unknown field `colour`"));
+    assert!(message.ends_with("\n  size > 1KB and colour = red\n                 ^"));
+}
+
+#[test]
+fn test_type_dir_is_rejected() {
+    let Err(Error::Parse {
+        location, message, ..
+    }) = Query::parse("type in (file, dir)")
+    else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(location.unwrap().column, Some(16));
+    assert!(message.starts_with("the expression applies to files"));
+}
+
+#[test]
+fn test_invalid_expressions() {
+    for query in [
+        "",
+        "size >",
+        "size > big",
+        "mtime = 7d",
+        "ext in rs",
+        "ext in (rs",
+        "(ext = rs",
+        "name = 'open",
+        "type = socket",
+        "ext = rs size > 1",
+    ] {
+        assert!(Query::parse(query).is_err(), "{:?} should not parse", query);
+    }
+}
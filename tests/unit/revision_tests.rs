@@ -0,0 +1,139 @@
+#![cfg(feature = "git")]
+
+use denarborea::utils::calculate_checksum;
+use denarborea::{ChecksumAlgorithm, Config, Revision, TreeVisualizer};
+use git2::{Repository, Signature};
+use std::path::Path;
+
+use crate::common::test_helpers::TestFixture;
+
+// Commits the whole worktree of `repo_dir`, removed files included
+fn commit_all(repo_dir: &Path, message: &str) {
+    let repo = Repository::open(repo_dir).unwrap();
+    let mut index = repo.index().unwrap();
+    index.clear().unwrap();
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("test", "test@example.com").unwrap();
+    let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parent.iter().collect::<Vec<_>>(),
+    )
+    .unwrap();
+}
+
+fn repository(fixture: &TestFixture) -> &Path {
+    Repository::init(fixture.path()).unwrap();
+    fixture.create_file("a.txt", "first");
+    fixture.create_file("old/b.rs", "fn main() {}\n");
+    commit_all(fixture.path(), "one");
+
+    std::fs::remove_dir_all(fixture.path().join("old")).unwrap();
+    fixture.create_file("a.txt", "second version");
+    fixture.create_file("new.rs", "pub fn f() {}\n");
+    commit_all(fixture.path(), "two");
+    fixture.path()
+}
+
+#[test]
+fn test_tree_of_an_older_revision() {
+    let fixture = TestFixture::new();
+    let root = repository(&fixture);
+    fixture.create_file("untracked.txt", "not committed");
+
+    let mut visualizer = TreeVisualizer::new(Config {
+        revision: Some("HEAD~1".to_string()),
+        show_checksum: true,
+        ..Config::default()
+    });
+    let entries = visualizer.collect_entries(root).unwrap();
+    let mut paths: Vec<_> = entries.iter().map(|e| e.path.clone()).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![root.join("a.txt"), root.join("old"), root.join("old/b.rs")]
+    );
+
+    // sizes and checksums are those of the committed contents
+    let a = entries.iter().find(|e| e.name == "a.txt").unwrap();
+    assert_eq!(a.size, 5);
+    fixture.create_file("first.txt", "first");
+    assert_eq!(
+        a.checksum,
+        Some(calculate_checksum(&root.join("first.txt"), ChecksumAlgorithm::Md5).unwrap())
+    );
+
+    let mut visualizer = TreeVisualizer::new(Config {
+        revision: Some("HEAD~1".to_string()),
+        grep: Some("fn main".to_string()),
+        ..Config::default()
+    });
+    let entries = visualizer.collect_entries(root).unwrap();
+    let mut names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["b.rs", "old"]);
+    let b = entries.iter().find(|e| e.name == "b.rs").unwrap();
+    assert_eq!(b.content_matches.as_ref().unwrap().count, 1);
+}
+
+#[test]
+fn test_read_files_that_are_gone_from_the_worktree() {
+    let fixture = TestFixture::new();
+    let root = repository(&fixture);
+
+    let revision = Revision::open(&root.join("old"), "HEAD~1").unwrap();
+    assert_eq!(
+        revision.read(&root.join("old/b.rs")).unwrap(),
+        b"fn main() {}\n"
+    );
+    assert_eq!(
+        Revision::open(root, "HEAD")
+            .unwrap()
+            .read(&root.join("a.txt"))
+            .unwrap(),
+        b"second version"
+    );
+
+    let head = Revision::open(root, "HEAD").unwrap();
+    assert!(head.read(&root.join("old/b.rs")).is_err());
+    assert!(head.listing(&root.join("a.txt"), false).is_err());
+    assert!(Revision::open(root, "no-such-branch").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_names_in_a_revision() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let fixture = TestFixture::new();
+    Repository::init(fixture.path()).unwrap();
+    let name = OsStr::from_bytes(b"bad\xffname");
+    std::fs::create_dir(fixture.path().join(name)).unwrap();
+    std::fs::write(fixture.path().join(name).join(name), "needle").unwrap();
+    commit_all(fixture.path(), "one");
+    std::fs::remove_dir_all(fixture.path().join(name)).unwrap();
+
+    let mut visualizer = TreeVisualizer::new(Config {
+        revision: Some("HEAD".to_string()),
+        show_checksum: true,
+        disk_usage: true,
+        grep: Some("needle".to_string()),
+        ..Config::default()
+    });
+    let entries = visualizer.collect_entries(fixture.path()).unwrap();
+    let file = entries.iter().find(|e| !e.is_dir).unwrap();
+    assert_eq!(file.path, fixture.path().join(name).join(name));
+    assert_eq!(file.content_matches.as_ref().unwrap().count, 1);
+    assert!(file.checksum.is_some());
+    let dir = entries.iter().find(|e| e.is_dir).unwrap();
+    assert_eq!(dir.size, 6);
+}
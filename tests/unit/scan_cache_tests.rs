@@ -0,0 +1,92 @@
+use denarborea::ScanCache;
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::common::test_helpers::TestFixture;
+
+// Freshly created directories are not stored until they settle
+fn settle(dir: &Path) {
+    let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    File::open(dir).unwrap().set_modified(hour_ago).unwrap();
+}
+
+fn names(cache: &mut ScanCache, dir: &Path) -> Vec<String> {
+    cache
+        .listing(dir, false)
+        .unwrap()
+        .into_iter()
+        .map(|info| info.name)
+        .collect()
+}
+
+#[test]
+fn test_unchanged_directories_come_from_the_index() {
+    let fixture = TestFixture::new();
+    let root = fixture.create_dir("tree");
+    fixture.create_file("tree/b.txt", "b");
+    fixture.create_file("tree/sub/a.txt", "a");
+    settle(&root.join("sub"));
+    settle(&root);
+    let index = fixture.path().join("index.json");
+
+    let mut cache = ScanCache::open(&index, &root);
+    assert_eq!(names(&mut cache, &root), ["b.txt", "sub"]);
+    assert_eq!(names(&mut cache, &root.join("sub")), ["a.txt"]);
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    cache.save().unwrap();
+
+    let mut cache = ScanCache::open(&index, &root);
+    assert_eq!(names(&mut cache, &root), ["b.txt", "sub"]);
+    assert_eq!((cache.hits(), cache.misses()), (1, 0));
+
+    fixture.create_file("tree/sub/c.txt", "c");
+    assert_eq!(names(&mut cache, &root.join("sub")), ["a.txt", "c.txt"]);
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+}
+
+#[test]
+fn test_index_of_another_root_is_ignored() {
+    let fixture = TestFixture::new();
+    let first = fixture.create_dir("first");
+    let second = fixture.create_dir("second");
+    fixture.create_file("first/a.txt", "a");
+    settle(&first);
+    let index = fixture.path().join("index.json");
+
+    let mut cache = ScanCache::open(&index, &first);
+    names(&mut cache, &first);
+    cache.save().unwrap();
+
+    let mut cache = ScanCache::open(&index, &second);
+    assert!(names(&mut cache, &second).is_empty());
+    assert_eq!(cache.hits(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_names_survive_the_index() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let fixture = TestFixture::new();
+    let root = fixture.create_dir("tree");
+    let odd = root.join(OsStr::from_bytes(b"bad\xffdir"));
+    std::fs::create_dir(&odd).unwrap();
+    std::fs::write(odd.join(OsStr::from_bytes(b"bad\xfename")), "x").unwrap();
+    settle(&odd);
+    settle(&root);
+    let index = fixture.path().join("index.json");
+
+    let mut cache = ScanCache::open(&index, &root);
+    cache.listing(&root, false).unwrap();
+    cache.listing(&odd, false).unwrap();
+    cache.save().unwrap();
+
+    let mut cache = ScanCache::open(&index, &root);
+    let listing = cache.listing(&root, false).unwrap();
+    assert_eq!(listing[0].path, odd);
+    let listing = cache.listing(&odd, false).unwrap();
+    assert!(listing[0].path.exists());
+    assert_eq!((cache.hits(), cache.misses()), (2, 0));
+}
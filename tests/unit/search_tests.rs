@@ -0,0 +1,87 @@
+use denarborea::search::PREVIEW_LINES;
+use denarborea::{Config, ContentSearch, TreeVisualizer};
+use std::path::Path;
+
+use crate::common::test_helpers::TestFixture;
+
+fn search(pattern: &str, ignore_case: bool, fixed: bool) -> ContentSearch {
+    let config = Config {
+        grep: Some(pattern.to_string()),
+        grep_ignore_case: ignore_case,
+        grep_fixed_strings: fixed,
+        ..Config::default()
+    };
+    ContentSearch::new(&config).unwrap().unwrap()
+}
+
+#[test]
+fn test_counts_lines_and_keeps_the_first() {
+    let fixture = TestFixture::new();
+    let content: String = (1..=10).map(|i| format!("line {}\r\n", i)).collect();
+    fixture.create_file("a.txt", &content);
+
+    let matches = search(r"line \d$", false, false).search(&fixture.path().join("a.txt"));
+    assert_eq!(matches.count, 9);
+    assert_eq!(matches.lines.len(), PREVIEW_LINES);
+    assert_eq!(matches.lines[0].number, 1);
+    assert_eq!(matches.lines[0].text, "line 1");
+}
+
+#[test]
+fn test_fixed_strings_and_case() {
+    let fixture = TestFixture::new();
+    fixture.create_file("a.txt", "call foo(1)\nFOO(2)\n");
+    let path = fixture.path().join("a.txt");
+
+    assert_eq!(search("foo(", false, true).search(&path).count, 1);
+    assert_eq!(search("foo(", true, true).search(&path).count, 2);
+    assert_eq!(search("fo+", false, false).search(&path).count, 1);
+
+    let config = Config {
+        grep: Some("foo(".to_string()),
+        ..Config::default()
+    };
+    assert!(ContentSearch::new(&config).is_err());
+    assert!(ContentSearch::new(&Config::default()).unwrap().is_none());
+}
+
+#[test]
+fn test_binary_files_never_match() {
+    let fixture = TestFixture::new();
+    fixture.create_file("a.bin", "needle\0needle");
+
+    let matches = search("needle", false, false).search(&fixture.path().join("a.bin"));
+    assert_eq!(matches.count, 0);
+    assert!(matches.lines.is_empty());
+
+    // contents that are not on disk are checked the same way
+    let search = search("needle", false, false);
+    assert_eq!(search.search_bytes(b"needle\0needle").count, 0);
+    assert_eq!(search.search_bytes(b"hay\nneedle\n").lines[0].number, 2);
+}
+
+#[test]
+fn test_grep_prunes_tree() {
+    let fixture = TestFixture::new();
+    fixture.create_file("src/main.rs", "fn main() {}\n");
+    fixture.create_file("src/lib.rs", "pub mod x;\n");
+    fixture.create_file("docs/guide.md", "no code here\n");
+    fixture.create_file("big.rs", &format!("fn main() {{}}\n{}", "x".repeat(4096)));
+
+    for threads in [Some(1), None] {
+        let mut visualizer = TreeVisualizer::new(Config {
+            grep: Some("fn main".to_string()),
+            max_size: Some(1024),
+            threads,
+            ..Config::default()
+        });
+        let walk = visualizer.walk(fixture.path()).unwrap();
+        let paths: Vec<_> = walk
+            .map(|entry| {
+                let relative = entry.info.path.strip_prefix(fixture.path()).unwrap();
+                relative.to_path_buf()
+            })
+            .collect();
+        assert_eq!(paths, [Path::new("src"), Path::new("src/main.rs")]);
+    }
+}
@@ -0,0 +1,152 @@
+use denarborea::{ChecksumAlgorithm, Config, Manifest};
+use std::path::PathBuf;
+
+use crate::common::test_helpers::TestFixture;
+
+fn create_fixture() -> TestFixture {
+    let fixture = TestFixture::new();
+    fixture.create_file("a.txt", "alpha");
+    fixture.create_file("sub/b.txt", "beta");
+    fixture
+}
+
+fn checksum_config() -> Config {
+    Config {
+        show_checksum: true,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_snapshot_entries() {
+    let fixture = create_fixture();
+    let manifest = Manifest::capture(&checksum_config(), fixture.path()).unwrap();
+
+    let paths: Vec<_> = manifest.entries.iter().map(|entry| &entry.path).collect();
+    assert_eq!(
+        paths,
+        vec![
+            &PathBuf::from("a.txt"),
+            &PathBuf::from("sub"),
+            &PathBuf::from("sub/b.txt")
+        ]
+    );
+    assert_eq!(manifest.entries[0].size, Some(5));
+    assert!(manifest.entries[0].checksum.is_some());
+    assert!(manifest.entries[1].size.is_none());
+}
+
+#[test]
+fn test_snapshot_round_trip_verifies_clean() {
+    let fixture = create_fixture();
+    let manifest_path = fixture.path().join("manifest.json");
+    let config = Config {
+        exclude_patterns: vec!["manifest.json".to_string()],
+        ..checksum_config()
+    };
+
+    Manifest::capture(&config, fixture.path())
+        .unwrap()
+        .save(&manifest_path)
+        .unwrap();
+    let verification = Manifest::load(&manifest_path)
+        .unwrap()
+        .verify(&config, fixture.path())
+        .unwrap();
+
+    assert!(verification.is_clean());
+    assert_eq!(verification.checked, 3);
+}
+
+#[test]
+fn test_verify_reports_drift() {
+    let fixture = create_fixture();
+    let manifest = Manifest::capture(&checksum_config(), fixture.path()).unwrap();
+
+    std::fs::remove_file(fixture.path().join("sub/b.txt")).unwrap();
+    fixture.create_file("c.txt", "gamma");
+    let modified = fixture.path().join("a.txt");
+    let mtime = std::fs::metadata(&modified).unwrap().modified().unwrap();
+    std::fs::write(&modified, "ALPHA").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&modified)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+
+    let verification = manifest.verify(&Config::default(), fixture.path()).unwrap();
+    assert!(!verification.is_clean());
+    assert_eq!(verification.missing, vec![PathBuf::from("sub/b.txt")]);
+    assert_eq!(verification.extra, vec![PathBuf::from("c.txt")]);
+    assert_eq!(
+        verification.modified,
+        vec![(PathBuf::from("a.txt"), vec!["checksum"])]
+    );
+}
+
+#[test]
+fn test_manifest_version_is_checked() {
+    let fixture = create_fixture();
+    let path = fixture.create_file(
+        "manifest.json",
+        r#"{"version": 99, "root": ".", "created": "2026-01-01T00:00:00Z", "entries": []}"#,
+    );
+
+    let err = Manifest::load(&path).unwrap_err();
+    assert_eq!(err.exit_code(), 6);
+    assert!(err.to_string().contains("unsupported version 99"));
+}
+
+#[test]
+fn test_verify_uses_manifest_algorithm() {
+    let fixture = create_fixture();
+    let config = Config {
+        checksum_algorithm: ChecksumAlgorithm::Blake3,
+        ..checksum_config()
+    };
+    let manifest = Manifest::capture(&config, fixture.path()).unwrap();
+    assert_eq!(manifest.checksum_algorithm, ChecksumAlgorithm::Blake3);
+    assert_eq!(manifest.entries[0].checksum.as_ref().unwrap().len(), 64);
+
+    // verifying with a different --checksum algorithm still compares BLAKE3
+    let verify_config = Config {
+        checksum_algorithm: ChecksumAlgorithm::Sha1,
+        ..checksum_config()
+    };
+    assert!(manifest
+        .verify(&verify_config, fixture.path())
+        .unwrap()
+        .is_clean());
+}
+
+#[test]
+fn test_snapshot_skips_hidden_directories() {
+    let fixture = create_fixture();
+    fixture.create_file(".git/HEAD", "ref: refs/heads/main\n");
+    fixture.create_file(".git/logs/HEAD", "0000 1111 commit\n");
+
+    let manifest = Manifest::capture(&checksum_config(), fixture.path()).unwrap();
+    assert_eq!(manifest.entries.len(), 3);
+    assert!(manifest
+        .entries
+        .iter()
+        .all(|entry| !entry.path.starts_with(".git")));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_fails_on_unreadable_checksum() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = create_fixture();
+    let locked = fixture.create_file("locked.txt", "secret");
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+    // permissions do not stop root
+    if std::fs::File::open(&locked).is_ok() {
+        return;
+    }
+
+    assert!(Manifest::capture(&checksum_config(), fixture.path()).is_err());
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o644)).unwrap();
+}
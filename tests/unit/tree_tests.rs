@@ -0,0 +1,273 @@
+use denarborea::{Config, TreeStats, TreeVisualizer};
+use std::path::{Path, PathBuf};
+
+use crate::common::test_helpers::TestFixture;
+
+fn create_fixture() -> TestFixture {
+    let fixture = TestFixture::new();
+    for dir in ["alpha", "beta/inner", "gamma.d", ".hidden"] {
+        for i in 0..5 {
+            fixture.create_file(&format!("{}/file_{}.txt", dir, i), "content");
+        }
+    }
+    fixture.create_file("alpha.txt", "top level");
+    fixture.create_file("beta/inner/data.rs", "fn main() {}");
+    fixture
+}
+
+fn collect_paths(config: Config, fixture: &TestFixture) -> Vec<PathBuf> {
+    let mut visualizer = TreeVisualizer::new(config);
+    visualizer
+        .collect_entries(fixture.path())
+        .unwrap()
+        .into_iter()
+        .map(|info| info.path)
+        .collect()
+}
+
+#[test]
+fn test_parallel_walk_matches_serial() {
+    let fixture = create_fixture();
+
+    for show_hidden in [false, true] {
+        let serial = Config {
+            threads: Some(1),
+            show_hidden,
+            ..Config::default()
+        };
+        let parallel = Config {
+            threads: Some(4),
+            show_hidden,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            collect_paths(serial, &fixture),
+            collect_paths(parallel, &fixture)
+        );
+    }
+}
+
+#[test]
+fn test_parallel_walk_limit_matches_serial() {
+    let fixture = create_fixture();
+
+    for limit in [1, 3, 7, 12] {
+        let serial = Config {
+            threads: Some(1),
+            limit: Some(limit),
+            ..Config::default()
+        };
+        let parallel = Config {
+            threads: Some(4),
+            limit: Some(limit),
+            ..Config::default()
+        };
+
+        let serial_paths = collect_paths(serial, &fixture);
+        assert_eq!(serial_paths.len(), limit);
+        assert_eq!(serial_paths, collect_paths(parallel, &fixture));
+    }
+}
+
+#[test]
+fn test_parallel_walk_applies_filters() {
+    let fixture = create_fixture();
+    let config = Config {
+        filter_extension: Some("rs".to_string()),
+        files_only: true,
+        ..Config::default()
+    };
+
+    assert_eq!(
+        collect_paths(config, &fixture),
+        vec![fixture.path().join("beta/inner/data.rs")]
+    );
+}
+
+#[test]
+fn test_counts_only_collected_when_shown() {
+    let fixture = create_fixture();
+
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let entries = visualizer.collect_entries(fixture.path()).unwrap();
+    assert!(entries.iter().all(|info| info.file_count.is_none()));
+
+    let mut visualizer = TreeVisualizer::new(Config {
+        show_count: true,
+        ..Config::default()
+    });
+    let entries = visualizer.collect_entries(fixture.path()).unwrap();
+    let alpha = entries.iter().find(|info| info.name == "alpha").unwrap();
+    assert_eq!(alpha.file_count, Some(5));
+}
+
+#[test]
+fn test_walk_yields_depth_and_ancestry() {
+    let fixture = TestFixture::new();
+    fixture.create_file("a/one.txt", "1");
+    fixture.create_file("a/two.txt", "2");
+    fixture.create_file("b.txt", "b");
+
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    let entries: Vec<_> = visualizer.walk(fixture.path()).unwrap().collect();
+
+    let summary: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            (
+                entry.info.name.as_str(),
+                entry.depth,
+                entry.last_flags.clone(),
+                entry.parent.clone(),
+            )
+        })
+        .collect();
+
+    let root = fixture.path().to_path_buf();
+    assert_eq!(
+        summary,
+        vec![
+            ("a", 1, vec![false], root.clone()),
+            ("one.txt", 2, vec![false, false], root.join("a")),
+            ("two.txt", 2, vec![false, true], root.join("a")),
+            ("b.txt", 1, vec![true], root.clone()),
+        ]
+    );
+    assert!(entries[3].is_last());
+}
+
+#[test]
+fn test_walk_matches_tree() {
+    let fixture = create_fixture();
+    let config = Config {
+        filter_extension: Some("rs".to_string()),
+        ..Config::default()
+    };
+
+    let mut visualizer = TreeVisualizer::new(config);
+    let tree = visualizer.tree(fixture.path()).unwrap();
+    assert_eq!(tree.nodes.len(), 1);
+    assert_eq!(tree.nodes[0].info.name, "beta");
+
+    let paths: Vec<_> = tree.into_iter().map(|entry| entry.info.path).collect();
+    assert_eq!(
+        paths,
+        vec![
+            fixture.path().join("beta"),
+            fixture.path().join("beta/inner"),
+            fixture.path().join("beta/inner/data.rs"),
+        ]
+    );
+}
+
+#[test]
+fn test_walk_missing_root() {
+    let fixture = TestFixture::new();
+    let mut visualizer = TreeVisualizer::new(Config::default());
+    assert!(visualizer.walk(&fixture.path().join("missing")).is_err());
+}
+
+#[cfg(unix)]
+fn create_broken_link_fixture() -> TestFixture {
+    let fixture = TestFixture::new();
+    fixture.create_file("sub/ok.txt", "fine");
+    std::os::unix::fs::symlink("/nonexistent/target", fixture.path().join("sub/broken")).unwrap();
+    fixture
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unreadable_entries_are_reported() {
+    let fixture = create_broken_link_fixture();
+    let broken = fixture.path().join("sub/broken");
+
+    for threads in [1, 4] {
+        let config = Config {
+            follow_links: true,
+            threads: Some(threads),
+            ..Config::default()
+        };
+        let mut visualizer = TreeVisualizer::new(config);
+        let entries = visualizer.collect_entries(fixture.path()).unwrap();
+
+        let failed = entries.iter().find(|info| info.path == broken).unwrap();
+        assert_eq!(failed.error.as_deref(), Some("entity not found"));
+        assert!(entries
+            .iter()
+            .any(|info| info.name == "ok.txt" && info.error.is_none()));
+
+        assert_eq!(visualizer.errors().len(), 1);
+        assert_eq!(visualizer.errors()[0].0, broken);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_strict_mode_fails_fast() {
+    let fixture = create_broken_link_fixture();
+
+    for threads in [1, 4] {
+        let config = Config {
+            follow_links: true,
+            strict: true,
+            threads: Some(threads),
+            ..Config::default()
+        };
+        let mut visualizer = TreeVisualizer::new(config);
+        let err = visualizer.collect_entries(fixture.path()).unwrap_err();
+        assert!(err.is_not_found());
+    }
+}
+
+#[test]
+fn test_stats_display_error_count() {
+    let mut stats = TreeStats::new();
+    assert!(!stats.display().contains("Errors"));
+
+    stats.add_error(Path::new("a"), "permission denied");
+    stats.add_error(Path::new("b"), "permission denied");
+    assert!(stats.display().contains("Errors: 2"));
+}
+
+#[test]
+fn test_time_filters_prune_directories() {
+    let fixture = TestFixture::new();
+    let old = fixture.create_file("old/a.txt", "old");
+    fixture.create_file("mixed/new.txt", "new");
+    let mixed_old = fixture.create_file("mixed/old.txt", "old");
+    let past = std::time::SystemTime::now() - std::time::Duration::from_secs(10 * 24 * 60 * 60);
+    for path in [old, mixed_old] {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+    }
+
+    let paths = |config: Config| -> Vec<String> {
+        let mut visualizer = TreeVisualizer::new(config);
+        visualizer
+            .walk(fixture.path())
+            .unwrap()
+            .map(|entry| {
+                let path = entry.info.path.strip_prefix(fixture.path()).unwrap();
+                path.display().to_string()
+            })
+            .collect()
+    };
+
+    let week_ago = Some(chrono::Utc::now() - chrono::Duration::days(7));
+    let newer = Config {
+        newer: week_ago,
+        ..Config::default()
+    };
+    assert_eq!(paths(newer), ["mixed", "mixed/new.txt"]);
+
+    let older = Config {
+        older: week_ago,
+        ..Config::default()
+    };
+    assert_eq!(paths(older), ["mixed", "mixed/old.txt", "old", "old/a.txt"]);
+}
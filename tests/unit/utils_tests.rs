@@ -1,4 +1,9 @@
-use denarborea::utils::{count_files_in_dir, parse_size};
+use denarborea::utils::{
+    calculate_checksum, count_files_in_dir, directory_totals, parse_duration, parse_size,
+    parse_time,
+};
+use denarborea::ChecksumAlgorithm;
+use std::fs;
 use std::path::Path;
 
 use crate::common::test_helpers::TestFixture;
@@ -167,3 +172,112 @@ fn test_count_files_nonexistent_dir() {
     assert_eq!(files, 0);
     assert_eq!(dirs, 0);
 }
+
+#[test]
+fn test_directory_totals_are_cumulative() {
+    let fixture = TestFixture::new();
+    fixture.create_file("top.txt", "12345");
+    fixture.create_file("sub/a.txt", "123");
+    fixture.create_file("sub/nested/b.txt", "1234567");
+
+    let totals = directory_totals(fixture.path(), false);
+
+    assert_eq!(totals[fixture.path()].0, 15);
+    assert_eq!(totals[&fixture.path().join("sub")].0, 10);
+    assert_eq!(totals[&fixture.path().join("sub/nested")].0, 7);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_directory_totals_count_hardlinks_once() {
+    let fixture = TestFixture::new();
+    let original = fixture.create_file("data/original.bin", "0123456789");
+    fs::hard_link(&original, fixture.path().join("data/link.bin")).unwrap();
+
+    let totals = directory_totals(fixture.path(), false);
+
+    assert_eq!(totals[&fixture.path().join("data")].0, 10);
+}
+
+#[test]
+fn test_calculate_checksum_algorithms() {
+    let fixture = TestFixture::new();
+    let path = fixture.create_file("hello.txt", "hello");
+
+    let cases = [
+        (ChecksumAlgorithm::Md5, "5d41402abc4b2a76b9719d911017c592"),
+        (
+            ChecksumAlgorithm::Sha1,
+            "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d",
+        ),
+        (
+            ChecksumAlgorithm::Sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+        ),
+        (
+            ChecksumAlgorithm::Blake3,
+            "ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f",
+        ),
+        (ChecksumAlgorithm::Xxh3, "9555e8555c62dcfd"),
+    ];
+    for (algorithm, expected) in cases {
+        assert_eq!(calculate_checksum(&path, algorithm).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_calculate_checksum_spans_chunks() {
+    let fixture = TestFixture::new();
+    // larger than one read chunk, and not a multiple of it
+    let content = "0123456789".repeat(20_000);
+    let path = fixture.create_file("large.txt", &content);
+
+    assert_eq!(
+        calculate_checksum(&path, ChecksumAlgorithm::Md5).unwrap(),
+        format!("{:x}", md5::compute(content.as_bytes()))
+    );
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("30s").unwrap().as_secs(), 30);
+    assert_eq!(parse_duration("2d").unwrap().as_secs(), 2 * 86400);
+    assert_eq!(parse_duration("1h30m").unwrap().as_secs(), 5400);
+    assert_eq!(parse_duration("1w").unwrap().as_secs(), 604800);
+    for invalid in ["", "5", "h", "2x", "1.5h", "-1d"] {
+        assert!(parse_duration(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_parse_time() {
+    use chrono::{Local, TimeZone, Utc};
+
+    let expected = Local.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(parse_time("2026-01-01").unwrap(), expected);
+    let expected = Local.with_ymd_and_hms(2026, 1, 1, 12, 30, 0).unwrap();
+    assert_eq!(parse_time("2026-01-01 12:30").unwrap(), expected);
+    assert_eq!(parse_time("2026-01-01T12:30:00").unwrap(), expected);
+    assert_eq!(
+        parse_time("2026-01-01T12:00:00Z").unwrap(),
+        Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()
+    );
+
+    let two_days = Utc::now() - parse_time("2d").unwrap();
+    assert!((two_days.num_seconds() - 2 * 86400).abs() < 60);
+
+    assert!(parse_time("yesterday").is_err());
+    assert!(parse_time("2026-13-01").is_err());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_matches_pattern_still_available() {
+    use denarborea::utils::matches_pattern;
+
+    assert!(matches_pattern(Path::new("/src/main.rs"), r"\.rs$"));
+    assert!(!matches_pattern(Path::new("/src/main.py"), r"\.rs$"));
+    // not a valid regex, matched as a glob
+    assert!(matches_pattern(Path::new("/src/main.rs"), "*.rs"));
+    assert!(!matches_pattern(Path::new("/src/main.py"), "*.rs"));
+}
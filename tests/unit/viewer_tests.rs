@@ -346,3 +346,16 @@ fn test_viewer_options_with_limits() {
     assert_eq!(options.strategy, ViewerStrategy::Streaming);
     assert_eq!(options.preview_size, 32 * 1024);
 }
+
+#[test]
+fn test_view_bytes_uses_the_name_for_the_format() {
+    let viewer = FileViewer::new(ViewerFormat::Auto);
+    let path = Path::new("gone/config.json");
+
+    let output = viewer.view_bytes(path, br#"{"key": 1}"#).unwrap();
+    assert!(output.contains("JSON File: gone/config.json"));
+    assert!(output.contains("\"key\": 1"));
+
+    let err = viewer.view_bytes(path, b"{not json").unwrap_err();
+    assert_eq!(err.path(), Some(path));
+}
@@ -1,7 +1,21 @@
 mod common;
 mod unit {
+    mod checksum_cache_tests;
     mod config_tests;
+    mod diff_tests;
     mod display_tests;
+    mod duplicates_tests;
+    mod error_tests;
+    mod git_diff_tests;
+    mod git_tests;
+    mod interactive_tests;
+    mod patterns_tests;
+    mod query_tests;
+    mod revision_tests;
+    mod scan_cache_tests;
+    mod search_tests;
+    mod snapshot_tests;
+    mod tree_tests;
     mod utils_tests;
     mod viewer_tests;
 }